@@ -0,0 +1,22 @@
+// Per-frame data, shared by every program.
+// Mirrors FrameUniforms in src/frame_uniforms.rs.
+
+#define MAX_LIGHTS 16
+
+struct PointLight {
+  vec3 position;
+  float radius;
+  vec3 colour;
+  float intensity;
+};
+
+layout(std140, binding = 0) uniform Frame {
+  mat4 view;
+  mat4 projection;
+  mat4 view_projection;
+  vec3 camera_position;
+  float time;
+  uint light_count;
+  PointLight lights[MAX_LIGHTS];
+}
+frame;
@@ -0,0 +1,20 @@
+// Conversions between sRGB and linear colour spaces.
+// Calcuations as documented by OpenGL.
+
+vec4 srgb_to_linear(vec4 srgb_color) {
+  vec3 srgb = srgb_color.rgb;
+  vec3 selector = ceil(srgb - 0.04045);
+  vec3 less_than_branch = srgb / 12.92;
+  vec3 greater_than_branch = pow((srgb + 0.055) / 1.055, vec3(2.4));
+  return vec4(mix(less_than_branch, greater_than_branch, selector),
+              srgb_color.a);
+}
+
+vec4 linear_to_srgb(vec4 linear_color) {
+  vec3 linear = linear_color.rgb;
+  vec3 selector = ceil(linear - 0.0031308);
+  vec3 less_than_branch = linear * 12.92;
+  vec3 greater_than_branch = pow(linear, vec3(1.0 / 2.4)) * 1.055 - 0.055;
+  return vec4(mix(less_than_branch, greater_than_branch, selector),
+              linear_color.a);
+}
@@ -0,0 +1,12 @@
+#version 430 core
+#include "common/frame.glsl"
+
+layout(location = 0) in vec3 attribute_Position;
+layout(location = 1) in vec3 attribute_Colour;
+
+layout(location = 0) out vec3 vertexColour;
+
+void main() {
+  gl_Position = frame.view_projection * vec4(attribute_Position, 1.0);
+  vertexColour = attribute_Colour;
+}
@@ -3,4 +3,9 @@ layout(location = 0) out vec4 FragColor;
 
 layout(location = 0) in vec3 vertexColour;
 
-void main() { FragColor = vec4(vertexColour, 1.0f); }
+// Replaces the vertex colour by its alpha, for debug render modes.
+uniform vec4 colour_override;
+
+void main() {
+  FragColor = vec4(mix(vertexColour, colour_override.rgb, colour_override.a), 1.0f);
+}
@@ -6,14 +6,7 @@ layout(binding = 1) uniform sampler2D tex;
 
 layout(location = 0) out vec4 out_color;
 
-vec4 linear_to_srgb(vec4 linear_color) {
-  vec3 linear = linear_color.rgb;
-  vec3 selector = ceil(linear - 0.0031308);
-  vec3 less_than_branch = linear * 12.92;
-  vec3 greater_than_branch = pow(linear, vec3(1.0 / 2.4)) * 1.055 - 0.055;
-  return vec4(mix(less_than_branch, greater_than_branch, selector),
-              linear_color.a);
-}
+#include "common/srgb.glsl"
 
 void main() {
   vec4 linear_color = fragment_color * texture(tex, fragment_uv.st);
@@ -10,17 +10,7 @@ layout (location = 0) out vec2 fragment_uv;
 layout (location = 1) out vec4 fragment_color;
 
 // Because imgui only specifies sRGB colors
-vec4 srgb_to_linear(vec4 srgb_color) {
-    // Calcuation as documented by OpenGL
-    vec3 srgb = srgb_color.rgb;
-    vec3 selector = ceil(srgb - 0.04045);
-    vec3 less_than_branch = srgb / 12.92;
-    vec3 greater_than_branch = pow((srgb + 0.055) / 1.055, vec3(2.4));
-    return vec4(
-        mix(less_than_branch, greater_than_branch, selector),
-        srgb_color.a
-    );
-}
+#include "common/srgb.glsl"
 
 void main() {
     fragment_uv = uv;
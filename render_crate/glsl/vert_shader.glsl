@@ -1,11 +1,15 @@
 #version 430 core
+#include "common/frame.glsl"
 
 layout(location = 0) in vec3 attribute_Position;
 layout(location = 1) in vec3 attribute_Colour;
 
 layout(location = 0) out vec3 vertexColour;
 
+// Moves vertices from model space to world space.
+uniform mat4 model;
+
 void main() {
-  gl_Position = vec4(attribute_Position.xyz, 1.0);
+  gl_Position = frame.view_projection * model * vec4(attribute_Position.xyz, 1.0);
   vertexColour = attribute_Colour;
-}
\ No newline at end of file
+}
@@ -0,0 +1,496 @@
+//! Immediate-mode debug drawing.
+//!
+//! Call these from anywhere on the render thread during a frame:
+//! ```no_run
+//! debug_draw::line(a, b, colour::RED);
+//! debug_draw::aabb(min, max, colour::GREEN).seconds(2.0).xray();
+//! debug_draw::text(origin, "spawn", colour::WHITE);
+//! ```
+//! Shapes are queued, then drawn after the world by [`DebugDrawPass`], and text
+//! is drawn into the imgui overlay by [`draw_queued_text`]. Call [`end_frame`]
+//! once everything has been drawn to throw away shapes that have expired.
+use std::{
+    cell::RefCell,
+    ptr::null,
+    time::{Duration, Instant},
+};
+
+use glm::{Vec3, Vec4};
+
+use crate::{
+    Camera, InputParams, RenderError,
+    error::{check_gl, create_object},
+    gl_wrappers::{Preprocessor, Program, Shader, ShaderType},
+    render_vec::RenderVec,
+    stats,
+};
+
+/// Common colours for debug drawing.
+pub mod colour {
+    use glm::Vector3;
+
+    /// Pure red.
+    pub const RED: Vector3<f32> = Vector3 { x: 1., y: 0., z: 0. };
+    /// Pure green.
+    pub const GREEN: Vector3<f32> = Vector3 { x: 0., y: 1., z: 0. };
+    /// Pure blue.
+    pub const BLUE: Vector3<f32> = Vector3 { x: 0., y: 0., z: 1. };
+    /// Yellow.
+    pub const YELLOW: Vector3<f32> = Vector3 { x: 1., y: 1., z: 0. };
+    /// Cyan.
+    pub const CYAN: Vector3<f32> = Vector3 { x: 0., y: 1., z: 1. };
+    /// Magenta.
+    pub const MAGENTA: Vector3<f32> = Vector3 { x: 1., y: 0., z: 1. };
+    /// White.
+    pub const WHITE: Vector3<f32> = Vector3 { x: 1., y: 1., z: 1. };
+}
+
+/// Number of line segments used to approximate a circle.
+const CIRCLE_SEGMENTS: usize = 24;
+/// The longest a shape can be kept for. Longer or infinite times are cut down to this.
+const MAX_LIFETIME: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// A queued set of line segments.
+struct QueuedLines {
+    /// Pairs of points, one line between each pair.
+    segments: Vec<[Vec3; 2]>,
+    /// Colour of every line.
+    colour: Vec3,
+    /// Whether the lines are hidden behind world geometry.
+    depth_test: bool,
+    /// When this should stop being drawn. [`None`] means after one frame.
+    expires_at: Option<Instant>,
+}
+
+/// A queued piece of text.
+struct QueuedText {
+    /// World space point the text is anchored to.
+    position: Vec3,
+    /// The text itself.
+    text: String,
+    /// Colour of the text.
+    colour: Vec3,
+    /// When this should stop being drawn. [`None`] means after one frame.
+    expires_at: Option<Instant>,
+}
+
+/// Everything queued for drawing this frame.
+#[derive(Default)]
+struct Queue {
+    /// Queued line shapes.
+    lines: Vec<QueuedLines>,
+    /// Queued text.
+    texts: Vec<QueuedText>,
+}
+
+thread_local! {
+    /// The queue every function in this module pushes into.
+    static QUEUE: RefCell<Queue> = RefCell::new(Queue::default());
+}
+
+/// What a [`DebugShape`] will queue when dropped.
+enum Pending {
+    /// A set of lines.
+    Lines(QueuedLines),
+    /// Some text.
+    Text(QueuedText),
+}
+
+/// A shape about to be queued. It's queued when dropped, so options can be
+/// chained onto the call that created it.
+pub struct DebugShape {
+    /// The shape. Only [`None`] after being queued.
+    pending: Option<Pending>,
+}
+
+impl DebugShape {
+    /// Wrap lines to be queued.
+    fn lines(segments: Vec<[Vec3; 2]>, colour: Vec3) -> Self {
+        Self {
+            pending: Some(Pending::Lines(QueuedLines {
+                segments,
+                colour,
+                depth_test: true,
+                expires_at: None,
+            })),
+        }
+    }
+    /// Keep drawing this shape for `seconds`, instead of only this frame.
+    ///
+    /// Anything over a day, including [`f32::INFINITY`], is kept for a day.
+    pub fn seconds(mut self, seconds: f32) -> Self {
+        let lifetime = Duration::try_from_secs_f32(seconds.max(0.)).unwrap_or(MAX_LIFETIME);
+        let expires_at = Some(Instant::now() + lifetime.min(MAX_LIFETIME));
+        match &mut self.pending {
+            Some(Pending::Lines(lines)) => lines.expires_at = expires_at,
+            Some(Pending::Text(text)) => text.expires_at = expires_at,
+            None => (),
+        }
+        self
+    }
+    /// Draw this shape on top of the world, ignoring depth.
+    ///
+    /// Text is always drawn on top, so this does nothing for it.
+    pub fn xray(mut self) -> Self {
+        if let Some(Pending::Lines(lines)) = &mut self.pending {
+            lines.depth_test = false;
+        }
+        self
+    }
+}
+
+impl Drop for DebugShape {
+    fn drop(&mut self) {
+        let Some(pending) = self.pending.take() else {
+            return;
+        };
+        // if the thread is shutting down there's nothing left to draw to anyway
+        let _ = QUEUE.try_with(|queue| {
+            let mut queue = queue.borrow_mut();
+            match pending {
+                Pending::Lines(lines) => queue.lines.push(lines),
+                Pending::Text(text) => queue.texts.push(text),
+            }
+        });
+    }
+}
+
+/// Draw a line from `a` to `b`.
+pub fn line(a: Vec3, b: Vec3, colour: Vec3) -> DebugShape {
+    DebugShape::lines(vec![[a, b]], colour)
+}
+
+/// Draw an axis-aligned bounding box between two corners.
+pub fn aabb(min: Vec3, max: Vec3, colour: Vec3) -> DebugShape {
+    let corner = |x: bool, y: bool, z: bool| {
+        glm::vec3(
+            if x { max.x } else { min.x },
+            if y { max.y } else { min.y },
+            if z { max.z } else { min.z },
+        )
+    };
+    let mut segments = Vec::with_capacity(12);
+    for a in [false, true] {
+        for b in [false, true] {
+            segments.push([corner(false, a, b), corner(true, a, b)]);
+            segments.push([corner(a, false, b), corner(a, true, b)]);
+            segments.push([corner(a, b, false), corner(a, b, true)]);
+        }
+    }
+    DebugShape::lines(segments, colour)
+}
+
+/// Draw a sphere, as a circle around each axis.
+pub fn sphere(center: Vec3, radius: f32, colour: Vec3) -> DebugShape {
+    let mut segments = Vec::with_capacity(CIRCLE_SEGMENTS * 3);
+    let point = |angle: f32, axis: usize| {
+        let (sin, cos) = (angle.sin() * radius, angle.cos() * radius);
+        center
+            + match axis {
+                0 => glm::vec3(0., cos, sin),
+                1 => glm::vec3(cos, 0., sin),
+                _ => glm::vec3(cos, sin, 0.),
+            }
+    };
+    for axis in 0..3 {
+        for i in 0..CIRCLE_SEGMENTS {
+            let a = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            let b = (i + 1) as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+            segments.push([point(a, axis), point(b, axis)]);
+        }
+    }
+    DebugShape::lines(segments, colour)
+}
+
+/// Draw an arrow from `from`, with its head at `to`.
+pub fn arrow(from: Vec3, to: Vec3, colour: Vec3) -> DebugShape {
+    let mut segments = vec![[from, to]];
+    let length = glm::length(to - from);
+    if length > f32::EPSILON {
+        let dir = (to - from) / length;
+        // any vector not parallel to dir works to build the head's basis
+        let helper = if glm::abs(dir.y) < 0.9 {
+            glm::vec3(0., 1., 0.)
+        } else {
+            glm::vec3(1., 0., 0.)
+        };
+        let side = glm::normalize(glm::cross(dir, helper));
+        let up = glm::cross(side, dir);
+        let head_length = length * 0.2;
+        let base = to - dir * head_length;
+        for offset in [side, -side, up, -up] {
+            segments.push([to, base + offset * (head_length * 0.5)]);
+        }
+    }
+    DebugShape::lines(segments, colour)
+}
+
+/// Draw the view frustum of `camera`.
+pub fn frustum(camera: &Camera, colour: Vec3) -> DebugShape {
+    let inverse = glm::inverse(&(camera.projection_matrix() * camera.view_matrix()));
+    let corner = |x: f32, y: f32, z: f32| {
+        let point: Vec4 = inverse * glm::vec4(x, y, z, 1.);
+        glm::vec3(point.x, point.y, point.z) / point.w
+    };
+    let mut segments = Vec::with_capacity(12);
+    for z in [-1., 1.] {
+        let quad = [
+            corner(-1., -1., z),
+            corner(1., -1., z),
+            corner(1., 1., z),
+            corner(-1., 1., z),
+        ];
+        for i in 0..4 {
+            segments.push([quad[i], quad[(i + 1) % 4]]);
+        }
+    }
+    for (x, y) in [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)] {
+        segments.push([corner(x, y, -1.), corner(x, y, 1.)]);
+    }
+    DebugShape::lines(segments, colour)
+}
+
+/// Draw `text` on screen, over the world space point `position`.
+pub fn text(position: Vec3, text: impl Into<String>, colour: Vec3) -> DebugShape {
+    DebugShape {
+        pending: Some(Pending::Text(QueuedText {
+            position,
+            text: text.into(),
+            colour,
+            expires_at: None,
+        })),
+    }
+}
+
+/// Draw all queued text into the imgui foreground, as seen from `camera`.
+pub fn draw_queued_text(ui: &imgui::Ui, camera: &Camera) {
+    let view_projection = camera.projection_matrix() * camera.view_matrix();
+    let [width, height] = ui.io().display_size;
+    let draw_list = ui.get_foreground_draw_list();
+    QUEUE.with_borrow(|queue| {
+        for text in queue.texts.iter() {
+            let p = text.position;
+            let clip: Vec4 = view_projection * glm::vec4(p.x, p.y, p.z, 1.);
+            // behind the camera
+            if clip.w <= 0. {
+                continue;
+            }
+            let ndc = glm::vec2(clip.x / clip.w, clip.y / clip.w);
+            let screen = [(ndc.x + 1.) * 0.5 * width, (1. - ndc.y) * 0.5 * height];
+            let colour = [text.colour.x, text.colour.y, text.colour.z, 1.];
+            draw_list.add_text(screen, colour, &text.text);
+        }
+    });
+}
+
+/// Throw away everything that has expired. Call once per frame, after drawing.
+pub fn end_frame() {
+    let now = Instant::now();
+    let alive = |expires_at: Option<Instant>| expires_at.is_some_and(|time| time > now);
+    QUEUE.with_borrow_mut(|queue| {
+        queue.lines.retain(|lines| alive(lines.expires_at));
+        queue.texts.retain(|text| alive(text.expires_at));
+    });
+}
+
+/// GL state for drawing queued debug lines.
+pub struct DebugDrawPass {
+    /// Vertex array describing [`InputParams`].
+    vao: gl::types::GLuint,
+    /// Vertex buffer the lines are streamed into.
+    vbo: gl::types::GLuint,
+    /// Program drawing coloured lines with the frame's camera.
+    program: Program,
+    /// Reused between frames to avoid allocating.
+    vertices: RenderVec<InputParams>,
+}
+
+impl DebugDrawPass {
+    /// Create the buffers and program for drawing debug lines.
+    ///
+    /// # Errors
+    /// Errors if the debug draw shaders fail to build, or OpenGL can't create the buffers.
+    pub fn new() -> Result<Self, RenderError> {
+        let preprocessor = Preprocessor::new();
+        let vert_shader = Shader::from_processed(
+            ShaderType::Vertex,
+            preprocessor.process("debug_draw/vert.glsl")?,
+        )
+        .compile()?;
+        let frag_shader = Shader::from_processed(
+            ShaderType::Fragment,
+            preprocessor.process("debug_draw/frag.glsl")?,
+        )
+        .compile()?;
+        let program = crate::construct_program!(vert_shader, frag_shader;)?;
+        if cfg!(debug_assertions) {
+            program.validate_layout::<InputParams>()?;
+        }
+
+        let vao = unsafe { create_object("vertex array", gl::CreateVertexArrays) }?;
+        let vbo = unsafe { create_object("buffer", gl::CreateBuffers) }?;
+        unsafe {
+            gl::BindVertexArray(vao);
+            gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
+            let stride = (6 * size_of::<f32>()).try_into().unwrap();
+            gl::VertexAttribPointer(0, 3, gl::FLOAT, gl::FALSE, stride, null());
+            gl::EnableVertexAttribArray(0);
+            gl::VertexAttribPointer(
+                1,
+                3,
+                gl::FLOAT,
+                gl::FALSE,
+                stride,
+                (3 * size_of::<f32>()) as *const _,
+            );
+            gl::EnableVertexAttribArray(1);
+            gl::BindVertexArray(0);
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+        check_gl("DebugDrawPass::new")?;
+
+        Ok(Self {
+            vao,
+            vbo,
+            program,
+            vertices: RenderVec::new(),
+        })
+    }
+
+    /// Draw every queued line. Expects the frame uniforms to be up to date.
+    ///
+    /// # Errors
+    /// Errors if OpenGL reports an error while drawing.
+    pub fn render(&mut self) -> Result<(), RenderError> {
+        // depth tested lines first, then the ones drawn on top of everything
+        for depth_test in [true, false] {
+            self.vertices.clear();
+            QUEUE.with_borrow(|queue| {
+                for lines in queue.lines.iter().filter(|l| l.depth_test == depth_test) {
+                    for [a, b] in lines.segments.iter() {
+                        self.vertices.push(InputParams {
+                            position: *a,
+                            color: lines.colour,
+                        });
+                        self.vertices.push(InputParams {
+                            position: *b,
+                            color: lines.colour,
+                        });
+                    }
+                }
+            });
+            if self.vertices.gl_len() == 0 {
+                continue;
+            }
+            unsafe {
+                if depth_test {
+                    gl::Enable(gl::DEPTH_TEST);
+                } else {
+                    gl::Disable(gl::DEPTH_TEST);
+                }
+                gl::UseProgram(self.program.id());
+                stats::record_program_bind();
+                gl::BindVertexArray(self.vao);
+                gl::NamedBufferData(
+                    self.vbo,
+                    self.vertices.gl_byte_size(),
+                    self.vertices.gl_data(),
+                    gl::STREAM_DRAW,
+                );
+                stats::record_upload(self.vertices.gl_byte_size().unsigned_abs());
+                gl::DrawArrays(gl::LINES, 0, self.vertices.gl_len());
+                stats::record_draw(gl::LINES, self.vertices.gl_len().unsigned_abs() as usize);
+                gl::BindVertexArray(0);
+            }
+        }
+        unsafe { gl::Disable(gl::DEPTH_TEST) };
+        check_gl("debug draw")
+    }
+}
+
+impl Drop for DebugDrawPass {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.vbo);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Take the segments out of `shape` without queueing it.
+    fn segments(mut shape: DebugShape) -> Vec<[Vec3; 2]> {
+        match shape.pending.take() {
+            Some(Pending::Lines(lines)) => lines.segments,
+            _ => panic!("not a line shape"),
+        }
+    }
+
+    /// When `shape` would stop being drawn, without queueing it.
+    fn expiry(mut shape: DebugShape) -> Option<Instant> {
+        match shape.pending.take() {
+            Some(Pending::Lines(lines)) => lines.expires_at,
+            Some(Pending::Text(text)) => text.expires_at,
+            None => None,
+        }
+    }
+
+    #[test]
+    fn aabb_joins_its_corners() {
+        let (min, max) = (glm::vec3(-1., -2., -3.), glm::vec3(1., 2., 3.));
+        let segments = segments(aabb(min, max, colour::RED));
+        assert_eq!(segments.len(), 12);
+        for [a, b] in &segments {
+            // every edge runs along exactly one axis
+            let differences = (0..3).filter(|&axis| a[axis] != b[axis]).count();
+            assert_eq!(differences, 1);
+        }
+        for corner in 0..8 {
+            let pick = |bit: usize, axis: usize| if corner & bit == 0 { min[axis] } else { max[axis] };
+            let corner = glm::vec3(pick(1, 0), pick(2, 1), pick(4, 2));
+            // each corner has an edge for each axis
+            let edges = segments.iter().flatten().filter(|&&point| point == corner).count();
+            assert_eq!(edges, 3);
+        }
+    }
+
+    #[test]
+    fn sphere_segments() {
+        let center = glm::vec3(1., 2., 3.);
+        let segments = segments(sphere(center, 2., colour::GREEN));
+        assert_eq!(segments.len(), CIRCLE_SEGMENTS * 3);
+        for point in segments.iter().flatten() {
+            assert!((glm::length(*point - center) - 2.).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn arrow_segments() {
+        let (from, to) = (glm::vec3(0., 0., 0.), glm::vec3(0., 0., 5.));
+        let segments = segments(arrow(from, to, colour::BLUE));
+        assert_eq!(segments.len(), 5);
+        assert_eq!(segments[0], [from, to]);
+        assert!(segments[1..].iter().all(|[start, _]| *start == to));
+
+        // no length, so no head to point anywhere
+        assert_eq!(self::segments(arrow(to, to, colour::BLUE)).len(), 1);
+    }
+
+    #[test]
+    fn lifetimes() {
+        let now = Instant::now();
+        assert_eq!(expiry(line(glm::vec3(0., 0., 0.), glm::vec3(1., 1., 1.), colour::RED)), None);
+        for seconds in [-1., f32::NAN, 0.] {
+            let expires_at = expiry(text(glm::vec3(0., 0., 0.), "", colour::RED).seconds(seconds));
+            assert!(expires_at.unwrap() < now + Duration::from_secs(1));
+        }
+        for seconds in [f32::INFINITY, f32::MAX, 1e12] {
+            let expires_at = expiry(text(glm::vec3(0., 0., 0.), "", colour::RED).seconds(seconds));
+            assert!(expires_at.unwrap() <= Instant::now() + MAX_LIFETIME);
+            assert!(expires_at.unwrap() > now + MAX_LIFETIME / 2);
+        }
+    }
+}
@@ -0,0 +1,242 @@
+//! Exports [`RenderError`], and [`check_gl`] for turning `glGetError` into one.
+use std::{
+    error::Error,
+    fmt::{self, Display},
+};
+
+use crate::gl_wrappers::{PreprocessError, ShaderError};
+
+/// Most errors [`drain_gl_errors`] will collect before giving up.
+/// A lost context can keep reporting errors forever.
+pub const MAX_DRAINED_ERRORS: usize = 16;
+
+/// An error code returned by `glGetError`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GlErrorCode {
+    /// `GL_INVALID_ENUM`: an enum argument was out of range.
+    InvalidEnum,
+    /// `GL_INVALID_VALUE`: a numeric argument was out of range.
+    InvalidValue,
+    /// `GL_INVALID_OPERATION`: the call isn't allowed in the current state.
+    InvalidOperation,
+    /// `GL_STACK_OVERFLOW`: a push would overflow a stack.
+    StackOverflow,
+    /// `GL_STACK_UNDERFLOW`: a pop would underflow a stack.
+    StackUnderflow,
+    /// `GL_OUT_OF_MEMORY`: there isn't enough memory left to run the call.
+    /// The state of the context is undefined afterwards.
+    OutOfMemory,
+    /// `GL_INVALID_FRAMEBUFFER_OPERATION`: the framebuffer isn't complete.
+    InvalidFramebufferOperation,
+    /// `GL_CONTEXT_LOST`: the context was lost, e.g. by a graphics card reset.
+    ContextLost,
+    /// A code this doesn't know about.
+    Unknown(gl::types::GLenum),
+}
+
+impl GlErrorCode {
+    /// Decode an error returned by `glGetError`, or [`None`] for `GL_NO_ERROR`.
+    pub fn from_gl(code: gl::types::GLenum) -> Option<Self> {
+        Some(match code {
+            gl::NO_ERROR => return None,
+            gl::INVALID_ENUM => Self::InvalidEnum,
+            gl::INVALID_VALUE => Self::InvalidValue,
+            gl::INVALID_OPERATION => Self::InvalidOperation,
+            gl::STACK_OVERFLOW => Self::StackOverflow,
+            gl::STACK_UNDERFLOW => Self::StackUnderflow,
+            gl::OUT_OF_MEMORY => Self::OutOfMemory,
+            gl::INVALID_FRAMEBUFFER_OPERATION => Self::InvalidFramebufferOperation,
+            gl::CONTEXT_LOST => Self::ContextLost,
+            unknown => Self::Unknown(unknown),
+        })
+    }
+}
+
+impl Display for GlErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidEnum => f.write_str("GL_INVALID_ENUM"),
+            Self::InvalidValue => f.write_str("GL_INVALID_VALUE"),
+            Self::InvalidOperation => f.write_str("GL_INVALID_OPERATION"),
+            Self::StackOverflow => f.write_str("GL_STACK_OVERFLOW"),
+            Self::StackUnderflow => f.write_str("GL_STACK_UNDERFLOW"),
+            Self::OutOfMemory => f.write_str("GL_OUT_OF_MEMORY"),
+            Self::InvalidFramebufferOperation => f.write_str("GL_INVALID_FRAMEBUFFER_OPERATION"),
+            Self::ContextLost => f.write_str("GL_CONTEXT_LOST"),
+            Self::Unknown(code) => write!(f, "unknown GL error {code:#x}"),
+        }
+    }
+}
+
+/// Errors from rendering, and from creating what's needed to render.
+#[derive(Debug)]
+pub enum RenderError {
+    /// OpenGL reported errors after a call.
+    Gl {
+        /// The call that was checked, e.g. `glDrawArrays`.
+        /// Errors from earlier, unchecked calls end up here too.
+        call: &'static str,
+        /// Every error that was pending, oldest first.
+        codes: Vec<GlErrorCode>,
+    },
+    /// A shader failed to preprocess, compile, or link, or doesn't match what uses it.
+    Shader(ShaderError),
+    /// OpenGL couldn't create an object. Carries what kind of object, e.g. "buffer".
+    CreateObject(&'static str),
+    /// An id doesn't name a usable object of the expected kind.
+    InvalidObject {
+        /// What kind of object was expected, e.g. "shader".
+        kind: &'static str,
+        /// The id that was given.
+        id: gl::types::GLuint,
+    },
+    /// A texture couldn't be registered with ImGui.
+    RegisterTexture,
+    /// Something that can only be created once per process was created again.
+    /// Carries its name.
+    AlreadyInitialized(&'static str),
+}
+
+impl From<ShaderError> for RenderError {
+    fn from(value: ShaderError) -> Self {
+        Self::Shader(value)
+    }
+}
+
+impl From<PreprocessError> for RenderError {
+    fn from(value: PreprocessError) -> Self {
+        Self::Shader(value.into())
+    }
+}
+
+impl Error for RenderError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Shader(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Gl { call, codes } => {
+                write!(f, "OpenGL error after {call}: ")?;
+                for (i, code) in codes.iter().enumerate() {
+                    if i != 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{code}")?;
+                }
+                Ok(())
+            }
+            Self::Shader(err) => write!(f, "{err}"),
+            Self::CreateObject(kind) => write!(f, "Failed to create {kind} object"),
+            Self::InvalidObject { kind, id } => write!(f, "{id} is not a valid {kind} object"),
+            Self::RegisterTexture => f.write_str("Failed to register texture with ImGui"),
+            Self::AlreadyInitialized(what) => {
+                write!(f, "Cannot initialize {what} more than once")
+            }
+        }
+    }
+}
+
+/// Take every pending error off OpenGL's error queue, oldest first,
+/// stopping after [`MAX_DRAINED_ERRORS`].
+pub fn drain_gl_errors() -> Vec<GlErrorCode> {
+    let mut codes = vec![];
+    while codes.len() < MAX_DRAINED_ERRORS {
+        // SAFETY: glGetError has no preconditions beyond a current context.
+        let Some(code) = GlErrorCode::from_gl(unsafe { gl::GetError() }) else {
+            break;
+        };
+        codes.push(code);
+    }
+    codes
+}
+
+/// Check for errors raised by `call`, or anything since the last check.
+///
+/// # Errors
+/// Errors with [`RenderError::Gl`] if any were pending.
+pub fn check_gl(call: &'static str) -> Result<(), RenderError> {
+    let codes = drain_gl_errors();
+    if codes.is_empty() {
+        Ok(())
+    } else {
+        Err(RenderError::Gl { call, codes })
+    }
+}
+
+/// Create one GL object with a `glGen*`/`glCreate*` style function, erroring if it hands back 0.
+///
+/// # Safety
+/// `generate` must be a GL function that writes `n` new object ids to its pointer.
+pub(crate) unsafe fn create_object(
+    kind: &'static str,
+    generate: unsafe fn(gl::types::GLsizei, *mut gl::types::GLuint),
+) -> Result<gl::types::GLuint, RenderError> {
+    let mut id = 0;
+    unsafe { generate(1, &mut id) };
+    if id == 0 {
+        Err(RenderError::CreateObject(kind))
+    } else {
+        Ok(id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn no_error() {
+        assert_eq!(GlErrorCode::from_gl(gl::NO_ERROR), None);
+    }
+
+    #[test]
+    fn error_codes() {
+        let codes = [
+            (gl::INVALID_ENUM, GlErrorCode::InvalidEnum, "GL_INVALID_ENUM"),
+            (gl::INVALID_VALUE, GlErrorCode::InvalidValue, "GL_INVALID_VALUE"),
+            (gl::INVALID_OPERATION, GlErrorCode::InvalidOperation, "GL_INVALID_OPERATION"),
+            (gl::STACK_OVERFLOW, GlErrorCode::StackOverflow, "GL_STACK_OVERFLOW"),
+            (gl::STACK_UNDERFLOW, GlErrorCode::StackUnderflow, "GL_STACK_UNDERFLOW"),
+            (gl::OUT_OF_MEMORY, GlErrorCode::OutOfMemory, "GL_OUT_OF_MEMORY"),
+            (
+                gl::INVALID_FRAMEBUFFER_OPERATION,
+                GlErrorCode::InvalidFramebufferOperation,
+                "GL_INVALID_FRAMEBUFFER_OPERATION",
+            ),
+            (gl::CONTEXT_LOST, GlErrorCode::ContextLost, "GL_CONTEXT_LOST"),
+            (0x1234, GlErrorCode::Unknown(0x1234), "unknown GL error 0x1234"),
+        ];
+        for (gl_enum, code, name) in codes {
+            assert_eq!(GlErrorCode::from_gl(gl_enum), Some(code));
+            assert_eq!(code.to_string(), name);
+        }
+    }
+
+    #[test]
+    fn gl_error_messages() {
+        let err = RenderError::Gl {
+            call: "glDrawArrays",
+            codes: vec![GlErrorCode::InvalidEnum],
+        };
+        assert_eq!(err.to_string(), "OpenGL error after glDrawArrays: GL_INVALID_ENUM");
+
+        let err = RenderError::Gl {
+            call: "glBufferData",
+            codes: vec![
+                GlErrorCode::InvalidValue,
+                GlErrorCode::OutOfMemory,
+                GlErrorCode::Unknown(0xff),
+            ],
+        };
+        assert_eq!(
+            err.to_string(),
+            "OpenGL error after glBufferData: GL_INVALID_VALUE, GL_OUT_OF_MEMORY, unknown GL error 0xff"
+        );
+    }
+}
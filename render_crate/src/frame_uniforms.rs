@@ -0,0 +1,84 @@
+//! Exports [`FrameUniforms`], the per-frame data shared by every program.
+//!
+//! The GLSL side of this lives in `glsl/common/frame.glsl`.
+//! If you change one, change the other!
+use glm::{Mat4, Vec3};
+
+use crate::{Camera, uniform_block};
+
+/// The uniform buffer binding point [`FrameUniforms`] is attached to.
+pub const FRAME_UNIFORMS_BINDING: gl::types::GLuint = 0;
+
+/// The name of the uniform block in `common/frame.glsl`.
+pub const FRAME_UNIFORMS_BLOCK: &str = "Frame";
+
+/// The maximum number of lights that can be uploaded per frame.
+pub const MAX_LIGHTS: usize = 16;
+
+uniform_block! {
+    /// A point light, as seen by shaders.
+    #[derive(Copy)]
+    pub struct PointLight {
+        /// Position in world space.
+        pub position: Vec3,
+        /// Distance at which this light no longer has any effect.
+        pub radius: f32,
+        /// Linear RGB colour.
+        pub colour: Vec3,
+        /// Brightness multiplier.
+        pub intensity: f32,
+    }
+}
+
+uniform_block! {
+    /// Data that's constant for a whole frame.
+    pub struct FrameUniforms {
+        /// World space to view space.
+        pub view: Mat4,
+        /// View space to clip space.
+        pub projection: Mat4,
+        /// `projection * view`.
+        pub view_projection: Mat4,
+        /// Camera position in world space.
+        pub camera_position: Vec3,
+        /// Seconds since the renderer was created.
+        pub time: f32,
+        /// Number of valid entries in `lights`.
+        pub light_count: u32,
+        /// Lights affecting this frame.
+        pub lights: [PointLight; MAX_LIGHTS],
+    }
+}
+
+impl Default for PointLight {
+    fn default() -> Self {
+        Self {
+            position: glm::to_vec3(0.),
+            radius: 0.,
+            colour: glm::to_vec3(1.),
+            intensity: 0.,
+        }
+    }
+}
+
+impl FrameUniforms {
+    /// Create frame data for `camera`, `time` seconds in, lit by `lights`.
+    ///
+    /// Lights past [`MAX_LIGHTS`] are ignored.
+    pub fn new(camera: &Camera, time: f32, lights: &[PointLight]) -> Self {
+        let view = camera.view_matrix();
+        let projection = camera.projection_matrix();
+        let light_count = lights.len().min(MAX_LIGHTS);
+        let mut all_lights = [PointLight::default(); MAX_LIGHTS];
+        all_lights[..light_count].copy_from_slice(&lights[..light_count]);
+        Self {
+            view,
+            projection,
+            view_projection: projection * view,
+            camera_position: camera.position(),
+            time,
+            light_count: light_count as u32,
+            lights: all_lights,
+        }
+    }
+}
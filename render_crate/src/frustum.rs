@@ -0,0 +1,123 @@
+//! Exports [`Frustum`].
+use glm::{Mat4, Vec3, Vec4};
+use world::Aabb;
+
+/// The six planes bounding what a camera can see.
+///
+/// Planes are stored as `(normal, distance)` with normals pointing into the frustum,
+/// so a point is inside a plane when `dot(normal, point) + distance >= 0`.
+#[derive(Clone, Copy, Debug)]
+pub struct Frustum {
+    /// Left, right, bottom, top, near and far planes.
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extract the planes from a view-projection matrix.
+    ///
+    /// <https://www.gamedevs.org/uploads/fast-extraction-viewing-frustum-planes-from-world-view-projection-matrix.pdf>
+    pub fn from_matrix(view_projection: &Mat4) -> Self {
+        let m = view_projection.as_array();
+        // glm matrices are column major, so row i is element i of each column
+        let row = |i: usize| glm::vec4(m[0][i], m[1][i], m[2][i], m[3][i]);
+        let (x, y, z, w) = (row(0), row(1), row(2), row(3));
+        let planes = [w + x, w - x, w + y, w - y, w + z, w - z].map(|plane| {
+            let length = glm::length(glm::vec3(plane.x, plane.y, plane.z));
+            if length > f32::EPSILON {
+                plane / length
+            } else {
+                plane
+            }
+        });
+        Self { planes }
+    }
+
+    /// Whether any part of `aabb` might be visible.
+    ///
+    /// Conservative: boxes near a corner of the frustum can pass while being outside it.
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // the corner furthest along the plane's normal
+            let corner = glm::vec3(
+                if plane.x >= 0. { aabb.max.x } else { aabb.min.x },
+                if plane.y >= 0. { aabb.max.y } else { aabb.min.y },
+                if plane.z >= 0. { aabb.max.z } else { aabb.min.z },
+            );
+            Self::distance(plane, corner) >= 0.
+        })
+    }
+
+    /// Whether `point` is inside the frustum.
+    pub fn contains_point(&self, point: Vec3) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| Self::distance(plane, point) >= 0.)
+    }
+
+    /// Signed distance from `plane` to `point`.
+    fn distance(plane: &Vec4, point: Vec3) -> f32 {
+        plane.x * point.x + plane.y * point.y + plane.z * point.z + plane.w
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A camera at the origin looking down -z, seeing 45 degrees either side, from 0.1 to 100.
+    fn frustum() -> Frustum {
+        let projection = glm::ext::perspective(90f32.to_radians(), 1., 0.1, 100.);
+        let view = glm::ext::look_at(
+            glm::vec3(0., 0., 0.),
+            glm::vec3(0., 0., -1.),
+            glm::vec3(0., 1., 0.),
+        );
+        Frustum::from_matrix(&(projection * view))
+    }
+
+    /// A cube with half width 1 around `center`.
+    fn cube(x: f32, y: f32, z: f32) -> Aabb {
+        let center = glm::vec3(x, y, z);
+        Aabb::new(center - glm::vec3(1., 1., 1.), center + glm::vec3(1., 1., 1.))
+    }
+
+    #[test]
+    fn points() {
+        let frustum = frustum();
+        assert!(frustum.contains_point(glm::vec3(0., 0., -10.)));
+        assert!(frustum.contains_point(glm::vec3(9., -9., -10.)));
+        // behind the camera, before the near plane and past the far plane
+        assert!(!frustum.contains_point(glm::vec3(0., 0., 10.)));
+        assert!(!frustum.contains_point(glm::vec3(0., 0., -0.05)));
+        assert!(!frustum.contains_point(glm::vec3(0., 0., -101.)));
+        // off each side
+        assert!(!frustum.contains_point(glm::vec3(-11., 0., -10.)));
+        assert!(!frustum.contains_point(glm::vec3(11., 0., -10.)));
+        assert!(!frustum.contains_point(glm::vec3(0., -11., -10.)));
+        assert!(!frustum.contains_point(glm::vec3(0., 11., -10.)));
+    }
+
+    #[test]
+    fn boxes() {
+        let frustum = frustum();
+        assert!(frustum.intersects_aabb(&cube(0., 0., -10.)));
+        assert!(!frustum.intersects_aabb(&cube(0., 0., 10.)));
+        assert!(!frustum.intersects_aabb(&cube(0., 0., -110.)));
+        assert!(!frustum.intersects_aabb(&cube(-15., 0., -10.)));
+        assert!(!frustum.intersects_aabb(&cube(15., 0., -10.)));
+        assert!(!frustum.intersects_aabb(&cube(0., -15., -10.)));
+        assert!(!frustum.intersects_aabb(&cube(0., 15., -10.)));
+    }
+
+    #[test]
+    fn boxes_straddling_a_plane() {
+        let frustum = frustum();
+        // the camera is inside this one
+        assert!(frustum.intersects_aabb(&cube(0., 0., 0.)));
+        assert!(frustum.intersects_aabb(&cube(0., 0., -100.)));
+        assert!(frustum.intersects_aabb(&cube(-10., 0., -10.)));
+        assert!(frustum.intersects_aabb(&cube(10., 0., -10.)));
+        assert!(frustum.intersects_aabb(&cube(0., -10., -10.)));
+        assert!(frustum.intersects_aabb(&cube(0., 10., -10.)));
+    }
+}
@@ -0,0 +1,352 @@
+//! Exports [`ShaderError`] and [`ShaderLogEntry`].
+//!
+//! Drivers report compile errors as free-form text in an info log.
+//! This parses the common formats into file/line/column entries, so that
+//! errors can point at (and print) the line of GLSL that caused them.
+use std::{
+    borrow::Cow,
+    error::Error,
+    fmt::{self, Display},
+};
+
+use super::{PreprocessError, ShaderType, preprocessor::SourceMap};
+
+/// A single message parsed from a driver info log.
+#[derive(Clone, Debug)]
+pub struct ShaderLogEntry {
+    /// The file the message refers to, if it could be determined.
+    pub file: Option<String>,
+    /// The 1-based line in `file` the message refers to.
+    pub line: Option<usize>,
+    /// The 1-based column the message refers to, if the driver reports one.
+    pub column: Option<usize>,
+    /// The message itself, e.g. `error: 'foo' : undeclared identifier`.
+    pub message: String,
+    /// The offending line of source code.
+    pub source_line: Option<String>,
+}
+
+impl Display for ShaderLogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.file, self.line, self.column) {
+            (Some(file), Some(line), Some(column)) => write!(f, "{file}:{line}:{column}: ")?,
+            (Some(file), Some(line), None) => write!(f, "{file}:{line}: ")?,
+            (Some(file), None, _) => write!(f, "{file}: ")?,
+            (None, _, _) => (),
+        }
+        write!(f, "{}", self.message)?;
+        if let Some(source_line) = &self.source_line {
+            let gutter = self.line.map(|line| line.to_string()).unwrap_or_default();
+            write!(f, "\n {gutter} | {source_line}")?;
+            if let Some(column) = self.column {
+                let pad = " ".repeat(gutter.len());
+                let indent = " ".repeat(column.saturating_sub(1));
+                write!(f, "\n {pad} | {indent}^")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Errors from creating shaders and programs.
+#[derive(Debug)]
+pub enum ShaderError {
+    /// The shader source couldn't be preprocessed.
+    Preprocess(PreprocessError),
+    /// The shader failed to compile.
+    Compile {
+        /// Which stage the shader is for.
+        stage: ShaderType,
+        /// The name of the shader's top level file.
+        file: String,
+        /// Messages parsed from the info log.
+        entries: Vec<ShaderLogEntry>,
+        /// The full, unparsed info log.
+        log: String,
+    },
+    /// The program failed to link. Carries the full info log.
+    Link(String),
+    /// A uniform was expected in a program, but wasn't active.
+    UniformNotFound(Cow<'static, str>),
+    /// An attribute was expected in a program, but wasn't active.
+    AttributeNotFound(Cow<'static, str>),
+    /// A vertex layout doesn't match the inputs of the vertex shader.
+    LayoutMismatch {
+        /// The name of the Rust type providing the layout.
+        layout: &'static str,
+        /// The types the layout uploads.
+        expected: String,
+        /// The inputs the vertex shader declares.
+        found: String,
+    },
+}
+
+impl From<PreprocessError> for ShaderError {
+    fn from(value: PreprocessError) -> Self {
+        Self::Preprocess(value)
+    }
+}
+
+impl Error for ShaderError {}
+
+impl Display for ShaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Preprocess(err) => write!(f, "Error preprocessing shader: {err}"),
+            Self::Compile {
+                stage,
+                file,
+                entries,
+                log,
+            } => {
+                write!(f, "Error compiling {stage} shader `{file}`")?;
+                if entries.is_empty() {
+                    write!(f, ":\n{}", log.trim_end())
+                } else {
+                    for entry in entries {
+                        write!(f, "\n{entry}")?;
+                    }
+                    Ok(())
+                }
+            }
+            Self::Link(log) => write!(f, "Error linking shader program:\n{}", log.trim_end()),
+            Self::UniformNotFound(uniform_name) => {
+                write!(f, "Uniform `{uniform_name}` not found in shader program")
+            }
+            Self::AttributeNotFound(attribute_name) => {
+                write!(f, "Attribute `{attribute_name}` not found in shader program")
+            }
+            Self::LayoutMismatch {
+                layout,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Vertex layout `{layout}` does not match the vertex shader inputs.\n\
+                 layout uploads: {expected}\n\
+                 shader expects: {found}"
+            ),
+        }
+    }
+}
+
+impl Display for ShaderType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Fragment => "fragment",
+            Self::Geometry => "geometry",
+            Self::Vertex => "vertex",
+        })
+    }
+}
+
+/// A location found in a line of a driver info log.
+pub(crate) struct LogLocation {
+    /// Byte index the location starts at.
+    pub start: usize,
+    /// Byte index directly after the location.
+    pub end: usize,
+    /// The 1-based line of the compiled source.
+    pub line: usize,
+    /// The 1-based column, if the driver reports one.
+    pub column: Option<usize>,
+}
+
+/// Find the `string:line(column)` (Mesa), `string:line` (AMD, Intel)
+/// or `string(line)` (Nvidia) location in a line of a driver info log.
+pub(crate) fn find_log_location(line: &str) -> Option<LogLocation> {
+    let bytes = line.as_bytes();
+    let digits_from = |from: usize| {
+        let mut end = from;
+        while end < bytes.len() && bytes[end].is_ascii_digit() {
+            end += 1;
+        }
+        end
+    };
+    let mut i = 0;
+    while i < bytes.len() {
+        if !bytes[i].is_ascii_digit() || (i > 0 && bytes[i - 1].is_ascii_alphanumeric()) {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        i = digits_from(i);
+        let closing = match bytes.get(i) {
+            Some(b':') => None,
+            Some(b'(') => Some(b')'),
+            _ => continue,
+        };
+        let line_start = i + 1;
+        let line_end = digits_from(line_start);
+        if line_end == line_start {
+            continue;
+        }
+        let mut end = match closing {
+            Some(closing) if bytes.get(line_end) == Some(&closing) => line_end + 1,
+            Some(_) => continue,
+            None => line_end,
+        };
+        let mut column = None;
+        if closing.is_none() && bytes.get(end) == Some(&b'(') {
+            let column_end = digits_from(end + 1);
+            if column_end > end + 1 && bytes.get(column_end) == Some(&b')') {
+                column = line[end + 1..column_end].parse().ok();
+                end = column_end + 1;
+            }
+        }
+        return Some(LogLocation {
+            start,
+            end,
+            line: line[line_start..line_end].parse().ok()?,
+            column,
+        });
+    }
+    None
+}
+
+/// Parse a driver info log into entries.
+///
+/// `source` is the code that was compiled, and `map` maps its lines back to
+/// their original files, if it was preprocessed.
+pub(crate) fn parse_info_log(
+    log: &str,
+    source: &str,
+    map: Option<&SourceMap>,
+    fallback_file: &str,
+) -> Vec<ShaderLogEntry> {
+    let source_lines: Vec<&str> = source.lines().collect();
+    let mut entries = vec![];
+    for line in log.lines().filter(|line| !line.trim().is_empty()) {
+        let Some(location) = find_log_location(line) else {
+            // summaries like "1 compilation errors" don't point anywhere
+            entries.push(ShaderLogEntry {
+                file: None,
+                line: None,
+                column: None,
+                message: line.trim().to_owned(),
+                source_line: None,
+            });
+            continue;
+        };
+
+        let prefix = line[..location.start].trim();
+        let rest = line[location.end..].trim_start_matches([' ', ':']);
+        let message = if prefix.is_empty() {
+            rest.to_owned()
+        } else {
+            // AMD and Intel put the severity before the location
+            format!("{}: {rest}", prefix.trim_end_matches(':').to_lowercase())
+        };
+
+        let (file, original_line) = match map.and_then(|map| map.resolve(location.line)) {
+            Some(resolved) => (
+                map.and_then(|map| map.file_name(resolved.file))
+                    .map(str::to_owned),
+                resolved.line,
+            ),
+            None => (Some(fallback_file.to_owned()), location.line),
+        };
+
+        entries.push(ShaderLogEntry {
+            file,
+            line: Some(original_line),
+            column: location.column,
+            message,
+            source_line: source_lines
+                .get(location.line.wrapping_sub(1))
+                .map(|line| line.trim_end().to_owned()),
+        });
+    }
+    entries
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gl_wrappers::preprocessor::Preprocessor;
+
+    const SOURCE: &str = "#version 430 core\nvoid main() {\n  foo = 1;\n}\n";
+
+    #[test]
+    fn mesa_locations() {
+        let location = find_log_location("0:3(3): error: `foo' undeclared").unwrap();
+        assert_eq!((location.start, location.end), (0, 6));
+        assert_eq!((location.line, location.column), (3, Some(3)));
+    }
+
+    #[test]
+    fn nvidia_locations() {
+        let location = find_log_location("0(3) : error C1008: undefined variable \"foo\"").unwrap();
+        assert_eq!((location.start, location.end), (0, 4));
+        assert_eq!((location.line, location.column), (3, None));
+    }
+
+    #[test]
+    fn amd_locations() {
+        let location = find_log_location("ERROR: 0:3: 'foo' : undeclared identifier").unwrap();
+        assert_eq!((location.start, location.end), (7, 10));
+        assert_eq!((location.line, location.column), (3, None));
+    }
+
+    #[test]
+    fn lines_without_locations() {
+        assert!(find_log_location("ERROR: 1 compilation errors.  No code generated.").is_none());
+        assert!(find_log_location("vec3(1.0)").is_none());
+        assert!(find_log_location("").is_none());
+    }
+
+    #[test]
+    fn entries_without_a_source_map() {
+        let entries = parse_info_log(
+            "ERROR: 0:3: 'foo' : undeclared identifier\nERROR: 1 compilation errors.\n",
+            SOURCE,
+            None,
+            "shader.glsl",
+        );
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].file.as_deref(), Some("shader.glsl"));
+        assert_eq!(entries[0].line, Some(3));
+        assert_eq!(entries[0].message, "error: 'foo' : undeclared identifier");
+        assert_eq!(entries[0].source_line.as_deref(), Some("  foo = 1;"));
+        assert_eq!(entries[1].line, None);
+        assert_eq!(entries[1].message, "ERROR: 1 compilation errors.");
+    }
+
+    #[test]
+    fn entries_point_at_included_files() {
+        let resolver = |path: &str| match path {
+            "main.glsl" => Some(Cow::Borrowed("#version 430 core\n#include \"lib.glsl\"\n")),
+            "lib.glsl" => Some(Cow::Borrowed("// lib\nfoo = 1;\n")),
+            _ => None,
+        };
+        let processed = Preprocessor::with_resolver(&resolver)
+            .define("A")
+            .process("main.glsl")
+            .unwrap();
+        let source = processed.source.to_str().unwrap();
+        let entries = parse_info_log(
+            "0:4(1): error: `foo' undeclared",
+            source,
+            Some(&processed.map),
+            "main.glsl",
+        );
+        assert_eq!(entries[0].file.as_deref(), Some("lib.glsl"));
+        assert_eq!(entries[0].line, Some(2));
+        assert_eq!(entries[0].source_line.as_deref(), Some("foo = 1;"));
+    }
+
+    #[test]
+    fn entries_display_with_a_caret() {
+        let entry = ShaderLogEntry {
+            file: Some("a.glsl".to_owned()),
+            line: Some(12),
+            column: Some(3),
+            message: "error: oops".to_owned(),
+            source_line: Some("  foo = 1;".to_owned()),
+        };
+        assert_eq!(
+            entry.to_string(),
+            "a.glsl:12:3: error: oops\n 12 |   foo = 1;\n    |   ^"
+        );
+    }
+}
@@ -1,11 +1,26 @@
 //! Standalone functions and modules to wrap the nitty-gritty of
 //! OpenGL objects with Rust structs.
+pub mod diagnostics;
 #[macro_use]
 pub mod program;
+pub mod preprocessor;
+pub mod reflection;
 pub mod shader;
+#[macro_use]
+pub mod std140;
+pub mod timer_query;
+pub mod uniform;
+pub mod uniform_buffer;
 
+pub use diagnostics::*;
+pub use preprocessor::*;
 pub use program::*;
+pub use reflection::*;
 pub use shader::*;
+pub use std140::Std140;
+pub use timer_query::*;
+pub use uniform::*;
+pub use uniform_buffer::*;
 
 /// Safely update the OpenGL viewport.
 pub fn gl_upd_viewport(width: u32, height: u32) {
@@ -0,0 +1,479 @@
+//! Exports [`Preprocessor`] and [`ProcessedSource`].
+//!
+//! GLSL has no way of including other files, so shaders get run through this
+//! before being handed to [`Shader`](super::Shader). It resolves
+//! `#include "file.glsl"` directives relative to the `glsl` directory,
+//! injects defines directly after `#version`, and remembers where every
+//! output line came from so compiler errors can point at the real file.
+use std::{
+    borrow::Cow,
+    error::Error,
+    ffi::CString,
+    fmt::{self, Display, Write as _},
+};
+
+/// Every GLSL file in the `glsl` directory, keyed by its path relative to it.
+///
+/// Shaders are embedded at compile time, so adding a new file means adding it here too.
+macro_rules! glsl_files {
+    ( $( $path:literal ),* $(,)? ) => {
+        &[ $( ($path, include_str!(concat!("../../glsl/", $path))) ),* ]
+    };
+}
+
+/// Sources of every shader file shipped with the engine.
+pub static GLSL_FILES: &[(&str, &str)] = glsl_files![
+    "frag_shader.glsl",
+    "vert_shader.glsl",
+    "common/frame.glsl",
+    "common/srgb.glsl",
+    "debug_draw/frag.glsl",
+    "debug_draw/vert.glsl",
+    "imgui/frag.glsl",
+    "imgui/vert.glsl",
+];
+
+/// Look up an embedded file from [`GLSL_FILES`].
+pub fn embedded_glsl(path: &str) -> Option<&'static str> {
+    GLSL_FILES
+        .iter()
+        .find(|(name, _)| *name == path)
+        .map(|(_, source)| *source)
+}
+
+/// [`embedded_glsl`], in the shape a [`Preprocessor`] resolver takes.
+fn resolve_embedded(path: &str) -> Option<Cow<'static, str>> {
+    embedded_glsl(path).map(Cow::Borrowed)
+}
+
+/// Name used in a [`SourceMap`] for lines that were generated by the preprocessor.
+pub const INJECTED_FILE_NAME: &str = "<injected>";
+
+/// Maximum depth of nested `#include`s before assuming something went wrong.
+const MAX_INCLUDE_DEPTH: usize = 32;
+
+/// Runs the GLSL preprocessing step.
+///
+/// # Usage
+/// ```no_run
+/// let source = Preprocessor::new()
+///     .define("OUTPUT_SRGB")
+///     .process("imgui/frag.glsl")?;
+/// let shader = Shader::from_processed(ShaderType::Fragment, source);
+/// ```
+pub struct Preprocessor<'a> {
+    /// Defines to inject after `#version`, in order.
+    defines: Vec<(Cow<'a, str>, Option<Cow<'a, str>>)>,
+    /// Resolves a path relative to the `glsl` directory to its source.
+    resolver: &'a dyn Fn(&str) -> Option<Cow<'static, str>>,
+}
+
+impl Default for Preprocessor<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> Preprocessor<'a> {
+    /// Create a preprocessor resolving files from the embedded `glsl` directory.
+    pub fn new() -> Self {
+        Self {
+            defines: vec![],
+            resolver: &resolve_embedded,
+        }
+    }
+    /// Create a preprocessor resolving files with a custom function,
+    /// for example one reading from disk.
+    pub fn with_resolver(resolver: &'a dyn Fn(&str) -> Option<Cow<'static, str>>) -> Self {
+        Self {
+            defines: vec![],
+            resolver,
+        }
+    }
+    /// Add a `#define name` to the output.
+    pub fn define(mut self, name: impl Into<Cow<'a, str>>) -> Self {
+        self.defines.push((name.into(), None));
+        self
+    }
+    /// Add a `#define name value` to the output.
+    pub fn define_value(
+        mut self,
+        name: impl Into<Cow<'a, str>>,
+        value: impl Into<Cow<'a, str>>,
+    ) -> Self {
+        self.defines.push((name.into(), Some(value.into())));
+        self
+    }
+
+    /// Preprocess the file at `path`, relative to the `glsl` directory.
+    ///
+    /// # Errors
+    /// Errors if `path` or any file it includes cannot be found,
+    /// if an include directive is malformed, or if includes recurse.
+    pub fn process(&self, path: &str) -> Result<ProcessedSource, PreprocessError> {
+        let source = (self.resolver)(path)
+            .ok_or_else(|| PreprocessError::FileNotFound(path.to_owned(), None))?;
+
+        let mut state = ProcessState {
+            output: String::with_capacity(source.len()),
+            map: SourceMap {
+                files: vec![INJECTED_FILE_NAME.to_owned()],
+                lines: vec![],
+            },
+            stack: vec![],
+        };
+
+        // split off the #version line, since defines can only come after it
+        let (version, body) = source.split_at(find_version_line(&source).unwrap_or(0));
+        let file = state.map.add_file(path);
+        for (i, line) in version.lines().enumerate() {
+            state.push_line(line, SourceLocation { file, line: i + 1 });
+        }
+        self.inject_defines(&mut state);
+
+        state.stack.push(path.to_owned());
+        self.process_lines(&mut state, path, body, version.lines().count())?;
+        state.finish()
+    }
+
+    /// Write all the defines into the output, marked as injected lines.
+    fn inject_defines(&self, state: &mut ProcessState) {
+        for (i, (name, value)) in self.defines.iter().enumerate() {
+            let line = match value {
+                Some(value) => format!("#define {name} {value}"),
+                None => format!("#define {name}"),
+            };
+            state.push_line(&line, SourceLocation { file: 0, line: i + 1 });
+        }
+    }
+
+    /// Copy `source` to the output, recursively expanding includes.
+    ///
+    /// `line_offset` is the number of lines of this file already written.
+    fn process_lines(
+        &self,
+        state: &mut ProcessState,
+        path: &str,
+        source: &str,
+        line_offset: usize,
+    ) -> Result<(), PreprocessError> {
+        let file = state.map.add_file(path);
+        for (i, line) in source.lines().enumerate() {
+            let location = SourceLocation {
+                file,
+                line: line_offset + i + 1,
+            };
+            let Some(directive) = parse_directive(line, "include") else {
+                if parse_directive(line, "version").is_some() && state.stack.len() > 1 {
+                    return Err(PreprocessError::BadDirective(
+                        path.to_owned(),
+                        location.line,
+                        "#version is only allowed in the top level file".into(),
+                    ));
+                }
+                state.push_line(line, location);
+                continue;
+            };
+
+            let include = parse_include_path(directive).ok_or_else(|| {
+                PreprocessError::BadDirective(
+                    path.to_owned(),
+                    location.line,
+                    format!("expected #include \"file.glsl\", found '{}'", line.trim()),
+                )
+            })?;
+
+            if state.stack.iter().any(|open| open == include) {
+                let mut chain = state.stack.join(" -> ");
+                let _ = write!(chain, " -> {include}");
+                return Err(PreprocessError::RecursiveInclude(chain));
+            }
+            if state.stack.len() >= MAX_INCLUDE_DEPTH {
+                return Err(PreprocessError::RecursiveInclude(state.stack.join(" -> ")));
+            }
+
+            let included = (self.resolver)(include).ok_or_else(|| {
+                PreprocessError::FileNotFound(include.to_owned(), Some((path.to_owned(), location.line)))
+            })?;
+
+            state.stack.push(include.to_owned());
+            self.process_lines(state, include, &included, 0)?;
+            state.stack.pop();
+        }
+        Ok(())
+    }
+}
+
+/// Intermediate state kept while preprocessing one file.
+struct ProcessState {
+    /// The resulting source code.
+    output: String,
+    /// Where each output line came from.
+    map: SourceMap,
+    /// Files currently being processed, outermost first.
+    stack: Vec<String>,
+}
+
+impl ProcessState {
+    /// Write a line to the output, remembering where it came from.
+    fn push_line(&mut self, line: &str, location: SourceLocation) {
+        self.output.push_str(line);
+        self.output.push('\n');
+        self.map.lines.push(location);
+    }
+    /// Turn this into the final output.
+    fn finish(self) -> Result<ProcessedSource, PreprocessError> {
+        let source = CString::new(self.output).map_err(|_| PreprocessError::InteriorNul)?;
+        Ok(ProcessedSource {
+            source,
+            map: self.map,
+        })
+    }
+}
+
+/// Return the byte index directly after the `#version` line, if there is one.
+fn find_version_line(source: &str) -> Option<usize> {
+    let mut idx = 0;
+    for line in source.split_inclusive('\n') {
+        idx += line.len();
+        if parse_directive(line, "version").is_some() {
+            return Some(idx);
+        }
+        let trimmed = line.trim();
+        // #version must come before anything but comments and whitespace
+        if !trimmed.is_empty() && !trimmed.starts_with("//") {
+            return None;
+        }
+    }
+    None
+}
+
+/// If `line` is the preprocessor directive `name`, return the rest of the line.
+fn parse_directive<'l>(line: &'l str, name: &str) -> Option<&'l str> {
+    let rest = line.trim_start().strip_prefix('#')?.trim_start();
+    let rest = rest.strip_prefix(name)?;
+    if rest.starts_with(|c: char| c.is_ascii_alphanumeric() || c == '_') {
+        return None;
+    }
+    Some(rest.trim())
+}
+
+/// Parse the `"file.glsl"` part of an include directive.
+fn parse_include_path(directive: &str) -> Option<&str> {
+    let inner = directive.strip_prefix('"')?;
+    let end = inner.find('"')?;
+    let trailing = inner[end + 1..].trim();
+    if end == 0 || !(trailing.is_empty() || trailing.starts_with("//")) {
+        return None;
+    }
+    Some(&inner[..end])
+}
+
+/// A line in an original source file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SourceLocation {
+    /// Index into [`SourceMap::files`].
+    pub file: usize,
+    /// 1-based line number in that file.
+    pub line: usize,
+}
+
+/// Maps lines of preprocessed output back to the files they came from.
+#[derive(Clone, Debug, Default)]
+pub struct SourceMap {
+    /// Names of every file that contributed to the output.
+    /// The first entry is always [`INJECTED_FILE_NAME`].
+    files: Vec<String>,
+    /// Original location of each output line, indexed by output line - 1.
+    lines: Vec<SourceLocation>,
+}
+
+impl SourceMap {
+    /// Get the index of `name` in `files`, adding it if necessary.
+    fn add_file(&mut self, name: &str) -> usize {
+        match self.files.iter().position(|file| file == name) {
+            Some(idx) => idx,
+            None => {
+                self.files.push(name.to_owned());
+                self.files.len() - 1
+            }
+        }
+    }
+    /// The name of the file at `idx`.
+    pub fn file_name(&self, idx: usize) -> Option<&str> {
+        self.files.get(idx).map(String::as_str)
+    }
+    /// The name of the top level file, if anything was processed.
+    pub fn root_file(&self) -> Option<&str> {
+        self.file_name(1)
+    }
+    /// Map a 1-based line of preprocessed output back to its origin.
+    pub fn resolve(&self, output_line: usize) -> Option<SourceLocation> {
+        self.lines.get(output_line.checked_sub(1)?).copied()
+    }
+}
+
+/// Preprocessed shader source, ready to be compiled.
+pub struct ProcessedSource {
+    /// The final source code.
+    pub source: CString,
+    /// Where each line of `source` came from.
+    pub map: SourceMap,
+}
+
+/// Errors from preprocessing a shader.
+#[derive(Debug)]
+pub enum PreprocessError {
+    /// A file could not be found. Carries the file that included it, and the line.
+    FileNotFound(String, Option<(String, usize)>),
+    /// A directive could not be parsed. Carries the file, line, and reason.
+    BadDirective(String, usize, String),
+    /// A file included itself, directly or indirectly.
+    RecursiveInclude(String),
+    /// The output contained a nul byte.
+    InteriorNul,
+}
+
+impl Error for PreprocessError {}
+
+impl Display for PreprocessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FileNotFound(file, None) => write!(f, "shader file `{file}` not found"),
+            Self::FileNotFound(file, Some((parent, line))) => write!(
+                f,
+                "{parent}:{line}: included shader file `{file}` not found"
+            ),
+            Self::BadDirective(file, line, msg) => write!(f, "{file}:{line}: {msg}"),
+            Self::RecursiveInclude(chain) => write!(f, "recursive #include: {chain}"),
+            Self::InteriorNul => write!(f, "shader source contains interior nul byte(s)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Resolve files from a fixed list, like [`GLSL_FILES`] but for tests.
+    fn resolver(
+        files: &'static [(&'static str, &'static str)],
+    ) -> impl Fn(&str) -> Option<Cow<'static, str>> {
+        move |path| {
+            files
+                .iter()
+                .find(|(name, _)| *name == path)
+                .map(|(_, source)| Cow::Borrowed(*source))
+        }
+    }
+
+    fn output(source: &ProcessedSource) -> &str {
+        source.source.to_str().unwrap()
+    }
+
+    #[test]
+    fn defines_go_after_version() {
+        let files = resolver(&[("main.glsl", "#version 430 core\nvoid main() {}\n")]);
+        let source = Preprocessor::with_resolver(&files)
+            .define("A")
+            .define_value("B", "2")
+            .process("main.glsl")
+            .unwrap();
+        assert_eq!(
+            output(&source),
+            "#version 430 core\n#define A\n#define B 2\nvoid main() {}\n"
+        );
+        let injected = source.map.resolve(2).unwrap();
+        assert_eq!(source.map.file_name(injected.file), Some(INJECTED_FILE_NAME));
+        let body = source.map.resolve(4).unwrap();
+        assert_eq!(source.map.file_name(body.file), Some("main.glsl"));
+        assert_eq!(body.line, 2);
+    }
+
+    #[test]
+    fn includes_are_expanded_and_mapped() {
+        let files = resolver(&[
+            ("main.glsl", "#version 430 core\n#include \"lib.glsl\"\nvoid main() {}\n"),
+            ("lib.glsl", "// lib\nfloat f() { return 1.; }\n"),
+        ]);
+        let source = Preprocessor::with_resolver(&files)
+            .process("main.glsl")
+            .unwrap();
+        assert_eq!(
+            output(&source),
+            "#version 430 core\n// lib\nfloat f() { return 1.; }\nvoid main() {}\n"
+        );
+        let lib = source.map.resolve(3).unwrap();
+        assert_eq!(source.map.file_name(lib.file), Some("lib.glsl"));
+        assert_eq!(lib.line, 2);
+        let main = source.map.resolve(4).unwrap();
+        assert_eq!(source.map.file_name(main.file), Some("main.glsl"));
+        assert_eq!(main.line, 3);
+        assert_eq!(source.map.root_file(), Some("main.glsl"));
+        assert_eq!(source.map.resolve(0), None);
+        assert_eq!(source.map.resolve(5), None);
+    }
+
+    #[test]
+    fn recursive_includes_are_errors() {
+        let files = resolver(&[
+            ("a.glsl", "#include \"b.glsl\"\n"),
+            ("b.glsl", "#include \"a.glsl\"\n"),
+        ]);
+        let err = Preprocessor::with_resolver(&files)
+            .process("a.glsl")
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "recursive #include: a.glsl -> b.glsl -> a.glsl");
+    }
+
+    #[test]
+    fn missing_includes_name_the_includer() {
+        let files = resolver(&[("a.glsl", "\n#include \"nope.glsl\"\n")]);
+        let err = Preprocessor::with_resolver(&files)
+            .process("a.glsl")
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "a.glsl:2: included shader file `nope.glsl` not found");
+    }
+
+    #[test]
+    fn version_only_in_top_level_file() {
+        let files = resolver(&[
+            ("a.glsl", "#version 430 core\n#include \"b.glsl\"\n"),
+            ("b.glsl", "#version 430 core\n"),
+        ]);
+        let err = Preprocessor::with_resolver(&files)
+            .process("a.glsl")
+            .err()
+            .unwrap();
+        assert!(matches!(err, PreprocessError::BadDirective(file, 1, _) if file == "b.glsl"));
+    }
+
+    #[test]
+    fn include_paths() {
+        assert_eq!(parse_include_path("\"a.glsl\""), Some("a.glsl"));
+        assert_eq!(parse_include_path("\"a.glsl\" // why"), Some("a.glsl"));
+        assert_eq!(parse_include_path("\"\""), None);
+        assert_eq!(parse_include_path("a.glsl"), None);
+        assert_eq!(parse_include_path("\"a.glsl\" junk"), None);
+    }
+
+    #[test]
+    fn directives() {
+        assert_eq!(parse_directive("  # include \"a\"", "include"), Some("\"a\""));
+        assert_eq!(parse_directive("#includes", "include"), None);
+        assert_eq!(parse_directive("include", "include"), None);
+        assert_eq!(find_version_line("// hi\n#version 430\nx\n"), Some(19));
+        assert_eq!(find_version_line("x\n#version 430\n"), None);
+    }
+
+    #[test]
+    fn every_embedded_shader_preprocesses() {
+        for (path, _) in GLSL_FILES {
+            if !path.starts_with("common/") {
+                Preprocessor::new().process(path).unwrap();
+            }
+        }
+    }
+}
@@ -1,7 +1,7 @@
 //! Exports [`Program`].
-use std::ffi::CString;
+use std::{cell::OnceCell, collections::HashMap, ffi::CString};
 
-use super::CompiledShader;
+use super::{CompiledShader, ShaderError, uniform::CachedUniform};
 
 /// Wrapper for an OpenGL program.
 ///
@@ -9,6 +9,8 @@ use super::CompiledShader;
 pub struct Program {
     /// The internal OpenGL id for this object.
     id: gl::types::GLuint,
+    /// Locations of active uniforms, filled on first use by [`Program::set`].
+    pub(crate) uniform_cache: OnceCell<HashMap<String, CachedUniform>>,
 }
 
 /// Make a new [`Program`].
@@ -56,7 +58,7 @@ impl Program {
         vert_shader: &CompiledShader,
         geo_shader: Option<&CompiledShader>,
         frag_shader: &CompiledShader,
-    ) -> Result<Self, String> {
+    ) -> Result<Self, ShaderError> {
         Self::from_args(ProgramArgs {
             frag_shader,
             vert_shader,
@@ -65,7 +67,10 @@ impl Program {
         })
     }
     /// Create a new program from a [`ProgramArgs`] struct.
-    pub fn from_args(args: ProgramArgs<'_>) -> Result<Self, String> {
+    ///
+    /// # Errors
+    /// Errors if linking was unsuccessful, with the full response from OpenGL.
+    pub fn from_args(args: ProgramArgs<'_>) -> Result<Self, ShaderError> {
         let inner = unsafe {
             let program = gl::CreateProgram();
             gl::AttachShader(program, args.vert_shader.id());
@@ -80,20 +85,17 @@ impl Program {
             let mut success = 0;
             ::gl::GetProgramiv(program, ::gl::LINK_STATUS, &mut success);
             if success != gl::TRUE.into() {
-                let mut infolog: Vec<u8> = vec![0; 512];
-                let mut length = 0;
-                ::gl::GetProgramInfoLog(program, 512, &mut length, infolog.as_mut_ptr().cast());
-                infolog.truncate(length.try_into().unwrap());
-                let loggable_string = CString::new(infolog).unwrap().into_string().unwrap();
-                return Err(format!(
-                    "Error linking program. OpenGL reply: '{}'",
-                    loggable_string
-                ));
+                let log = program_info_log(program);
+                gl::DeleteProgram(program);
+                return Err(ShaderError::Link(log));
             }
             program
         };
 
-        Ok(Self { id: inner })
+        Ok(Self {
+            id: inner,
+            uniform_cache: OnceCell::new(),
+        })
     }
     /// Get the internal id of this program.
     pub fn id(&self) -> gl::types::GLuint {
@@ -130,3 +132,21 @@ impl Program {
         }
     }
 }
+
+/// Read the full info log of a program object.
+fn program_info_log(program: gl::types::GLuint) -> String {
+    let mut length = 0;
+    unsafe { gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut length) };
+    let mut infolog: Vec<u8> = vec![0; length.max(1).try_into().unwrap()];
+    let mut written = 0;
+    unsafe {
+        gl::GetProgramInfoLog(
+            program,
+            length.max(1),
+            &mut written,
+            infolog.as_mut_ptr().cast(),
+        );
+    }
+    infolog.truncate(written.try_into().unwrap());
+    String::from_utf8_lossy(&infolog).into_owned()
+}
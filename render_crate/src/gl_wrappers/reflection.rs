@@ -0,0 +1,352 @@
+//! Exports [`GlslType`], [`ActiveVariable`] and [`UniformBlock`].
+//!
+//! Lets a linked [`Program`] be asked what it actually contains,
+//! and checks that what we upload matches what the shaders expect.
+use std::fmt::{self, Display};
+
+use super::{Program, ShaderError};
+use crate::render_vec::{GlType, StaticGlLayout};
+
+/// The type of a GLSL variable, as reported by OpenGL.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[allow(clippy::missing_docs_in_private_items)]
+pub enum GlslType {
+    Float,
+    Vec2,
+    Vec3,
+    Vec4,
+    Double,
+    DVec2,
+    DVec3,
+    DVec4,
+    Int,
+    IVec2,
+    IVec3,
+    IVec4,
+    UInt,
+    UVec2,
+    UVec3,
+    UVec4,
+    Bool,
+    Mat2,
+    Mat3,
+    Mat4,
+    Sampler2D,
+    Sampler3D,
+    SamplerCube,
+    Sampler2DArray,
+    /// A type without a variant here yet. Carries the GL enum.
+    Other(gl::types::GLenum),
+}
+
+impl GlslType {
+    /// Convert the GL enum reported for a variable.
+    pub fn from_gl(gl_enum: gl::types::GLenum) -> Self {
+        match gl_enum {
+            gl::FLOAT => Self::Float,
+            gl::FLOAT_VEC2 => Self::Vec2,
+            gl::FLOAT_VEC3 => Self::Vec3,
+            gl::FLOAT_VEC4 => Self::Vec4,
+            gl::DOUBLE => Self::Double,
+            gl::DOUBLE_VEC2 => Self::DVec2,
+            gl::DOUBLE_VEC3 => Self::DVec3,
+            gl::DOUBLE_VEC4 => Self::DVec4,
+            gl::INT => Self::Int,
+            gl::INT_VEC2 => Self::IVec2,
+            gl::INT_VEC3 => Self::IVec3,
+            gl::INT_VEC4 => Self::IVec4,
+            gl::UNSIGNED_INT => Self::UInt,
+            gl::UNSIGNED_INT_VEC2 => Self::UVec2,
+            gl::UNSIGNED_INT_VEC3 => Self::UVec3,
+            gl::UNSIGNED_INT_VEC4 => Self::UVec4,
+            gl::BOOL => Self::Bool,
+            gl::FLOAT_MAT2 => Self::Mat2,
+            gl::FLOAT_MAT3 => Self::Mat3,
+            gl::FLOAT_MAT4 => Self::Mat4,
+            gl::SAMPLER_2D => Self::Sampler2D,
+            gl::SAMPLER_3D => Self::Sampler3D,
+            gl::SAMPLER_CUBE => Self::SamplerCube,
+            gl::SAMPLER_2D_ARRAY => Self::Sampler2DArray,
+            other => Self::Other(other),
+        }
+    }
+
+    /// The [`GlType`]s a vertex attribute of this type is made of,
+    /// or [`None`] if it has no equivalent.
+    pub fn components(&self) -> Option<(GlType, usize)> {
+        Some(match self {
+            Self::Float => (GlType::Float, 1),
+            Self::Vec2 => (GlType::Float, 2),
+            Self::Vec3 => (GlType::Float, 3),
+            Self::Vec4 => (GlType::Float, 4),
+            Self::Mat2 => (GlType::Float, 4),
+            Self::Mat3 => (GlType::Float, 9),
+            Self::Mat4 => (GlType::Float, 16),
+            Self::Double => (GlType::Double, 1),
+            Self::DVec2 => (GlType::Double, 2),
+            Self::DVec3 => (GlType::Double, 3),
+            Self::DVec4 => (GlType::Double, 4),
+            _ => return None,
+        })
+    }
+}
+
+impl Display for GlslType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Float => "float",
+            Self::Vec2 => "vec2",
+            Self::Vec3 => "vec3",
+            Self::Vec4 => "vec4",
+            Self::Double => "double",
+            Self::DVec2 => "dvec2",
+            Self::DVec3 => "dvec3",
+            Self::DVec4 => "dvec4",
+            Self::Int => "int",
+            Self::IVec2 => "ivec2",
+            Self::IVec3 => "ivec3",
+            Self::IVec4 => "ivec4",
+            Self::UInt => "uint",
+            Self::UVec2 => "uvec2",
+            Self::UVec3 => "uvec3",
+            Self::UVec4 => "uvec4",
+            Self::Bool => "bool",
+            Self::Mat2 => "mat2",
+            Self::Mat3 => "mat3",
+            Self::Mat4 => "mat4",
+            Self::Sampler2D => "sampler2D",
+            Self::Sampler3D => "sampler3D",
+            Self::SamplerCube => "samplerCube",
+            Self::Sampler2DArray => "sampler2DArray",
+            Self::Other(gl_enum) => return write!(f, "<GL type 0x{gl_enum:04x}>"),
+        };
+        f.write_str(name)
+    }
+}
+
+/// An active uniform or attribute in a [`Program`].
+#[derive(Clone, Debug)]
+pub struct ActiveVariable {
+    /// The name, as written in the shader. Arrays are reported as `name[0]`.
+    pub name: String,
+    /// The type of a single element.
+    pub gl_type: GlslType,
+    /// The number of elements; 1 unless this is an array.
+    pub size: gl::types::GLint,
+    /// The location, or [`None`] for built-ins and uniform block members.
+    pub location: Option<gl::types::GLint>,
+    /// For uniforms, the index of the block this belongs to, if any.
+    pub block_index: Option<gl::types::GLuint>,
+}
+
+/// An active uniform block in a [`Program`].
+#[derive(Clone, Debug)]
+pub struct UniformBlock {
+    /// The name of the block, as written in the shader.
+    pub name: String,
+    /// The index of this block in the program.
+    pub index: gl::types::GLuint,
+    /// The uniform buffer binding point this block reads from.
+    pub binding: gl::types::GLuint,
+    /// The minimum size of a buffer backing this block, in bytes.
+    pub data_size: usize,
+}
+
+/// Read a name out of OpenGL with a function that takes a buffer.
+fn read_name(
+    max_length: gl::types::GLint,
+    read: impl FnOnce(gl::types::GLsizei, *mut gl::types::GLsizei, *mut gl::types::GLchar),
+) -> String {
+    let mut buf: Vec<u8> = vec![0; max_length.max(1).try_into().unwrap()];
+    let mut length = 0;
+    read(max_length.max(1), &mut length, buf.as_mut_ptr().cast());
+    buf.truncate(length.try_into().unwrap());
+    String::from_utf8_lossy(&buf).into_owned()
+}
+
+impl Program {
+    /// Query a single integer parameter of this program.
+    fn get_param(&self, param: gl::types::GLenum) -> gl::types::GLint {
+        let mut value = 0;
+        unsafe { gl::GetProgramiv(self.id(), param, &mut value) };
+        value
+    }
+
+    /// List every active uniform in this program, including those in uniform blocks.
+    pub fn active_uniforms(&self) -> Vec<ActiveVariable> {
+        let count = self.get_param(gl::ACTIVE_UNIFORMS);
+        let max_length = self.get_param(gl::ACTIVE_UNIFORM_MAX_LENGTH);
+        (0..count as gl::types::GLuint)
+            .map(|index| {
+                let (mut size, mut gl_type) = (0, 0);
+                let name = read_name(max_length, |buf_size, length, name| unsafe {
+                    gl::GetActiveUniform(
+                        self.id(),
+                        index,
+                        buf_size,
+                        length,
+                        &mut size,
+                        &mut gl_type,
+                        name,
+                    )
+                });
+                let mut block_index = -1;
+                unsafe {
+                    gl::GetActiveUniformsiv(
+                        self.id(),
+                        1,
+                        &index,
+                        gl::UNIFORM_BLOCK_INDEX,
+                        &mut block_index,
+                    )
+                };
+                ActiveVariable {
+                    location: self.get_uniform_location(&name),
+                    name,
+                    gl_type: GlslType::from_gl(gl_type),
+                    size,
+                    block_index: block_index.try_into().ok(),
+                }
+            })
+            .collect()
+    }
+
+    /// List every active vertex attribute in this program.
+    pub fn active_attributes(&self) -> Vec<ActiveVariable> {
+        let count = self.get_param(gl::ACTIVE_ATTRIBUTES);
+        let max_length = self.get_param(gl::ACTIVE_ATTRIBUTE_MAX_LENGTH);
+        (0..count as gl::types::GLuint)
+            .map(|index| {
+                let (mut size, mut gl_type) = (0, 0);
+                let name = read_name(max_length, |buf_size, length, name| unsafe {
+                    gl::GetActiveAttrib(
+                        self.id(),
+                        index,
+                        buf_size,
+                        length,
+                        &mut size,
+                        &mut gl_type,
+                        name,
+                    )
+                });
+                ActiveVariable {
+                    location: self.get_attrib_location(&name),
+                    name,
+                    gl_type: GlslType::from_gl(gl_type),
+                    size,
+                    block_index: None,
+                }
+            })
+            .collect()
+    }
+
+    /// List every active uniform block in this program.
+    pub fn uniform_blocks(&self) -> Vec<UniformBlock> {
+        let count = self.get_param(gl::ACTIVE_UNIFORM_BLOCKS);
+        let max_length = self.get_param(gl::ACTIVE_UNIFORM_BLOCK_MAX_NAME_LENGTH);
+        (0..count as gl::types::GLuint)
+            .map(|index| {
+                let name = read_name(max_length, |buf_size, length, name| unsafe {
+                    gl::GetActiveUniformBlockName(self.id(), index, buf_size, length, name)
+                });
+                let (mut binding, mut data_size) = (0, 0);
+                unsafe {
+                    gl::GetActiveUniformBlockiv(
+                        self.id(),
+                        index,
+                        gl::UNIFORM_BLOCK_BINDING,
+                        &mut binding,
+                    );
+                    gl::GetActiveUniformBlockiv(
+                        self.id(),
+                        index,
+                        gl::UNIFORM_BLOCK_DATA_SIZE,
+                        &mut data_size,
+                    );
+                }
+                UniformBlock {
+                    name,
+                    index,
+                    binding: binding.try_into().unwrap_or_default(),
+                    data_size: data_size.try_into().unwrap_or_default(),
+                }
+            })
+            .collect()
+    }
+
+    /// Check that the vertex inputs of this program, ordered by location,
+    /// are made of exactly the types `LayoutT` uploads.
+    ///
+    /// # Errors
+    /// Errors with a description of both layouts if they differ.
+    pub fn validate_layout<LayoutT: StaticGlLayout>(&self) -> Result<(), ShaderError>
+    where
+        [(); LayoutT::LEN]:,
+    {
+        let mut attributes: Vec<ActiveVariable> = self
+            .active_attributes()
+            .into_iter()
+            .filter(|attrib| attrib.location.is_some())
+            .collect();
+        attributes.sort_by_key(|attrib| attrib.location);
+
+        let expected = LayoutT::gl_type_layout();
+        let mut found = vec![];
+        let mut matches = true;
+        for attrib in attributes.iter() {
+            match attrib.gl_type.components() {
+                Some((gl_type, count)) => {
+                    let count = count * usize::try_from(attrib.size).unwrap_or(1);
+                    found.extend(std::iter::repeat_n(gl_type, count));
+                }
+                None => matches = false,
+            }
+        }
+        if matches && found.as_slice() == expected.as_slice() {
+            return Ok(());
+        }
+
+        let shader_inputs = attributes
+            .iter()
+            .map(|attrib| {
+                format!(
+                    "{} {} (location {})",
+                    attrib.gl_type,
+                    attrib.name,
+                    attrib.location.unwrap_or_default()
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        Err(ShaderError::LayoutMismatch {
+            layout: std::any::type_name::<LayoutT>(),
+            expected: format!("{:?}", expected.as_slice()),
+            found: shader_inputs,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gl_enums() {
+        assert_eq!(GlslType::from_gl(gl::FLOAT_VEC3), GlslType::Vec3);
+        assert_eq!(GlslType::from_gl(gl::FLOAT_MAT4), GlslType::Mat4);
+        assert_eq!(GlslType::from_gl(gl::INT), GlslType::Int);
+        assert_eq!(GlslType::from_gl(gl::SAMPLER_2D), GlslType::Sampler2D);
+        assert_eq!(GlslType::from_gl(0xdead), GlslType::Other(0xdead));
+    }
+
+    #[test]
+    fn components() {
+        let components = |gl_enum| GlslType::from_gl(gl_enum).components();
+        assert_eq!(components(gl::FLOAT_VEC3), Some((GlType::Float, 3)));
+        assert_eq!(components(gl::FLOAT_MAT4), Some((GlType::Float, 16)));
+        assert_eq!(components(gl::DOUBLE), Some((GlType::Double, 1)));
+        assert_eq!(components(gl::INT), None);
+        assert_eq!(components(gl::SAMPLER_2D), None);
+        assert_eq!(components(0xdead), None);
+    }
+}
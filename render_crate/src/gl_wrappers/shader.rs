@@ -1,18 +1,33 @@
 //! Exports [`Shader`] and [`CompiledShader`].
 use std::{ffi::CString, ptr::null};
 
+use crate::RenderError;
+
+use super::{
+    diagnostics::{ShaderError, parse_info_log},
+    preprocessor::{ProcessedSource, SourceMap},
+};
+
+/// Name used in errors for shaders that weren't loaded from a file.
+const UNNAMED_SHADER: &str = "<unnamed>";
+
 /// An uncompiled OpenGL shader.
 /// Contains the source code necessary to compile it.
 pub struct Shader {
     /// GL ID for this shader.
     inner: gl::types::GLuint,
+    /// Which stage this shader is for.
+    shader_type: ShaderType,
     /// The source code for this shader.
     source: CString,
     /// Was this shader
     was_compiled: bool,
+    /// Maps lines back to their original files, if this shader was preprocessed.
+    source_map: Option<SourceMap>,
 }
 
 /// Represents the type of a shader object.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ShaderType {
     /// This shader is a Fragment shader.
     Fragment,
@@ -34,10 +49,20 @@ impl Shader {
         };
         Self {
             inner: shader,
+            shader_type,
             source: source.into(),
             was_compiled: false,
+            source_map: None,
         }
     }
+    /// Wrap the output of a [`Preprocessor`](super::Preprocessor).
+    ///
+    /// Compilation errors will refer to the original files and lines.
+    pub fn from_processed(shader_type: ShaderType, processed: ProcessedSource) -> Self {
+        let mut shader = Self::new(shader_type, processed.source);
+        shader.source_map = Some(processed.map);
+        shader
+    }
     /// Helper function for `Shader::new()` with vertex shaders.
     pub fn vertex(source: CString) -> Self {
         Self::new(ShaderType::Vertex, source)
@@ -56,8 +81,8 @@ impl Shader {
     /// Returns a [`CompiledShader`], for use in [`Program`](super::Program)s.
     ///
     /// # Errors
-    /// Errors if compilation was unsuccessful, with the response from OpenGL.
-    pub fn compile(mut self) -> Result<CompiledShader, String> {
+    /// Errors if compilation was unsuccessful, with the parsed response from OpenGL.
+    pub fn compile(mut self) -> Result<CompiledShader, ShaderError> {
         let compiled_shader = unsafe {
             gl::ShaderSource(self.inner, 1, &self.source.as_ptr(), null());
             gl::CompileShader(self.inner);
@@ -66,12 +91,7 @@ impl Shader {
             gl::GetShaderiv(self.inner, gl::COMPILE_STATUS, &mut success);
 
             if success != gl::TRUE.into() {
-                let mut infolog: Vec<u8> = vec![0; 512];
-                let mut length = 0;
-                gl::GetShaderInfoLog(self.inner, 512, &mut length, infolog.as_mut_ptr().cast());
-                infolog.truncate(length.try_into().unwrap());
-                let loggable_string = CString::new(infolog).unwrap().into_string().unwrap();
-                return Err(format!("Shader compilation error: {}", loggable_string));
+                return Err(self.compile_error());
             }
             self.was_compiled = true;
             self.inner
@@ -79,6 +99,46 @@ impl Shader {
         // Safety: we check for
         unsafe { Ok(CompiledShader::new_unchecked(compiled_shader)) }
     }
+
+    /// Read the info log and build an error out of it.
+    fn compile_error(&self) -> ShaderError {
+        let log = shader_info_log(self.inner);
+        let file = self
+            .source_map
+            .as_ref()
+            .and_then(SourceMap::root_file)
+            .unwrap_or(UNNAMED_SHADER);
+        let entries = parse_info_log(
+            &log,
+            &self.source.to_string_lossy(),
+            self.source_map.as_ref(),
+            file,
+        );
+        ShaderError::Compile {
+            stage: self.shader_type,
+            file: file.to_owned(),
+            entries,
+            log,
+        }
+    }
+}
+
+/// Read the full info log of a shader object.
+fn shader_info_log(shader: gl::types::GLuint) -> String {
+    let mut length = 0;
+    unsafe { gl::GetShaderiv(shader, gl::INFO_LOG_LENGTH, &mut length) };
+    let mut infolog: Vec<u8> = vec![0; length.max(1).try_into().unwrap()];
+    let mut written = 0;
+    unsafe {
+        gl::GetShaderInfoLog(
+            shader,
+            length.max(1),
+            &mut written,
+            infolog.as_mut_ptr().cast(),
+        );
+    }
+    infolog.truncate(written.try_into().unwrap());
+    String::from_utf8_lossy(&infolog).into_owned()
 }
 
 impl Drop for Shader {
@@ -109,12 +169,27 @@ impl CompiledShader {
     ///
     /// # Errors
     /// Errors if `shader` is not the index of a valid compiled shader in the OpenGL context.
-    pub fn new(_shader: gl::types::GLuint) -> Result<Self, ()> {
-        unimplemented!()
+    pub fn new(shader: gl::types::GLuint) -> Result<Self, RenderError> {
+        let invalid = RenderError::InvalidObject {
+            kind: "compiled shader",
+            id: shader,
+        };
+        // SAFETY: both calls accept any id, and the status is only queried for real shaders.
+        unsafe {
+            if gl::IsShader(shader) == gl::FALSE {
+                return Err(invalid);
+            }
+            let mut status = 0;
+            gl::GetShaderiv(shader, gl::COMPILE_STATUS, &mut status);
+            if status != gl::TRUE.into() {
+                return Err(invalid);
+            }
+            Ok(Self::new_unchecked(shader))
+        }
     }
 
     /// Alias for [`CompiledShader::new`].
-    pub fn from_opengl_uint(uint: gl::types::GLuint) -> Result<Self, ()> {
+    pub fn from_opengl_uint(uint: gl::types::GLuint) -> Result<Self, RenderError> {
         Self::new(uint)
     }
 
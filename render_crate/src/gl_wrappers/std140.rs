@@ -0,0 +1,268 @@
+//! Exports [`Std140`] and the [`uniform_block!`] macro.
+//!
+//! Uniform blocks declared `layout(std140)` have a fixed, but padded, memory layout.
+//! Rather than hand-padding `#[repr(C)]` structs (and getting it wrong), structs
+//! declared with [`uniform_block!`] are written field by field into a byte buffer,
+//! with the alignment rules from the GLSL spec applied along the way.
+//!
+//! <https://registry.khronos.org/OpenGL/specs/gl/glspec45.core.pdf#page=159>
+use crate::vector3::to_byte_slice;
+
+/// A type that can be written into a `std140` uniform block.
+pub trait Std140 {
+    /// The base alignment of this type, in bytes.
+    const ALIGN: usize;
+    /// Write this value to `out`, which is already aligned to [`Self::ALIGN`].
+    fn write_std140(&self, out: &mut Std140Writer);
+}
+
+/// Round `offset` up to the next multiple of `align`.
+pub const fn align_to(offset: usize, align: usize) -> usize {
+    offset.next_multiple_of(align)
+}
+
+/// Alignment of vec4s, and of arrays and structs in `std140`.
+pub const VEC4_ALIGN: usize = 16;
+
+/// A byte buffer being filled with `std140` data.
+#[derive(Default)]
+pub struct Std140Writer {
+    /// The bytes written so far.
+    bytes: Vec<u8>,
+}
+
+impl Std140Writer {
+    /// Create an empty writer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Pad the buffer until it is a multiple of `align`.
+    pub fn align(&mut self, align: usize) {
+        self.bytes.resize(align_to(self.bytes.len(), align), 0);
+    }
+    /// Write a field, applying its alignment first.
+    pub fn write<T: Std140 + ?Sized>(&mut self, value: &T) {
+        self.align(T::ALIGN);
+        value.write_std140(self);
+    }
+    /// Append raw bytes with no alignment.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+    /// The bytes written so far.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+    /// Clear the buffer, keeping its allocation.
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+    }
+}
+
+/// Implement [`Std140`] for plain scalars and vectors.
+macro_rules! std140_plain {
+    ($($ty:ty => $align:expr, $len:expr;)*) => {
+        $(
+            impl Std140 for $ty {
+                const ALIGN: usize = $align;
+                fn write_std140(&self, out: &mut Std140Writer) {
+                    // SAFETY: these types are repr(C) collections of 4 byte scalars
+                    let scalars: &[u32] = unsafe {
+                        std::slice::from_raw_parts((self as *const $ty).cast(), $len)
+                    };
+                    out.write_bytes(to_byte_slice(scalars));
+                }
+            }
+        )*
+    };
+}
+
+std140_plain! {
+    f32 => 4, 1;
+    i32 => 4, 1;
+    u32 => 4, 1;
+    glm::Vec2 => 8, 2;
+    glm::Vec3 => 16, 3;
+    glm::Vec4 => 16, 4;
+    glm::IVec2 => 8, 2;
+    glm::IVec3 => 16, 3;
+    glm::IVec4 => 16, 4;
+    glm::UVec2 => 8, 2;
+    glm::UVec3 => 16, 3;
+    glm::UVec4 => 16, 4;
+}
+
+impl Std140 for bool {
+    const ALIGN: usize = 4;
+    fn write_std140(&self, out: &mut Std140Writer) {
+        u32::from(*self).write_std140(out);
+    }
+}
+
+// Matrices are arrays of column vectors, each padded to a vec4.
+impl Std140 for glm::Mat3 {
+    const ALIGN: usize = VEC4_ALIGN;
+    fn write_std140(&self, out: &mut Std140Writer) {
+        for column in self.as_array() {
+            out.write(column);
+        }
+        out.align(VEC4_ALIGN);
+    }
+}
+
+impl Std140 for glm::Mat4 {
+    const ALIGN: usize = VEC4_ALIGN;
+    fn write_std140(&self, out: &mut Std140Writer) {
+        for column in self.as_array() {
+            out.write(column);
+        }
+    }
+}
+
+// Array elements are each padded to a vec4.
+impl<T: Std140, const N: usize> Std140 for [T; N] {
+    const ALIGN: usize = align_to(T::ALIGN, VEC4_ALIGN);
+    fn write_std140(&self, out: &mut Std140Writer) {
+        for element in self {
+            out.write(element);
+            out.align(Self::ALIGN);
+        }
+    }
+}
+
+/// Declare a struct that can be uploaded to a `std140` uniform block.
+///
+/// Fields can be any [`Std140`] type, including other `uniform_block!` structs
+/// and arrays of them. The field order must match the GLSL declaration.
+///
+/// # Usage
+/// ```no_run
+/// uniform_block! {
+///     /// Matches `uniform Camera { mat4 view; vec3 position; float fov; }`.
+///     pub struct CameraBlock {
+///         pub view: glm::Mat4,
+///         pub position: glm::Vec3,
+///         pub fov: f32,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! uniform_block {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $( $(#[$field_meta:meta])* $field_vis:vis $field:ident : $ty:ty ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Debug)]
+        $vis struct $name {
+            $( $(#[$field_meta])* $field_vis $field: $ty ),*
+        }
+
+        impl $crate::gl_wrappers::std140::Std140 for $name {
+            const ALIGN: usize = $crate::gl_wrappers::std140::VEC4_ALIGN;
+            fn write_std140(&self, out: &mut $crate::gl_wrappers::std140::Std140Writer) {
+                $( out.write(&self.$field); )*
+                out.align($crate::gl_wrappers::std140::VEC4_ALIGN);
+            }
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    uniform_block! {
+        /// `{ float a; vec3 b; float c; vec2 d; }`
+        struct Mixed {
+            a: f32,
+            b: glm::Vec3,
+            c: f32,
+            d: glm::Vec2,
+        }
+    }
+
+    uniform_block! {
+        /// `{ float x; Mixed inner[2]; bool flag; }`
+        struct Outer {
+            x: f32,
+            inner: [Mixed; 2],
+            flag: bool,
+        }
+    }
+
+    fn mixed(base: f32) -> Mixed {
+        Mixed {
+            a: base,
+            b: glm::vec3(base + 1., base + 2., base + 3.),
+            c: base + 4.,
+            d: glm::vec2(base + 5., base + 6.),
+        }
+    }
+
+    /// The 4 byte scalar written at byte `offset`.
+    fn scalar_at(writer: &Std140Writer, offset: usize) -> [u8; 4] {
+        writer.bytes()[offset..offset + 4].try_into().unwrap()
+    }
+
+    fn f32_at(writer: &Std140Writer, offset: usize) -> f32 {
+        f32::from_ne_bytes(scalar_at(writer, offset))
+    }
+
+    #[test]
+    fn align_to_rounds_up() {
+        assert_eq!(align_to(0, 16), 0);
+        assert_eq!(align_to(1, 16), 16);
+        assert_eq!(align_to(16, 16), 16);
+        assert_eq!(align_to(20, 8), 24);
+    }
+
+    #[test]
+    fn scalars_pack_after_a_vec3() {
+        let mut writer = Std140Writer::new();
+        writer.write(&mixed(0.));
+        // a@0, b@16, c fills b's padding @28, d@32, struct rounded up to 48
+        assert_eq!(writer.bytes().len(), 48);
+        for (offset, value) in [(0, 0.), (16, 1.), (20, 2.), (24, 3.), (28, 4.), (32, 5.), (36, 6.)] {
+            assert_eq!(f32_at(&writer, offset), value, "offset {offset}");
+        }
+    }
+
+    #[test]
+    fn array_elements_are_padded_to_vec4() {
+        let mut writer = Std140Writer::new();
+        writer.write(&[1f32, 2., 3.]);
+        assert_eq!(writer.bytes().len(), 48);
+        assert_eq!(f32_at(&writer, 16), 2.);
+        assert_eq!(f32_at(&writer, 32), 3.);
+    }
+
+    #[test]
+    fn mat3_columns_are_padded_to_vec4() {
+        let mut writer = Std140Writer::new();
+        let matrix = glm::mat3(1., 2., 3., 4., 5., 6., 7., 8., 9.);
+        writer.write(&matrix);
+        assert_eq!(writer.bytes().len(), 48);
+        assert_eq!(f32_at(&writer, 16), 4.);
+        assert_eq!(f32_at(&writer, 40), 9.);
+    }
+
+    #[test]
+    fn nested_structs_and_bools() {
+        let mut writer = Std140Writer::new();
+        writer.write(&Outer {
+            x: -1.,
+            inner: [mixed(10.), mixed(20.)],
+            flag: true,
+        });
+        // x@0, inner[0]@16, inner[1]@64, flag@112, rounded up to 128
+        assert_eq!(writer.bytes().len(), 128);
+        assert_eq!(f32_at(&writer, 0), -1.);
+        assert_eq!(f32_at(&writer, 16), 10.);
+        assert_eq!(f32_at(&writer, 64), 20.);
+        assert_eq!(f32_at(&writer, 64 + 28), 24.);
+        assert_eq!(scalar_at(&writer, 112), 1u32.to_ne_bytes());
+    }
+}
@@ -0,0 +1,127 @@
+//! Exports [`TimerQuery`] and [`GpuTimer`].
+use std::collections::VecDeque;
+
+use crate::error::{RenderError, create_object};
+
+/// Wrapper for an OpenGL `GL_TIME_ELAPSED` query object.
+///
+/// <https://www.khronos.org/opengl/wiki/Query_Object#Timer_queries>
+pub struct TimerQuery {
+    /// The internal OpenGL id for this query.
+    id: gl::types::GLuint,
+}
+
+impl TimerQuery {
+    /// Create a new query object.
+    ///
+    /// # Errors
+    /// Errors if OpenGL can't create the query.
+    pub fn new() -> Result<Self, RenderError> {
+        let create = |n, ids| unsafe { gl::CreateQueries(gl::TIME_ELAPSED, n, ids) };
+        let id = unsafe { create_object("timer query", create) }?;
+        Ok(Self { id })
+    }
+    /// Start timing the GL commands after this. Only one timer query can run at once.
+    pub fn begin(&self) {
+        unsafe { gl::BeginQuery(gl::TIME_ELAPSED, self.id) };
+    }
+    /// Stop timing.
+    pub fn end(&self) {
+        unsafe { gl::EndQuery(gl::TIME_ELAPSED) };
+    }
+    /// Whether the GPU has finished the timed commands, so the result can be read without waiting.
+    pub fn is_available(&self) -> bool {
+        let mut available = 0;
+        unsafe { gl::GetQueryObjectiv(self.id, gl::QUERY_RESULT_AVAILABLE, &mut available) };
+        available != gl::FALSE.into()
+    }
+    /// The time elapsed in nanoseconds. Stalls until the GPU is done if it isn't available yet.
+    pub fn result_ns(&self) -> u64 {
+        let mut result = 0;
+        unsafe { gl::GetQueryObjectui64v(self.id, gl::QUERY_RESULT, &mut result) };
+        result
+    }
+    /// Get the internal id of this query.
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+}
+
+impl Drop for TimerQuery {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteQueries(1, &self.id) };
+    }
+}
+
+/// Number of queries a [`GpuTimer`] can have in flight.
+/// Results usually arrive 1-3 frames late.
+pub const GPU_TIMER_RING_LEN: usize = 4;
+
+/// Times the same span of GL commands every frame without ever waiting on the GPU.
+///
+/// Each [`GpuTimer::begin`]/[`GpuTimer::end`] uses the next query in a ring,
+/// and [`GpuTimer::poll`] picks up whichever results have arrived since.
+/// If every query is still in flight, that frame simply isn't timed.
+pub struct GpuTimer {
+    /// The ring of queries.
+    queries: [TimerQuery; GPU_TIMER_RING_LEN],
+    /// Indices into `queries` waiting for results, oldest first.
+    in_flight: VecDeque<usize>,
+    /// The query started by [`GpuTimer::begin`], if it hasn't ended yet.
+    running: Option<usize>,
+    /// The most recent result, in milliseconds.
+    last_ms: Option<f64>,
+}
+
+impl GpuTimer {
+    /// Create a timer and its queries.
+    ///
+    /// # Errors
+    /// Errors if OpenGL can't create the queries.
+    pub fn new() -> Result<Self, RenderError> {
+        let mut queries = Vec::with_capacity(GPU_TIMER_RING_LEN);
+        for _ in 0..GPU_TIMER_RING_LEN {
+            queries.push(TimerQuery::new()?);
+        }
+        Ok(Self {
+            queries: queries.try_into().unwrap_or_else(|_| unreachable!()),
+            in_flight: VecDeque::with_capacity(GPU_TIMER_RING_LEN),
+            running: None,
+            last_ms: None,
+        })
+    }
+    /// Start timing, if there's a free query.
+    pub fn begin(&mut self) {
+        debug_assert!(self.running.is_none(), "GpuTimer::begin called twice");
+        if self.in_flight.len() == GPU_TIMER_RING_LEN {
+            return;
+        }
+        let index = (0..GPU_TIMER_RING_LEN)
+            .find(|index| !self.in_flight.contains(index))
+            .unwrap();
+        self.queries[index].begin();
+        self.running = Some(index);
+    }
+    /// Stop timing.
+    pub fn end(&mut self) {
+        if let Some(index) = self.running.take() {
+            self.queries[index].end();
+            self.in_flight.push_back(index);
+        }
+    }
+    /// Collect every result that's ready, without waiting for the rest.
+    pub fn poll(&mut self) {
+        while let Some(&index) = self.in_flight.front() {
+            let query = &self.queries[index];
+            if !query.is_available() {
+                break;
+            }
+            self.last_ms = Some(query.result_ns() as f64 / 1_000_000.);
+            self.in_flight.pop_front();
+        }
+    }
+    /// The most recent result in milliseconds, or [`None`] before the first arrives.
+    pub fn last_ms(&self) -> Option<f64> {
+        self.last_ms
+    }
+}
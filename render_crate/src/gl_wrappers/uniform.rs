@@ -0,0 +1,166 @@
+//! Exports [`Uniform`] and [`Sampler`].
+//!
+//! Typed uniform setting for [`Program`]s. Locations are looked up once and
+//! cached, and in debug builds the Rust type is checked against the GLSL type
+//! the shader declared.
+use std::collections::HashMap;
+
+use super::{GlslType, Program, ShaderError};
+
+/// A value that can be set as a uniform.
+pub trait Uniform {
+    /// The GLSL types this can be assigned to.
+    const GLSL_TYPES: &'static [GlslType];
+    /// Set the uniform at `location` in `program` to this value.
+    ///
+    /// # Safety
+    /// `program` must be a linked program, and `location` must be a location in it.
+    unsafe fn set_uniform(&self, program: gl::types::GLuint, location: gl::types::GLint);
+}
+
+/// A texture unit for a sampler uniform to read from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sampler(pub u32);
+
+/// Implement [`Uniform`] for a type through one of the `glProgramUniform*v` functions.
+macro_rules! uniform_impl {
+    ($($ty:ty => $func:ident, [$($glsl:ident),+];)*) => {
+        $(
+            impl Uniform for $ty {
+                const GLSL_TYPES: &'static [GlslType] = &[$(GlslType::$glsl),+];
+                unsafe fn set_uniform(&self, program: gl::types::GLuint, location: gl::types::GLint) {
+                    // SAFETY: glm types are repr(C) collections of scalars
+                    unsafe { gl::$func(program, location, 1, (self as *const $ty).cast()) }
+                }
+            }
+        )*
+    };
+}
+
+uniform_impl! {
+    f32 => ProgramUniform1fv, [Float];
+    glm::Vec2 => ProgramUniform2fv, [Vec2];
+    glm::Vec3 => ProgramUniform3fv, [Vec3];
+    glm::Vec4 => ProgramUniform4fv, [Vec4];
+    i32 => ProgramUniform1iv, [Int, Bool];
+    glm::IVec2 => ProgramUniform2iv, [IVec2];
+    glm::IVec3 => ProgramUniform3iv, [IVec3];
+    glm::IVec4 => ProgramUniform4iv, [IVec4];
+    u32 => ProgramUniform1uiv, [UInt, Bool];
+    glm::UVec2 => ProgramUniform2uiv, [UVec2];
+    glm::UVec3 => ProgramUniform3uiv, [UVec3];
+    glm::UVec4 => ProgramUniform4uiv, [UVec4];
+}
+
+impl Uniform for bool {
+    const GLSL_TYPES: &'static [GlslType] = &[GlslType::Bool];
+    unsafe fn set_uniform(&self, program: gl::types::GLuint, location: gl::types::GLint) {
+        unsafe { gl::ProgramUniform1i(program, location, (*self).into()) }
+    }
+}
+
+impl Uniform for glm::Mat3 {
+    const GLSL_TYPES: &'static [GlslType] = &[GlslType::Mat3];
+    unsafe fn set_uniform(&self, program: gl::types::GLuint, location: gl::types::GLint) {
+        // SAFETY: glm matrices are repr(C) and column major, like GL expects
+        unsafe {
+            gl::ProgramUniformMatrix3fv(program, location, 1, gl::FALSE, self.as_array().as_ptr().cast())
+        }
+    }
+}
+
+impl Uniform for glm::Mat4 {
+    const GLSL_TYPES: &'static [GlslType] = &[GlslType::Mat4];
+    unsafe fn set_uniform(&self, program: gl::types::GLuint, location: gl::types::GLint) {
+        // SAFETY: glm matrices are repr(C) and column major, like GL expects
+        unsafe {
+            gl::ProgramUniformMatrix4fv(program, location, 1, gl::FALSE, self.as_array().as_ptr().cast())
+        }
+    }
+}
+
+impl Uniform for Sampler {
+    const GLSL_TYPES: &'static [GlslType] = &[
+        GlslType::Sampler2D,
+        GlslType::Sampler3D,
+        GlslType::SamplerCube,
+        GlslType::Sampler2DArray,
+    ];
+    unsafe fn set_uniform(&self, program: gl::types::GLuint, location: gl::types::GLint) {
+        unsafe { gl::ProgramUniform1i(program, location, self.0.try_into().unwrap()) }
+    }
+}
+
+/// A cached uniform location and the type the shader declared it with.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct CachedUniform {
+    /// Location of the uniform.
+    location: gl::types::GLint,
+    /// The declared type.
+    gl_type: GlslType,
+}
+
+/// Build the name -> location cache for every active uniform in `program`.
+pub(crate) fn build_uniform_cache(program: &Program) -> HashMap<String, CachedUniform> {
+    let mut cache = HashMap::new();
+    for uniform in program.active_uniforms() {
+        let Some(location) = uniform.location else {
+            continue;
+        };
+        let cached = CachedUniform {
+            location,
+            gl_type: uniform.gl_type,
+        };
+        // arrays are reported as `name[0]`, but can be set through `name` too
+        if let Some(array_name) = uniform.name.strip_suffix("[0]") {
+            cache.insert(array_name.to_owned(), cached);
+        }
+        cache.insert(uniform.name, cached);
+    }
+    cache
+}
+
+impl Program {
+    /// Look up a uniform in the location cache, filling it on first use.
+    fn cached_uniform(&self, name: &str) -> Option<CachedUniform> {
+        self.uniform_cache
+            .get_or_init(|| build_uniform_cache(self))
+            .get(name)
+            .copied()
+    }
+
+    /// Set the uniform `name` to `value`.
+    ///
+    /// Like OpenGL, setting a uniform the shader doesn't use does nothing,
+    /// since the compiler is free to optimize unused uniforms out.
+    /// Use [`Program::try_set`] if it should be an error.
+    ///
+    /// # Panics
+    /// In debug builds, panics if `U` cannot be assigned to the declared GLSL type.
+    pub fn set<U: Uniform>(&self, name: impl AsRef<str>, value: U) {
+        let _ = self.try_set(name, value);
+    }
+
+    /// Set the uniform `name` to `value`.
+    ///
+    /// # Errors
+    /// Errors if there's no active uniform called `name`.
+    ///
+    /// # Panics
+    /// In debug builds, panics if `U` cannot be assigned to the declared GLSL type.
+    pub fn try_set<U: Uniform>(&self, name: impl AsRef<str>, value: U) -> Result<(), ShaderError> {
+        let name = name.as_ref();
+        let uniform = self
+            .cached_uniform(name)
+            .ok_or_else(|| ShaderError::UniformNotFound(name.to_owned().into()))?;
+        debug_assert!(
+            U::GLSL_TYPES.contains(&uniform.gl_type),
+            "uniform `{name}` is declared as {}, but was set with a {}",
+            uniform.gl_type,
+            std::any::type_name::<U>(),
+        );
+        // SAFETY: the location came from this program
+        unsafe { value.set_uniform(self.id(), uniform.location) };
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+//! Exports [`UniformBuffer`].
+use std::marker::PhantomData;
+
+use super::{
+    Program, ShaderError,
+    std140::{Std140, Std140Writer},
+};
+use crate::error::{RenderError, check_gl, create_object};
+
+/// Wrapper for an OpenGL uniform buffer object, holding one `T` in `std140` layout.
+///
+/// The buffer stays bound to its binding point, so every program declaring
+/// `layout(std140, binding = N) uniform ...` sees the same data without
+/// any per-program setup.
+///
+/// <https://www.khronos.org/opengl/wiki/Uniform_Buffer_Object>
+pub struct UniformBuffer<T: Std140> {
+    /// The internal OpenGL id for this buffer.
+    id: gl::types::GLuint,
+    /// The binding point this buffer is attached to.
+    binding: gl::types::GLuint,
+    /// Scratch space for laying out `T`, reused between updates.
+    writer: Std140Writer,
+    /// Size of the data last uploaded, in bytes.
+    size: usize,
+    /// This buffer holds a `T`.
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Std140> UniformBuffer<T> {
+    /// Create a buffer holding `value`, and attach it to `binding`.
+    ///
+    /// # Errors
+    /// Errors if OpenGL can't create the buffer, or reports an error uploading to it.
+    pub fn new(binding: gl::types::GLuint, value: &T) -> Result<Self, RenderError> {
+        let id = unsafe { create_object("uniform buffer", gl::CreateBuffers) }?;
+        let mut buffer = Self {
+            id,
+            binding,
+            writer: Std140Writer::new(),
+            size: 0,
+            _phantom: PhantomData,
+        };
+        buffer.update(value);
+        check_gl("UniformBuffer::new")?;
+        Ok(buffer)
+    }
+    /// Upload a new value and make sure the buffer is attached to its binding point.
+    pub fn update(&mut self, value: &T) {
+        self.writer.clear();
+        self.writer.write(value);
+        let bytes = self.writer.bytes();
+        self.size = bytes.len();
+        unsafe {
+            gl::NamedBufferData(
+                self.id,
+                bytes.len().cast_signed(),
+                bytes.as_ptr().cast(),
+                gl::DYNAMIC_DRAW,
+            );
+            gl::BindBufferBase(gl::UNIFORM_BUFFER, self.binding, self.id);
+        }
+        crate::stats::record_upload(bytes.len());
+    }
+    /// Get the internal id of this buffer.
+    pub fn id(&self) -> gl::types::GLuint {
+        self.id
+    }
+    /// The binding point this buffer is attached to.
+    pub fn binding(&self) -> gl::types::GLuint {
+        self.binding
+    }
+    /// Size of the data last uploaded, in bytes.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Check that `program` declares a block called `block_name`
+    /// on this buffer's binding point, with the same size as `T`.
+    ///
+    /// Blocks the program doesn't use are optimized out, so they pass.
+    ///
+    /// # Errors
+    /// Errors describing the mismatch if the block doesn't match.
+    pub fn validate_block(&self, program: &Program, block_name: &str) -> Result<(), ShaderError> {
+        let Some(block) = program
+            .uniform_blocks()
+            .into_iter()
+            .find(|block| block.name == block_name)
+        else {
+            return Ok(());
+        };
+        if block.binding != self.binding || block.data_size != self.size {
+            return Err(ShaderError::LayoutMismatch {
+                layout: std::any::type_name::<T>(),
+                expected: format!("binding {}, {} bytes", self.binding, self.size),
+                found: format!(
+                    "uniform {} with binding {}, {} bytes",
+                    block.name, block.binding, block.data_size
+                ),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl<T: Std140> Drop for UniformBuffer<T> {
+    fn drop(&mut self) {
+        unsafe { gl::DeleteBuffers(1, &self.id) };
+    }
+}
@@ -0,0 +1,93 @@
+//! Exports [`GpuProfiler`].
+use crate::gl_wrappers::GpuTimer;
+
+/// GPU time taken by a named render pass.
+#[derive(Clone, Copy, Debug)]
+pub struct PassTiming {
+    /// The name given to [`GpuProfiler::begin`].
+    pub name: &'static str,
+    /// The most recent GPU time in milliseconds, or [`None`] if there's no result yet.
+    pub ms: Option<f64>,
+}
+
+/// Times named render passes on the GPU.
+///
+/// Passes can't overlap, since only one `GL_TIME_ELAPSED` query may run at a time.
+/// ```no_run
+/// profiler.begin("world");
+/// render.render_world(&mesh, &camera)?;
+/// profiler.end();
+/// // once per frame
+/// profiler.poll();
+/// ```
+#[derive(Default)]
+pub struct GpuProfiler {
+    /// Each pass, in the order it was first seen.
+    passes: Vec<(&'static str, GpuTimer)>,
+    /// Index into `passes` of the pass being timed.
+    current: Option<usize>,
+    /// Set if a timer couldn't be created. Nothing is timed after that.
+    disabled: bool,
+}
+
+impl GpuProfiler {
+    /// Create a profiler with no passes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Start timing the pass called `name`.
+    pub fn begin(&mut self, name: &'static str) {
+        debug_assert!(
+            self.current.is_none(),
+            "GPU pass `{name}` started inside another pass"
+        );
+        if self.disabled {
+            return;
+        }
+        let index = match self.passes.iter().position(|(pass, _)| *pass == name) {
+            Some(index) => index,
+            None => match GpuTimer::new() {
+                Ok(timer) => {
+                    self.passes.push((name, timer));
+                    self.passes.len() - 1
+                }
+                Err(err) => {
+                    log::warn!("GPU timing disabled, creating a timer for `{name}` failed: {err}");
+                    self.disabled = true;
+                    return;
+                }
+            },
+        };
+        self.passes[index].1.begin();
+        self.current = Some(index);
+    }
+    /// Stop timing the current pass.
+    pub fn end(&mut self) {
+        if let Some(index) = self.current.take() {
+            self.passes[index].1.end();
+        }
+    }
+    /// Time everything `f` does as the pass called `name`.
+    pub fn scope<R>(&mut self, name: &'static str, f: impl FnOnce() -> R) -> R {
+        self.begin(name);
+        let ret = f();
+        self.end();
+        ret
+    }
+    /// Collect whichever results have arrived. Call once per frame.
+    pub fn poll(&mut self) {
+        for (_, timer) in self.passes.iter_mut() {
+            timer.poll();
+        }
+    }
+    /// The latest GPU time of every pass.
+    pub fn timings(&self) -> Vec<PassTiming> {
+        self.passes
+            .iter()
+            .map(|(name, timer)| PassTiming {
+                name,
+                ms: timer.last_ms(),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,20 @@
+//! Exports [`SdlClipboard`].
+use imgui::ClipboardBackend;
+use sdl2::clipboard::ClipboardUtil;
+
+/// Lets ImGui copy and paste through the system clipboard, using SDL.
+pub struct SdlClipboard(pub ClipboardUtil);
+
+impl ClipboardBackend for SdlClipboard {
+    fn get(&mut self) -> Option<String> {
+        if !self.0.has_clipboard_text() {
+            return None;
+        }
+        self.0.clipboard_text().ok()
+    }
+    fn set(&mut self, value: &str) {
+        if let Err(err) = self.0.set_clipboard_text(value) {
+            log::warn!("failed to set clipboard text: {err}");
+        }
+    }
+}
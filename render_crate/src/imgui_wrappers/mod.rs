@@ -1,3 +1,4 @@
 //! Wrappers to make ImGui work with our code.
+pub mod clipboard;
 pub mod renderer;
 pub mod sdlplatform;
@@ -28,7 +28,7 @@
 // OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
 // SOFTWARE.
 
-use std::{borrow::Cow, error::Error, ffi::CString, fmt::Display, mem::offset_of, str::FromStr};
+use std::mem::offset_of;
 
 use imgui::{
     Context, DrawCmd, DrawCmdParams, DrawData, DrawIdx, DrawVert, FontAtlas, TextureId, Textures,
@@ -36,8 +36,13 @@ use imgui::{
 };
 
 use crate::{
-    construct_program,
-    gl_wrappers::{Program, shader::Shader},
+    RenderError, construct_program,
+    error::{check_gl, create_object},
+    gl_wrappers::{
+        Preprocessor, Program, ShaderError,
+        shader::{Shader, ShaderType},
+    },
+    stats,
     vector3::to_byte_slice,
 };
 
@@ -116,39 +121,20 @@ const fn imgui_index_type_as_gl() -> u32 {
 
 #[allow(clippy::missing_docs_in_private_items)]
 impl ImguiRenderer {
-    pub fn new(imgui_context: &mut Context) -> Self {
+    pub fn new(imgui_context: &mut Context) -> Result<Self, RenderError> {
         let mut imgui_texture_map = Textures::new();
 
-        let font_atlas_texture =
-            prepare_font_atlas(imgui_context.fonts(), &mut imgui_texture_map).unwrap();
-        let shaders = Shaders::new().unwrap();
-        let vbo_handle = unsafe {
-            let mut id = 0;
-            gl::GenBuffers(1, &mut id);
-            if id == 0 {
-                Err(String::from("Failed to create buffer object"))
-            } else {
-                Ok(id)
-            }
-        }
-        .unwrap();
-        let ebo_handle = unsafe {
-            let mut id = 0;
-            gl::GenBuffers(1, &mut id);
-            if id == 0 {
-                Err(String::from("Failed to create buffer object"))
-            } else {
-                Ok(id)
-            }
-        }
-        .unwrap();
-        Self {
+        let font_atlas_texture = prepare_font_atlas(imgui_context.fonts(), &mut imgui_texture_map)?;
+        let shaders = Shaders::new()?;
+        let vbo_handle = unsafe { create_object("buffer", gl::GenBuffers) }?;
+        let ebo_handle = unsafe { create_object("buffer", gl::GenBuffers) }?;
+        Ok(Self {
             shaders,
             imgui_texture_map,
             font_atlas_texture,
             vbo_handle,
             ebo_handle,
-        }
+        })
     }
     fn pre_render(&mut self, data: &DrawData, frame_width: f32, frame_height: f32) {
         unsafe {
@@ -181,6 +167,7 @@ impl ImguiRenderer {
 
         unsafe {
             gl::UseProgram(self.shaders.program.id());
+            stats::record_program_bind();
             gl::Uniform1i(self.shaders.texture_uniform_location, 0);
             gl::UniformMatrix4fv(
                 self.shaders.matrix_uniform_location,
@@ -229,15 +216,18 @@ impl ImguiRenderer {
         }
     }
 
-    pub fn render(&mut self, data: &DrawData) {
+    /// Draw `data`, the UI imgui built this frame.
+    ///
+    /// # Errors
+    /// Errors if OpenGL reports an error while drawing.
+    pub fn render(&mut self, data: &DrawData) -> Result<(), RenderError> {
         let frame_width = data.display_size[0] * data.framebuffer_scale[0];
         let frame_height = data.display_size[1] * data.framebuffer_scale[1];
         if frame_width <= 0.0 || frame_height <= 0.0 {
-            return;
+            return Ok(());
         }
-        let mut vertex_array_object = 0;
+        let vertex_array_object = unsafe { create_object("vertex array", gl::CreateVertexArrays) }?;
         unsafe {
-            gl::CreateVertexArrays(1, &mut vertex_array_object);
             gl::BindVertexArray(vertex_array_object);
         }
         self.pre_render(data, frame_width, frame_height);
@@ -250,6 +240,7 @@ impl ImguiRenderer {
                     vtx_slice.as_ptr().cast(),
                     gl::STREAM_DRAW,
                 );
+                stats::record_upload(vtx_slice.len());
                 let idx_slice = to_byte_slice(draw_list.idx_buffer());
                 gl::BufferData(
                     gl::ELEMENT_ARRAY_BUFFER,
@@ -257,6 +248,7 @@ impl ImguiRenderer {
                     idx_slice.as_ptr().cast(),
                     gl::STREAM_DRAW,
                 );
+                stats::record_upload(idx_slice.len());
             }
             for command in draw_list.commands() {
                 match command {
@@ -274,6 +266,7 @@ impl ImguiRenderer {
             gl::DeleteVertexArrays(1, &vertex_array_object);
         }
         self.post_render();
+        check_gl("imgui render")
     }
 
     fn post_render(&mut self) {
@@ -324,6 +317,7 @@ impl ImguiRenderer {
                 gl::TEXTURE_2D,
                 self.imgui_texture_map.gl_texture(texture_id).unwrap(),
             );
+            stats::record_texture_bind();
 
             gl::DrawElementsBaseVertex(
                 gl::TRIANGLES,
@@ -333,6 +327,7 @@ impl ImguiRenderer {
                 vtx_offset as _,
             );
         }
+        stats::record_draw(gl::TRIANGLES, element_count);
     }
 
     fn configure_imgui_context(&self, imgui_context: &mut Context) {
@@ -366,22 +361,15 @@ pub struct Shaders {
 impl Shaders {
     /// Create a new [`Shaders`].
     fn new() -> Result<Self, ShaderError> {
-        const VERTEX_BODY: &str = include_str!("../../glsl/imgui/vert.glsl");
-        const FRAGMENT_BODY: &str = include_str!("../../glsl/imgui/frag.glsl");
+        let preprocessor = Preprocessor::new();
+        let vertex_source = preprocessor.process("imgui/vert.glsl")?;
+        let fragment_source = preprocessor.process("imgui/frag.glsl")?;
 
-        let vertex_source = CString::from_str(VERTEX_BODY).unwrap();
-        let fragment_source = CString::from_str(FRAGMENT_BODY).unwrap();
+        let vertex_shader = Shader::from_processed(ShaderType::Vertex, vertex_source).compile()?;
+        let fragment_shader =
+            Shader::from_processed(ShaderType::Fragment, fragment_source).compile()?;
 
-        let vertex_shader = Shader::vertex(vertex_source).compile().map_err(|str| {
-            ShaderError::CreateShader(format!("Error on create vert shader: {str}"))
-        })?;
-
-        let fragment_shader = Shader::fragment(fragment_source).compile().map_err(|str| {
-            ShaderError::CreateShader(format!("Error on create frag shader: {str}"))
-        })?;
-
-        let program = construct_program!(vertex_shader, fragment_shader;)
-            .map_err(ShaderError::CreateProgram)?;
+        let program = construct_program!(vertex_shader, fragment_shader;)?;
 
         Ok(Self {
             texture_uniform_location: program
@@ -407,79 +395,16 @@ impl Shaders {
     }
 }
 
-/// Errors from creating a [`Shaders`] struct.
-#[derive(Debug)]
-#[allow(clippy::missing_docs_in_private_items)]
-pub enum ShaderError {
-    IncompatibleVersion(String),
-    CreateShader(String),
-    CreateProgram(String),
-    CompileShader(String),
-    LinkProgram(String),
-    UniformNotFound(Cow<'static, str>),
-    AttributeNotFound(Cow<'static, str>),
-}
-
-impl Error for ShaderError {}
-
-impl Display for ShaderError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::IncompatibleVersion(msg) => write!(
-                f,
-                "Shader not compatible with OpenGL version found in the context: {}",
-                msg
-            ),
-            Self::CreateShader(msg) => write!(f, "Error creating shader object: {}", msg),
-            Self::CreateProgram(msg) => write!(f, "Error creating program object: {}", msg),
-            Self::CompileShader(msg) => write!(f, "Error compiling shader: {}", msg),
-            Self::LinkProgram(msg) => write!(f, "Error linking shader program: {}", msg),
-            Self::UniformNotFound(uniform_name) => {
-                write!(f, "Uniform `{}` not found in shader program", uniform_name)
-            }
-            Self::AttributeNotFound(attribute_name) => {
-                write!(
-                    f,
-                    "Attribute `{}` not found in shader program",
-                    attribute_name
-                )
-            }
-        }
-    }
-}
-
-/// Errors that may arise from creating the font atlas texture.
-#[derive(Debug)]
-#[allow(clippy::missing_docs_in_private_items)]
-pub enum InitError {
-    Shader(ShaderError),
-    CreateBufferObject(String),
-    CreateTexture(String),
-    RegisterTexture,
-    UserError(String),
-}
-
 /// Create the font atlas texture in OpenGL and return its index.
 fn prepare_font_atlas<T: TextureMap>(
     fonts: &mut FontAtlas,
     texture_map: &mut T,
-) -> Result<gl::types::GLuint, InitError> {
+) -> Result<gl::types::GLuint, RenderError> {
     #![allow(clippy::cast_possible_wrap)]
 
     let atlas_texture = fonts.build_rgba32_texture();
 
-    let gl_texture = unsafe {
-        // new: put the genned texture in a maybeuninit
-        let mut tex_id: gl::types::GLuint = 0;
-        gl::GenTextures(1, &mut tex_id);
-        if tex_id == 0 {
-            Err(InitError::CreateTexture(String::from(
-                "Unable to create Texture object",
-            )))
-        } else {
-            Ok(tex_id)
-        }
-    }?;
+    let gl_texture = unsafe { create_object("texture", gl::GenTextures) }?;
 
     unsafe {
         gl::BindTexture(gl::TEXTURE_2D, gl_texture);
@@ -500,7 +425,7 @@ fn prepare_font_atlas<T: TextureMap>(
 
     fonts.tex_id = texture_map
         .register(gl_texture)
-        .ok_or(InitError::RegisterTexture)?;
+        .ok_or(RenderError::RegisterTexture)?;
 
     Ok(gl_texture)
 }
@@ -7,18 +7,27 @@
 compile_error!("rustdoc does not support generic const expressions");
 
 use std::{
-    ffi::CStr,
     ops::Deref,
     ptr::{null, slice_from_raw_parts},
+    time::Instant,
 };
 
 extern crate world;
 
+pub mod debug_draw;
+pub mod error;
+pub mod frame_uniforms;
+pub mod frustum;
 pub mod gl_wrappers;
+pub mod gpu_timing;
+pub mod profiler;
 pub mod imgui_wrappers;
+pub mod render_mode;
 pub mod render_vec;
+pub mod stats;
 pub mod vector3;
 
+pub use error::RenderError;
 pub use gl;
 pub use gl_wrappers::gl_upd_viewport;
 pub use glm;
@@ -28,12 +37,24 @@ use gl::types as gltype;
 use glm::Vec3;
 use sdl2::video::GLContext;
 use world::{
-    Vertex,
-    brush::{BrushPlane, NGonPlane, TriPlane},
+    Aabb, Vertex, World,
+    brush::{Brush, BrushPlane, NGonPlane, TriPlane},
+    component::IDENTITY,
 };
 
 use crate::{
-    gl_wrappers::{program::Program, shader::Shader},
+    debug_draw::DebugDrawPass,
+    error::{check_gl, create_object},
+    frame_uniforms::{FRAME_UNIFORMS_BINDING, FRAME_UNIFORMS_BLOCK, FrameUniforms, PointLight},
+    frustum::Frustum,
+    gl_wrappers::{
+        UniformBuffer,
+        diagnostics::ShaderError,
+        preprocessor::Preprocessor,
+        program::Program,
+        shader::{Shader, ShaderType},
+    },
+    render_mode::{OVERDRAW_COLOUR, RenderMode, WIREFRAME_OVERLAY_COLOUR, brush_colour},
     render_vec::{GlTypeList, RenderVec, StaticGlLayout},
     vector3::to_byte_slice,
 };
@@ -43,25 +64,71 @@ use crate::{
 pub struct ScreenSpaceMesh {
     /// Internal vector of planes to be rendered.
     planes: Vec<BrushPlane>,
+    /// The brush each plane in `planes` came from.
+    /// Loose planes count as a brush of their own.
+    brush_ids: Vec<u32>,
+    /// Bounds of each brush, indexed by brush id.
+    brush_bounds: Vec<Option<Aabb>>,
 }
 
 impl ScreenSpaceMesh {
     /// Create a new mesh.
     pub fn new() -> Self {
-        Self { planes: vec![] }
+        Self {
+            planes: vec![],
+            brush_ids: vec![],
+            brush_bounds: vec![],
+        }
+    }
+    /// Create a mesh holding every brush in `world`.
+    pub fn from_world(world: &World) -> Self {
+        let mut ret = Self::new();
+        for brush in world.brushes() {
+            ret.add_brush(brush.as_ref());
+        }
+        ret
     }
     /// Add a triangle to the internal vector.
     pub fn add_tri(&mut self, tri: TriPlane) {
-        self.planes.push(BrushPlane::Triangle(tri));
+        self.add_loose_plane(BrushPlane::Triangle(tri));
     }
     /// Add an N-gon to the internal vector.
     pub fn add_ngon(&mut self, ngon: NGonPlane) {
-        self.planes.push(BrushPlane::NGon(ngon))
+        self.add_loose_plane(BrushPlane::NGon(ngon));
+    }
+    /// Add a plane that isn't part of a brush.
+    fn add_loose_plane(&mut self, plane: BrushPlane) {
+        self.brush_ids.push(self.next_brush_id());
+        self.brush_bounds.push(plane.bounds());
+        self.planes.push(plane);
+    }
+    /// Add every plane of `brush`, remembering they belong together.
+    pub fn add_brush(&mut self, brush: &dyn Brush) {
+        let id = self.next_brush_id();
+        for plane in brush.planes() {
+            self.planes.push(plane.clone());
+            self.brush_ids.push(id);
+        }
+        self.brush_bounds.push(brush.bounds());
+    }
+    /// The id the next brush added will get.
+    fn next_brush_id(&self) -> u32 {
+        self.brush_bounds.len().try_into().unwrap()
+    }
+    /// Number of brushes, counting each loose plane as one.
+    pub fn brush_count(&self) -> usize {
+        self.brush_bounds.len()
+    }
+    /// Whether there is nothing to render.
+    pub fn is_empty(&self) -> bool {
+        self.planes.is_empty()
     }
 
     /// Clears the internal vector, removing all polys.
     pub fn clear(&mut self) {
         self.planes.clear();
+        self.brush_ids.clear();
+        self.brush_bounds.clear();
     }
 
     /// create this struct with one triangle
@@ -97,14 +164,25 @@ impl ScreenSpaceMesh {
 }
 
 /// A camera in the scene.
+#[derive(Clone)]
 pub struct Camera {
     /// Position in world space.
     pos: Vec3,
-    /// XYZ Euler angles. (0,0,0) means upwards.
+    /// XYZ Euler angles in degrees. (0,0,0) means upwards.
     /// X: Roll
     /// Y: Pitch
     /// Z: Yaw
+    ///
+    /// The view direction is upwards, rotated around X, then Y, then Z.
     orientation: Vec3,
+    /// Vertical field of view in degrees.
+    fov_y: f32,
+    /// Width of the viewport divided by its height.
+    aspect: f32,
+    /// Distance to the near clipping plane.
+    near: f32,
+    /// Distance to the far clipping plane.
+    far: f32,
 }
 
 impl Default for Camera {
@@ -112,32 +190,120 @@ impl Default for Camera {
         Self {
             orientation: glm::vec3(-90.0, 0.0, 0.0),
             pos: glm::to_vec3(0.),
+            fov_y: 90.0,
+            aspect: 4.0 / 3.0,
+            near: 0.1,
+            far: 1000.0,
         }
     }
 }
 
+impl Camera {
+    /// Position in world space.
+    pub fn position(&self) -> Vec3 {
+        self.pos
+    }
+    /// Move the camera to `pos`.
+    pub fn set_position(&mut self, pos: Vec3) {
+        self.pos = pos;
+    }
+    /// XYZ Euler angles in degrees.
+    pub fn orientation(&self) -> Vec3 {
+        self.orientation
+    }
+    /// Point the camera with XYZ Euler angles in degrees.
+    pub fn set_orientation(&mut self, orientation: Vec3) {
+        self.orientation = orientation;
+    }
+    /// Vertical field of view in degrees.
+    pub fn fov_y(&self) -> f32 {
+        self.fov_y
+    }
+    /// Set the vertical field of view in degrees.
+    pub fn set_fov_y(&mut self, fov_y: f32) {
+        self.fov_y = fov_y;
+    }
+    /// Width of the viewport divided by its height.
+    pub fn aspect(&self) -> f32 {
+        self.aspect
+    }
+    /// Match the aspect ratio to a viewport of `width` by `height`.
+    pub fn set_viewport_size(&mut self, width: u32, height: u32) {
+        if height != 0 {
+            self.aspect = width as f32 / height as f32;
+        }
+    }
+    /// Distances to the near and far clipping planes.
+    pub fn clip_distances(&self) -> (f32, f32) {
+        (self.near, self.far)
+    }
+
+    /// The normalized direction the camera is looking in.
+    pub fn forward(&self) -> Vec3 {
+        let [x, y, z] = [
+            self.orientation.x.to_radians(),
+            self.orientation.y.to_radians(),
+            self.orientation.z.to_radians(),
+        ];
+        // upwards, rotated around X
+        let dir = glm::vec3(0.0, x.cos(), x.sin());
+        // then around Y
+        let dir = glm::vec3(dir.x * y.cos() + dir.z * y.sin(), dir.y, dir.z * y.cos() - dir.x * y.sin());
+        // then around Z
+        let dir = glm::vec3(dir.x * z.cos() - dir.y * z.sin(), dir.x * z.sin() + dir.y * z.cos(), dir.z);
+        glm::normalize(dir)
+    }
+    /// World space to view space.
+    pub fn view_matrix(&self) -> glm::Mat4 {
+        let forward = self.forward();
+        // looking straight up or down, pick another up so the basis isn't degenerate
+        let up = if glm::abs(forward.y) > 0.999 {
+            glm::vec3(0.0, 0.0, -forward.y.signum())
+        } else {
+            glm::vec3(0.0, 1.0, 0.0)
+        };
+        glm::ext::look_at(self.pos, self.pos + forward, up)
+    }
+    /// View space to clip space.
+    pub fn projection_matrix(&self) -> glm::Mat4 {
+        glm::ext::perspective(self.fov_y.to_radians(), self.aspect, self.near, self.far)
+    }
+    /// The planes bounding what this camera can see.
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_matrix(&(self.projection_matrix() * self.view_matrix()))
+    }
+}
+
+/// How many brushes the last [`Render::render_world`], and the [`Render::render_model`] calls
+/// after it, drew and skipped.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CullStats {
+    /// Brushes at least partly in view, which were drawn.
+    pub drawn: usize,
+    /// Brushes entirely outside the view, which were skipped.
+    pub culled: usize,
+}
+
 pub struct Render {
     vbo: gltype::GLuint,
     vao: gltype::GLuint,
     program: Program,
+    /// Per-frame data shared by every program.
+    frame_uniforms: UniformBuffer<FrameUniforms>,
+    /// Lights uploaded with the frame uniforms.
+    lights: Vec<PointLight>,
+    /// When this was created, for [`FrameUniforms::time`].
+    created_at: Instant,
+    /// Draws whatever was queued through [`debug_draw`].
+    debug_draw: DebugDrawPass,
+    /// How the world is drawn.
+    render_mode: RenderMode,
+    /// Whether brushes outside the camera's view are skipped.
+    frustum_culling: bool,
+    /// Counts from the last world render.
+    cull_stats: CullStats,
 }
 
-macro_rules! include_cstr {
-    ( $path:literal $(,)? ) => {{
-        // Use a constant to force the verification to run at compile time.
-        const VALUE: &'static ::core::ffi::CStr = match ::core::ffi::CStr::from_bytes_with_nul(
-            concat!(include_str!($path), "\0").as_bytes(),
-        ) {
-            Ok(value) => value,
-            Err(_) => panic!(concat!("interior NUL byte(s) in `", $path, "`")),
-        };
-        VALUE
-    }};
-}
-
-const FRAG_SHADER_SOURCE: &CStr = include_cstr!("../glsl/frag_shader.glsl");
-const VERT_SHADER_SOURCE: &CStr = include_cstr!("../glsl/vert_shader.glsl");
-
 static mut INITIALIZED_ALREADY: bool = false;
 
 // macro_rules! push_vertex_to_vec {
@@ -183,28 +349,30 @@ unsafe impl StaticGlLayout for InputParams {
 }
 
 impl Render {
-    pub fn init(gl_ctx: &GLContext) -> Self {
+    /// Create the renderer, and everything it draws with.
+    ///
+    /// # Errors
+    /// Errors if a GL object or shader couldn't be created, or a [`Render`] was already created.
+    pub fn init(gl_ctx: &GLContext) -> Result<Self, RenderError> {
         assert!(
             gl_ctx.is_current(),
             "gl_ctx must be current in order to create a Render"
         );
-        let (vao, vbo, program) = unsafe {
+        let (vao, vbo, program, frame_uniforms) = unsafe {
             if INITIALIZED_ALREADY {
-                panic!("Cannot initialize Render more than once");
+                return Err(RenderError::AlreadyInitialized("Render"));
             }
             INITIALIZED_ALREADY = true;
 
-            let mut vbo = 0;
-            gl::GenBuffers(1, &mut vbo);
-            let mut vao = 0;
-            gl::GenVertexArrays(1, &mut vao);
+            let vbo = create_object("buffer", gl::GenBuffers)?;
+            let vao = create_object("vertex array", gl::GenVertexArrays)?;
 
             // bind the Vertex Array Object first, then bind and set vertex buffers, and then configure attributes
             gl::BindVertexArray(vao);
             gl::BindBuffer(gl::ARRAY_BUFFER, vbo);
 
-            eprintln!("Render vbo: {vbo}");
-            eprintln!("Render vao: {vao}");
+            log::debug!("Render vbo: {vbo}");
+            log::debug!("Render vao: {vao}");
 
             // we're NamedBufferData-ing this later when we need to use it
             gl::BufferData(gl::ARRAY_BUFFER, 0, null(), gl::DYNAMIC_DRAW);
@@ -234,37 +402,143 @@ impl Render {
             // let vert_shader = gl::CreateShader(gl::VERTEX_SHADER);
             // compile_shader(vert_shader, VERT_SHADER_SOURCE).unwrap();
 
-            let vert_shader = Shader::vertex(VERT_SHADER_SOURCE.into()).compile().unwrap();
+            let preprocessor = Preprocessor::new();
+
+            let vert_shader = preprocessor
+                .process("vert_shader.glsl")
+                .map_err(ShaderError::from)
+                .and_then(|source| Shader::from_processed(ShaderType::Vertex, source).compile())?;
 
             // let frag_shader = gl::CreateShader(gl::FRAGMENT_SHADER);
             // compile_shader(frag_shader, FRAG_SHADER_SOURCE).unwrap();
 
-            let frag_shader = Shader::fragment(FRAG_SHADER_SOURCE.into())
-                .compile()
-                .unwrap();
+            let frag_shader = preprocessor
+                .process("frag_shader.glsl")
+                .map_err(ShaderError::from)
+                .and_then(|source| {
+                    Shader::from_processed(ShaderType::Fragment, source).compile()
+                })?;
 
-            let program = construct_program!(vert_shader, frag_shader;).unwrap();
+            let program = construct_program!(vert_shader, frag_shader;)?;
+
+            let frame_uniforms = UniformBuffer::new(
+                FRAME_UNIFORMS_BINDING,
+                &FrameUniforms::new(&Camera::default(), 0., &[]),
+            )?;
+
+            if cfg!(debug_assertions) {
+                program.validate_layout::<InputParams>()?;
+                frame_uniforms.validate_block(&program, FRAME_UNIFORMS_BLOCK)?;
+            }
 
             // let program = link_program!(vert_shader, frag_shader).unwrap();
-            gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
 
             // reset bound arrays
             gl::BindVertexArray(0);
             gl::BindBuffer(gl::ARRAY_BUFFER, 0);
-            (vao, vbo, program)
+            check_gl("Render::init")?;
+            (vao, vbo, program, frame_uniforms)
         };
+        let debug_draw = DebugDrawPass::new()?;
+
+        Ok(Render {
+            vao,
+            vbo,
+            program,
+            frame_uniforms,
+            lights: vec![],
+            created_at: Instant::now(),
+            debug_draw,
+            render_mode: RenderMode::default(),
+            frustum_culling: true,
+            cull_stats: CullStats::default(),
+        })
+    }
+
+    /// Set the lights uploaded with each frame.
+    /// Only the first [`MAX_LIGHTS`](frame_uniforms::MAX_LIGHTS) are used.
+    pub fn set_lights(&mut self, lights: &[PointLight]) {
+        self.lights.clear();
+        self.lights.extend_from_slice(lights);
+    }
 
-        Render { vao, vbo, program }
+    /// How the world is drawn.
+    pub fn render_mode(&self) -> RenderMode {
+        self.render_mode
+    }
+    /// Change how the world is drawn, from the next [`Render::render_world`] on.
+    pub fn set_render_mode(&mut self, mode: RenderMode) {
+        self.render_mode = mode;
     }
 
-    pub fn clear(&mut self) -> Result<(), ()> {
+    /// Skip drawing brushes that are entirely outside the camera's view.
+    pub fn set_frustum_culling(&mut self, enabled: bool) {
+        self.frustum_culling = enabled;
+    }
+    /// How many brushes the last [`Render::render_world`], and the [`Render::render_model`]
+    /// calls after it, drew and skipped.
+    pub fn cull_stats(&self) -> CullStats {
+        self.cull_stats
+    }
+
+    /// Clear the colour and depth buffers.
+    ///
+    /// # Errors
+    /// Errors if OpenGL reports an error, including ones left over from earlier calls.
+    pub fn clear(&mut self) -> Result<(), RenderError> {
         unsafe {
-            gl::ClearColor(0.2, 0.2, 0.3, 1.0);
-            gl::Clear(gl::COLOR_BUFFER_BIT);
+            if self.render_mode == RenderMode::Overdraw {
+                // black, so the heat from overlapping faces is all that shows
+                gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+            } else {
+                gl::ClearColor(0.2, 0.2, 0.3, 1.0);
+            }
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
         }
-        Ok(())
+        check_gl("glClear")
     }
-    pub fn render_world(&mut self, world: &ScreenSpaceMesh) -> Result<(), ()> {
+    /// Draw the parts of `world` that `camera` can see.
+    ///
+    /// # Errors
+    /// Errors if OpenGL reports an error while drawing.
+    pub fn render_world(
+        &mut self,
+        world: &ScreenSpaceMesh,
+        camera: &Camera,
+    ) -> Result<(), RenderError> {
+        crate::profile_scope!("render_world");
+        let time = self.created_at.elapsed().as_secs_f32();
+        self.frame_uniforms
+            .update(&FrameUniforms::new(camera, time, &self.lights));
+        self.cull_stats = CullStats::default();
+        self.draw_mesh(world, &IDENTITY, camera)
+    }
+    /// Draw the parts of `mesh`, whose vertices are in model space, that `camera` can see,
+    /// moved into world space by `model`. Brushes it skips or draws count towards
+    /// [`Render::cull_stats`].
+    /// Call after [`Render::render_world`], which sets up the frame.
+    ///
+    /// # Errors
+    /// Errors if OpenGL reports an error while drawing.
+    pub fn render_model(
+        &mut self,
+        mesh: &ScreenSpaceMesh,
+        model: &glm::Mat4,
+        camera: &Camera,
+    ) -> Result<(), RenderError> {
+        crate::profile_scope!("render_model");
+        self.draw_mesh(mesh, model, camera)
+    }
+    /// Draw `mesh` with the `model` matrix, skipping brushes outside `camera`'s view.
+    ///
+    /// # Errors
+    /// Errors if OpenGL reports an error while drawing.
+    fn draw_mesh(
+        &mut self,
+        mesh: &ScreenSpaceMesh,
+        model: &glm::Mat4,
+        camera: &Camera,
+    ) -> Result<(), RenderError> {
         let mut render_vec: RenderVec<InputParams> = RenderVec::new();
         // let mut vertex_arr: Vec<f64> = Vec::with_capacity(world.triangles.len() * 9);
         // dbg!(&world);
@@ -278,26 +552,57 @@ impl Render {
         //     push_vertex_to_vec!(vertex_arr, tri.2);
         //     vertex_arr.extend_from_slice(&colors);
         // }
-        let color = glm::vec3(0.584, 0.203, 0.92);
-        for tri in world.planes.iter() {
-            let plane = tri.clone();
-            render_vec.push(InputParams {
-                position: plane[0].pos,
-                color,
-            });
-            render_vec.push(InputParams {
-                position: plane[1].pos,
-                color,
-            });
-            render_vec.push(InputParams {
-                position: plane[2].pos,
-                color,
-            });
+        let frustum = camera.frustum();
+        let to_world = |point: Vec3, w: f32| {
+            let moved = *model * glm::vec4(point.x, point.y, point.z, w);
+            glm::vec3(moved.x, moved.y, moved.z)
+        };
+        let visible: Vec<bool> = mesh
+            .brush_bounds
+            .iter()
+            .map(|bounds| {
+                let bounds = bounds.and_then(|bounds| {
+                    Aabb::from_points(bounds.corners().map(|corner| to_world(corner, 1.)))
+                });
+                match bounds {
+                    Some(bounds) if self.frustum_culling => frustum.intersects_aabb(&bounds),
+                    _ => true,
+                }
+            })
+            .collect();
+        let drawn = visible.iter().filter(|visible| **visible).count();
+        self.cull_stats.drawn += drawn;
+        self.cull_stats.culled += visible.len() - drawn;
+
+        let default_color = glm::vec3(0.584, 0.203, 0.92);
+        for (plane, &brush_id) in mesh.planes.iter().zip(mesh.brush_ids.iter()) {
+            if !visible[brush_id as usize] {
+                continue;
+            }
+            let color = match self.render_mode {
+                RenderMode::Normals => plane.normal().map_or(glm::to_vec3(0.), |normal| {
+                    glm::normalize(to_world(normal, 0.)) * 0.5 + glm::to_vec3(0.5)
+                }),
+                RenderMode::BrushColours => brush_colour(brush_id),
+                _ => default_color,
+            };
+            // planes are convex, so a fan around the first vertex covers them
+            let verts = plane.vertices();
+            for pair in verts[1..].windows(2) {
+                for vert in [&verts[0], &pair[0], &pair[1]] {
+                    render_vec.push(InputParams {
+                        position: vert.pos,
+                        color,
+                    });
+                }
+            }
         }
         // dbg!(&vertex_arr);
         unsafe {
             // let mut arr = vertex_arr.into_boxed_slice();
             gl::UseProgram(self.program.id());
+            stats::record_program_bind();
+            self.program.set("model", *model);
             gl::BindVertexArray(self.vao);
             // gl::NamedBufferData(
             //     self.vbo,
@@ -311,6 +616,7 @@ impl Render {
                 render_vec.gl_data(),
                 gl::DYNAMIC_DRAW,
             );
+            stats::record_upload(render_vec.gl_byte_size().unsigned_abs());
 
             // if cfg!(debug_assertions) {
             //     let slice: &[f32] = std::slice::from_raw_parts(
@@ -329,17 +635,74 @@ impl Render {
             //     }
             //     println!("]");
             // }
-            // let error_pre = gl::GetError() == gl::TRUE.into();
+            check_gl("glNamedBufferData")?;
             gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
-            gl::DrawArrays(gl::TRIANGLES, 0, render_vec.gl_len());
+            self.draw_world_vertices(render_vec.gl_len());
 
             gl::BindVertexArray(0);
-            let error_post = gl::GetError() == gl::TRUE.into();
-            if error_post {
-                eprintln!("There was an error after gl::DrawArrays !!!");
+        }
+        check_gl("glDrawArrays")
+    }
+    /// Draw the uploaded world vertices with the state for the current [`RenderMode`].
+    ///
+    /// # Safety
+    /// The world program and vertex array must be bound.
+    unsafe fn draw_world_vertices(&self, count: gltype::GLsizei) {
+        let no_override = glm::vec4(0., 0., 0., 0.);
+        let with_override = |colour: Vec3| glm::vec4(colour.x, colour.y, colour.z, 1.);
+        let draw = || unsafe {
+            gl::DrawArrays(gl::TRIANGLES, 0, count);
+            stats::record_draw(gl::TRIANGLES, count.unsigned_abs() as usize);
+        };
+        unsafe {
+            match self.render_mode {
+                RenderMode::Solid | RenderMode::Normals | RenderMode::BrushColours => {
+                    gl::Enable(gl::DEPTH_TEST);
+                    self.program.set("colour_override", no_override);
+                    draw();
+                }
+                RenderMode::Wireframe => {
+                    gl::Enable(gl::DEPTH_TEST);
+                    gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
+                    self.program.set("colour_override", no_override);
+                    draw();
+                }
+                RenderMode::WireframeOverSolid => {
+                    gl::Enable(gl::DEPTH_TEST);
+                    self.program.set("colour_override", no_override);
+                    draw();
+                    // pull the edges towards the camera so they don't z-fight the faces
+                    gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
+                    gl::Enable(gl::POLYGON_OFFSET_LINE);
+                    gl::PolygonOffset(-1., -1.);
+                    self.program
+                        .set("colour_override", with_override(WIREFRAME_OVERLAY_COLOUR));
+                    draw();
+                    gl::Disable(gl::POLYGON_OFFSET_LINE);
+                }
+                RenderMode::Overdraw => {
+                    gl::Disable(gl::DEPTH_TEST);
+                    gl::Enable(gl::BLEND);
+                    gl::BlendFunc(gl::ONE, gl::ONE);
+                    self.program
+                        .set("colour_override", with_override(OVERDRAW_COLOUR));
+                    draw();
+                    gl::Disable(gl::BLEND);
+                }
             }
+            // leave the defaults for whatever draws next
+            gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+            gl::Disable(gl::DEPTH_TEST);
         }
-        Ok(())
+    }
+    /// Draw everything queued through [`debug_draw`] this frame.
+    /// Call after [`Render::render_world`], so the lines can be hidden by the world.
+    ///
+    /// # Errors
+    /// Errors if OpenGL reports an error while drawing.
+    pub fn render_debug_draw(&mut self) -> Result<(), RenderError> {
+        crate::profile_scope!("render_debug_draw");
+        self.debug_draw.render()
     }
 }
 
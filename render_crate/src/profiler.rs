@@ -0,0 +1,304 @@
+//! A hierarchical CPU profiler.
+//!
+//! Time a block by putting [`profile_scope!`](crate::profile_scope) at the top of it:
+//! ```no_run
+//! fn load() {
+//!     profile_scope!("load");
+//!     {
+//!         profile_scope!("parse_map");
+//!         // ...
+//!     }
+//! }
+//! ```
+//! Scopes are recorded into the current frame, and [`end_frame`] moves it into a
+//! ring of the last [`FRAME_HISTORY_LEN`] frames. Each thread has its own profiler.
+use std::{
+    cell::RefCell,
+    collections::VecDeque,
+    fmt::Write as _,
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+/// Number of finished frames kept.
+pub const FRAME_HISTORY_LEN: usize = 300;
+
+/// One timed scope.
+#[derive(Clone, Debug)]
+pub struct ScopeRecord {
+    /// The name given to [`profile_scope!`](crate::profile_scope).
+    pub name: &'static str,
+    /// When the scope started, since the profiler was created.
+    pub start: Duration,
+    /// How long the scope took. Zero while it's still running.
+    pub duration: Duration,
+    /// How many scopes this is nested inside.
+    pub depth: u32,
+}
+
+/// Every scope recorded during one frame.
+#[derive(Clone, Debug)]
+pub struct FrameRecord {
+    /// Counts up from 0 with each frame.
+    pub index: u64,
+    /// When the frame started, since the profiler was created.
+    pub start: Duration,
+    /// How long the whole frame took.
+    pub duration: Duration,
+    /// The scopes, in the order they started.
+    pub scopes: Vec<ScopeRecord>,
+}
+
+/// The state of the profiler on one thread.
+struct Profiler {
+    /// Every time is measured from here.
+    epoch: Instant,
+    /// The frame being recorded.
+    current: FrameRecord,
+    /// Depth of the next scope to start.
+    depth: u32,
+    /// Finished frames, oldest first.
+    history: VecDeque<FrameRecord>,
+    /// While paused, finished frames are thrown away instead of added to `history`.
+    paused: bool,
+}
+
+impl Profiler {
+    /// Create a profiler, starting the first frame now.
+    fn new() -> Self {
+        Self {
+            epoch: Instant::now(),
+            current: FrameRecord {
+                index: 0,
+                start: Duration::ZERO,
+                duration: Duration::ZERO,
+                scopes: vec![],
+            },
+            depth: 0,
+            history: VecDeque::with_capacity(FRAME_HISTORY_LEN),
+            paused: false,
+        }
+    }
+}
+
+thread_local! {
+    /// The profiler for this thread.
+    static PROFILER: RefCell<Profiler> = RefCell::new(Profiler::new());
+}
+
+/// Records a scope while alive. Made by [`profile_scope!`](crate::profile_scope).
+pub struct ScopeGuard {
+    /// Index of the record in the current frame.
+    index: usize,
+    /// The frame the record is in.
+    frame: u64,
+}
+
+impl ScopeGuard {
+    /// Start recording a scope called `name`.
+    pub fn new(name: &'static str) -> Self {
+        PROFILER.with_borrow_mut(|profiler| {
+            let start = profiler.epoch.elapsed();
+            let depth = profiler.depth;
+            profiler.depth += 1;
+            profiler.current.scopes.push(ScopeRecord {
+                name,
+                start,
+                duration: Duration::ZERO,
+                depth,
+            });
+            Self {
+                index: profiler.current.scopes.len() - 1,
+                frame: profiler.current.index,
+            }
+        })
+    }
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        let _ = PROFILER.try_with(|profiler| {
+            let mut profiler = profiler.borrow_mut();
+            profiler.depth = profiler.depth.saturating_sub(1);
+            // a scope still open when the frame ended stays at zero length
+            if profiler.current.index != self.frame {
+                return;
+            }
+            let now = profiler.epoch.elapsed();
+            let record = &mut profiler.current.scopes[self.index];
+            record.duration = now - record.start;
+        });
+    }
+}
+
+/// Time the rest of the enclosing block as a scope called `$name`.
+///
+/// # Usage
+/// ```no_run
+/// profile_scope!("render_world");
+/// ```
+#[macro_export]
+macro_rules! profile_scope {
+    ($name:expr) => {
+        let _profile_scope = $crate::profiler::ScopeGuard::new($name);
+    };
+}
+
+/// Finish the current frame and start the next. Call once per frame.
+pub fn end_frame() {
+    PROFILER.with_borrow_mut(|profiler| {
+        let now = profiler.epoch.elapsed();
+        let next = FrameRecord {
+            index: profiler.current.index + 1,
+            start: now,
+            duration: Duration::ZERO,
+            scopes: Vec::with_capacity(profiler.current.scopes.len()),
+        };
+        let mut finished = std::mem::replace(&mut profiler.current, next);
+        finished.duration = now - finished.start;
+        if profiler.paused {
+            return;
+        }
+        if profiler.history.len() == FRAME_HISTORY_LEN {
+            profiler.history.pop_front();
+        }
+        profiler.history.push_back(finished);
+    });
+}
+
+/// Stop or resume adding finished frames to the history, so it can be inspected.
+pub fn set_paused(paused: bool) {
+    PROFILER.with_borrow_mut(|profiler| profiler.paused = paused);
+}
+
+/// Whether finished frames are being thrown away.
+pub fn is_paused() -> bool {
+    PROFILER.with_borrow(|profiler| profiler.paused)
+}
+
+/// Look at the finished frames, oldest first.
+pub fn with_history<R>(f: impl FnOnce(&VecDeque<FrameRecord>) -> R) -> R {
+    PROFILER.with_borrow(|profiler| f(&profiler.history))
+}
+
+/// Write `frames` in the Chrome trace event format, for `chrome://tracing` or Perfetto.
+///
+/// <https://docs.google.com/document/d/1CvAClvFfyA5R-PhYUmn5OOQtYMH4h6I0nSsKchNAySU>
+///
+/// # Errors
+/// Errors if writing to `out` fails.
+pub fn write_chrome_trace<'a>(
+    frames: impl IntoIterator<Item = &'a FrameRecord>,
+    mut out: impl Write,
+) -> io::Result<()> {
+    let mut events = vec![];
+    for frame in frames {
+        events.push(trace_event(
+            &format!("frame {}", frame.index),
+            frame.start,
+            frame.duration,
+        ));
+        for scope in frame.scopes.iter() {
+            events.push(trace_event(scope.name, scope.start, scope.duration));
+        }
+    }
+    write!(out, "{{\"traceEvents\":[{}]}}", events.join(","))
+}
+
+/// A complete ("X") trace event, with times in microseconds.
+fn trace_event(name: &str, start: Duration, duration: Duration) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => {
+                let _ = write!(escaped, "\\u{:04x}", c as u32);
+            }
+            c => escaped.push(c),
+        }
+    }
+    format!(
+        "{{\"name\":\"{escaped}\",\"ph\":\"X\",\"ts\":{:.3},\"dur\":{:.3},\"pid\":1,\"tid\":1}}",
+        start.as_secs_f64() * 1_000_000.,
+        duration.as_secs_f64() * 1_000_000.,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escaping() {
+        let event = trace_event("a\"b\\c\nd\u{1}", Duration::ZERO, Duration::ZERO);
+        assert!(event.starts_with(r#"{"name":"a\"b\\c\u000ad\u0001","#), "{event}");
+    }
+
+    #[test]
+    fn trace_events() {
+        let frame = FrameRecord {
+            index: 7,
+            start: Duration::from_millis(1),
+            duration: Duration::from_micros(1500),
+            scopes: vec![ScopeRecord {
+                name: "render",
+                start: Duration::from_micros(1250),
+                duration: Duration::from_nanos(500),
+                depth: 0,
+            }],
+        };
+        let mut out = vec![];
+        write_chrome_trace([&frame], &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                r#"{"traceEvents":["#,
+                r#"{"name":"frame 7","ph":"X","ts":1000.000,"dur":1500.000,"pid":1,"tid":1},"#,
+                r#"{"name":"render","ph":"X","ts":1250.000,"dur":0.500,"pid":1,"tid":1}"#,
+                r#"]}"#,
+            )
+        );
+
+        let mut out = vec![];
+        write_chrome_trace([], &mut out).unwrap();
+        assert_eq!(out, br#"{"traceEvents":[]}"#);
+    }
+
+    #[test]
+    fn scopes_are_recorded() {
+        end_frame();
+        {
+            profile_scope!("outer");
+            profile_scope!("inner");
+        }
+        end_frame();
+        with_history(|history| {
+            let scopes = &history.back().unwrap().scopes;
+            let names: Vec<_> = scopes.iter().map(|scope| (scope.name, scope.depth)).collect();
+            assert_eq!(names, [("outer", 0), ("inner", 1)]);
+        });
+    }
+
+    #[test]
+    fn history_is_capped() {
+        for _ in 0..FRAME_HISTORY_LEN + 5 {
+            end_frame();
+        }
+        with_history(|history| {
+            assert_eq!(history.len(), FRAME_HISTORY_LEN);
+            assert_eq!(history.front().unwrap().index, 5);
+            assert_eq!(history.back().unwrap().index, FRAME_HISTORY_LEN as u64 + 4);
+        });
+
+        // paused frames are thrown away
+        set_paused(true);
+        end_frame();
+        set_paused(false);
+        end_frame();
+        with_history(|history| {
+            assert_eq!(history.len(), FRAME_HISTORY_LEN);
+            assert_eq!(history.back().unwrap().index, FRAME_HISTORY_LEN as u64 + 6);
+        });
+    }
+}
@@ -0,0 +1,125 @@
+//! Exports [`RenderMode`].
+use std::fmt::{self, Display};
+
+use glm::Vec3;
+
+/// How the world is drawn. Everything but [`RenderMode::Solid`] is for debugging.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Filled polygons, as normal.
+    #[default]
+    Solid,
+    /// Polygon edges only.
+    Wireframe,
+    /// Filled polygons with their edges drawn on top.
+    WireframeOverSolid,
+    /// Each face coloured by its normal, remapped from -1..1 to 0..1.
+    Normals,
+    /// Each brush in its own random colour.
+    BrushColours,
+    /// Faces added together with no depth test, so the brightest areas are drawn the most.
+    Overdraw,
+}
+
+impl RenderMode {
+    /// Every mode, in the order shown in the UI.
+    pub const ALL: [RenderMode; 6] = [
+        Self::Solid,
+        Self::Wireframe,
+        Self::WireframeOverSolid,
+        Self::Normals,
+        Self::BrushColours,
+        Self::Overdraw,
+    ];
+
+    /// A short human readable name.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Solid => "Solid",
+            Self::Wireframe => "Wireframe",
+            Self::WireframeOverSolid => "Wireframe over solid",
+            Self::Normals => "Face normals",
+            Self::BrushColours => "Brush colours",
+            Self::Overdraw => "Overdraw",
+        }
+    }
+}
+
+impl Display for RenderMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Colour of the edges in [`RenderMode::WireframeOverSolid`].
+pub(crate) const WIREFRAME_OVERLAY_COLOUR: Vec3 = Vec3 {
+    x: 1.,
+    y: 1.,
+    z: 1.,
+};
+
+/// What each face adds in [`RenderMode::Overdraw`]. Saturates after about 10 layers.
+pub(crate) const OVERDRAW_COLOUR: Vec3 = Vec3 {
+    x: 0.1,
+    y: 0.04,
+    z: 0.02,
+};
+
+/// A stable, distinct looking colour for the brush with index `id`.
+pub(crate) fn brush_colour(id: u32) -> Vec3 {
+    // stepping the hue by the golden ratio keeps neighbouring ids far apart
+    let hue = (id as f32 * 0.618_034).fract() * 6.;
+    let (saturation, value) = (0.6, 0.9);
+    let chroma = value * saturation;
+    let x = chroma * (1. - (hue % 2. - 1.).abs());
+    let (r, g, b) = match hue as u32 {
+        0 => (chroma, x, 0.),
+        1 => (x, chroma, 0.),
+        2 => (0., chroma, x),
+        3 => (0., x, chroma),
+        4 => (x, 0., chroma),
+        _ => (chroma, 0., x),
+    };
+    let min = value - chroma;
+    glm::vec3(r + min, g + min, b + min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unique_names() {
+        for (i, mode) in RenderMode::ALL.iter().enumerate() {
+            assert_eq!(mode.to_string(), mode.name());
+            for other in &RenderMode::ALL[i + 1..] {
+                assert_ne!(mode.name(), other.name());
+            }
+        }
+    }
+
+    #[test]
+    fn brush_colours_are_stable() {
+        for id in [0, 1, 7, 1000, u32::MAX] {
+            assert_eq!(brush_colour(id), brush_colour(id));
+        }
+    }
+
+    #[test]
+    fn brush_colours_are_in_range() {
+        for id in (0..1000).chain([u32::MAX - 1, u32::MAX]) {
+            let colour = brush_colour(id);
+            for channel in [colour.x, colour.y, colour.z] {
+                assert!((0. ..=1.).contains(&channel), "{id}: {colour:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn neighbouring_brushes_differ() {
+        for id in 0..1000 {
+            let (a, b) = (brush_colour(id), brush_colour(id + 1));
+            assert!(glm::length(a - b) > 0.1, "{id}: {a:?} and {b:?}");
+        }
+    }
+}
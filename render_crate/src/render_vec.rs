@@ -78,7 +78,7 @@ where
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum GlType {
     Float,  // f32
     Double, // f64
@@ -130,6 +130,10 @@ where
             self.inner.extend_from_slice(&value.as_gl_bytes());
         }
     }
+    /// Remove every value, keeping the allocation.
+    pub fn clear(&mut self) {
+        self.inner.clear();
+    }
     pub fn stride(&self) -> usize {
         self.stride
     }
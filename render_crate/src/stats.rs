@@ -0,0 +1,119 @@
+//! Per-frame rendering counters.
+//!
+//! Everything that issues GL calls records what it did here, through the
+//! `record_*` functions. [`end_frame`] hands back the totals and starts counting again.
+use std::cell::Cell;
+
+/// What the renderer did over one frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Number of draw calls issued.
+    pub draw_calls: u64,
+    /// Vertices submitted to draw calls, indexed or not.
+    pub vertices: u64,
+    /// Triangles submitted to draw calls.
+    pub triangles: u64,
+    /// Bytes uploaded into buffers.
+    pub bytes_uploaded: u64,
+    /// Number of times a program was bound.
+    pub programs_bound: u64,
+    /// Number of times a texture was bound.
+    pub textures_bound: u64,
+}
+
+thread_local! {
+    /// Counters for the frame in progress.
+    static CURRENT: Cell<RenderStats> = Cell::new(RenderStats::default());
+}
+
+/// Apply `f` to the counters for this frame.
+fn record(f: impl FnOnce(&mut RenderStats)) {
+    CURRENT.with(|current| {
+        let mut stats = current.get();
+        f(&mut stats);
+        current.set(stats);
+    });
+}
+
+/// Record a draw call drawing `count` vertices as `mode` primitives, e.g. [`gl::TRIANGLES`].
+pub fn record_draw(mode: gl::types::GLenum, count: usize) {
+    let count = count as u64;
+    let triangles = match mode {
+        gl::TRIANGLES => count / 3,
+        gl::TRIANGLE_STRIP | gl::TRIANGLE_FAN => count.saturating_sub(2),
+        _ => 0,
+    };
+    record(|stats| {
+        stats.draw_calls += 1;
+        stats.vertices += count;
+        stats.triangles += triangles;
+    });
+}
+
+/// Record `bytes` being uploaded into a buffer.
+pub fn record_upload(bytes: usize) {
+    record(|stats| stats.bytes_uploaded += bytes as u64);
+}
+
+/// Record a program being bound.
+pub fn record_program_bind() {
+    record(|stats| stats.programs_bound += 1);
+}
+
+/// Record a texture being bound.
+pub fn record_texture_bind() {
+    record(|stats| stats.textures_bound += 1);
+}
+
+/// Return everything recorded since the last call, and reset the counters.
+pub fn end_frame() -> RenderStats {
+    CURRENT.take()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Triangles counted for one draw call.
+    fn triangles(mode: gl::types::GLenum, count: usize) -> u64 {
+        end_frame();
+        record_draw(mode, count);
+        end_frame().triangles
+    }
+
+    #[test]
+    fn triangle_counts() {
+        assert_eq!(triangles(gl::TRIANGLES, 0), 0);
+        assert_eq!(triangles(gl::TRIANGLES, 3), 1);
+        assert_eq!(triangles(gl::TRIANGLES, 8), 2);
+        for mode in [gl::TRIANGLE_STRIP, gl::TRIANGLE_FAN] {
+            assert_eq!(triangles(mode, 0), 0);
+            assert_eq!(triangles(mode, 1), 0);
+            assert_eq!(triangles(mode, 2), 0);
+            assert_eq!(triangles(mode, 3), 1);
+            assert_eq!(triangles(mode, 6), 4);
+        }
+        assert_eq!(triangles(gl::LINES, 6), 0);
+    }
+
+    #[test]
+    fn end_frame_resets() {
+        end_frame();
+        record_draw(gl::TRIANGLES, 6);
+        record_draw(gl::LINES, 4);
+        record_upload(64);
+        record_program_bind();
+        record_texture_bind();
+        record_texture_bind();
+        let expected = RenderStats {
+            draw_calls: 2,
+            vertices: 10,
+            triangles: 2,
+            bytes_uploaded: 64,
+            programs_bound: 1,
+            textures_bound: 2,
+        };
+        assert_eq!(end_frame(), expected);
+        assert_eq!(end_frame(), RenderStats::default());
+    }
+}
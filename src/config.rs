@@ -0,0 +1,564 @@
+//! Settings kept between runs, and command-line arguments that override them.
+//!
+//! Settings are archived cvars (see [`Cvar::archived`]). The config file holds one
+//! `name value` line per setting, and lives in [`config_dir`] unless `--config` says otherwise.
+//! Settings changed while running are written back by [`Config::save`], but values that only
+//! came from the command line aren't.
+use std::{
+    collections::BTreeMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::{
+    console::{self, ConsoleError, Cvar},
+    frame_pacing::PacingMode,
+    window::WindowMode,
+};
+
+/// Name of the engine's directory in the user config dir.
+pub const APP_DIR_NAME: &str = "backshot";
+/// Name of the config file in [`config_dir`].
+pub const CONFIG_FILE_NAME: &str = "config.cfg";
+
+/// Default map, loaded from `maps/`.
+pub const DEFAULT_MAP: &str = "test";
+/// Default window size.
+pub const DEFAULT_WIDTH: u32 = 800;
+/// Default window size.
+pub const DEFAULT_HEIGHT: u32 = 600;
+/// OpenGL version asked for by default.
+pub const DEFAULT_GL_VERSION: (u8, u8) = (4, 3);
+
+/// Errors loading or saving settings, or reading the command line.
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    /// An argument isn't one of [`FLAGS`].
+    #[error("unknown argument \"{0}\", see --help")]
+    UnknownArgument(String),
+    /// A flag that takes a value was last.
+    #[error("{0} needs a value, see --help")]
+    MissingValue(&'static str),
+    /// A flag was given a value its setting doesn't take.
+    #[error("{0}: {1}")]
+    BadArgument(&'static str, ConsoleError),
+    /// The config file couldn't be read or written.
+    #[error("{0}: {1}")]
+    Io(PathBuf, io::Error),
+}
+
+/// The settings' directory in the user config dir, or [`None`] if it can't be found.
+///
+/// That's `$XDG_CONFIG_HOME` or `~/.config` on Linux, `~/Library/Application Support` on
+/// macOS, and `%APPDATA%` on Windows.
+pub fn config_dir() -> Option<PathBuf> {
+    let home = || env::var_os("HOME").map(PathBuf::from);
+    let base = if cfg!(windows) {
+        env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|home| home.join("Library/Application Support"))
+    } else {
+        env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            // relative paths are meant to be ignored
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| home().map(|home| home.join(".config")))
+    };
+    base.map(|base| base.join(APP_DIR_NAME))
+}
+
+/// Register the settings that don't belong to any other module.
+pub fn register_cvars() {
+    console::register_cvar(
+        Cvar::new("startmap", DEFAULT_MAP, "map loaded on startup, from maps/").archived(),
+    );
+    console::register_cvar(
+        Cvar::new(
+            "vid_width",
+            i64::from(DEFAULT_WIDTH),
+            "window width in points, or the resolution when exclusive",
+        )
+        .range(320., 16384.)
+        .archived(),
+    );
+    console::register_cvar(
+        Cvar::new(
+            "vid_height",
+            i64::from(DEFAULT_HEIGHT),
+            "window height in points, or the resolution when exclusive",
+        )
+        .range(240., 16384.)
+        .archived(),
+    );
+    console::register_cvar(
+        Cvar::new(
+            "vid_mode",
+            WindowMode::default().name(),
+            "windowed, borderless (covers the display) or exclusive (changes its resolution)",
+        )
+        .choices(&WindowMode::NAMES)
+        .archived(),
+    );
+    console::register_cvar(
+        Cvar::new("vid_display", 0_i64, "display to put the window on, from 0")
+            .range(0., 15.)
+            .archived(),
+    );
+    console::register_cvar(
+        Cvar::new(
+            "vid_pacing",
+            PacingMode::default().name(),
+            "how frames are paced: vsync, adaptive, uncapped, or cap at fps_max",
+        )
+        .choices(&PacingMode::NAMES)
+        .archived(),
+    );
+    console::register_cvar(
+        Cvar::new(
+            "gl_version_major",
+            i64::from(DEFAULT_GL_VERSION.0),
+            "OpenGL major version to ask for",
+        )
+        .range(3., 4.)
+        .archived(),
+    );
+    console::register_cvar(
+        Cvar::new(
+            "gl_version_minor",
+            i64::from(DEFAULT_GL_VERSION.1),
+            "OpenGL minor version to ask for",
+        )
+        .range(0., 6.)
+        .archived(),
+    );
+    console::register_cvar(
+        Cvar::new(
+            "gl_debug",
+            "",
+            "GL debug output spec, like \"sync,min=low\". BACKSHOT_GL_DEBUG overrides it",
+        )
+        .archived(),
+    );
+}
+
+/// What a flag does to its setting.
+enum FlagValue {
+    /// Takes the next argument as the value. Carries a name for it, for `--help`.
+    Takes(&'static str),
+    /// Always sets this value.
+    Fixed(&'static str),
+}
+
+/// A command-line flag that overrides a setting.
+struct Flag {
+    /// The flag, including the dashes.
+    name: &'static str,
+    /// The cvar it sets.
+    cvar: &'static str,
+    /// What it sets the cvar to.
+    value: FlagValue,
+    /// Description for `--help`.
+    help: &'static str,
+}
+
+/// Every flag that overrides a setting.
+const FLAGS: [Flag; 11] = [
+    Flag {
+        name: "--map",
+        cvar: "startmap",
+        value: FlagValue::Takes("<name>"),
+        help: "map to load, from maps/",
+    },
+    Flag {
+        name: "--width",
+        cvar: "vid_width",
+        value: FlagValue::Takes("<pixels>"),
+        help: "window width",
+    },
+    Flag {
+        name: "--height",
+        cvar: "vid_height",
+        value: FlagValue::Takes("<pixels>"),
+        help: "window height",
+    },
+    Flag {
+        name: "--fps-cap",
+        cvar: "fps_max",
+        value: FlagValue::Takes("<fps>"),
+        help: "most frames per second, 0 for no limit",
+    },
+    Flag {
+        name: "--fullscreen",
+        cvar: "vid_mode",
+        value: FlagValue::Fixed("exclusive"),
+        help: "fill the screen, at --width by --height",
+    },
+    Flag {
+        name: "--borderless",
+        cvar: "vid_mode",
+        value: FlagValue::Fixed("borderless"),
+        help: "fill the screen, at its own resolution",
+    },
+    Flag {
+        name: "--windowed",
+        cvar: "vid_mode",
+        value: FlagValue::Fixed("windowed"),
+        help: "run in a window",
+    },
+    Flag {
+        name: "--display",
+        cvar: "vid_display",
+        value: FlagValue::Takes("<index>"),
+        help: "display to use, from 0",
+    },
+    Flag {
+        name: "--vsync",
+        cvar: "vid_pacing",
+        value: FlagValue::Fixed("vsync"),
+        help: "wait for the display between frames",
+    },
+    Flag {
+        name: "--no-vsync",
+        cvar: "vid_pacing",
+        value: FlagValue::Fixed("cap"),
+        help: "don't wait for the display, cap at --fps-cap",
+    },
+    Flag {
+        name: "--pacing",
+        cvar: "vid_pacing",
+        value: FlagValue::Takes("<mode>"),
+        help: "vsync, adaptive, uncapped or cap",
+    },
+];
+
+/// Text printed for `--help`.
+pub fn usage() -> String {
+    let mut usage = String::from(
+        "usage: backshot_engine [options]\n\n\
+         options:\n  \
+         --config <path>         settings file to use instead of the default\n  \
+         -h, --help              show this message\n",
+    );
+    for flag in &FLAGS {
+        let name = match flag.value {
+            FlagValue::Takes(value) => format!("{} {value}", flag.name),
+            FlagValue::Fixed(_) => flag.name.to_owned(),
+        };
+        usage.push_str(&format!("  {name:<24}{}\n", flag.help));
+    }
+    usage
+}
+
+/// The parsed command line.
+#[derive(Debug, Default)]
+pub struct CliArgs {
+    /// `--help` was given.
+    pub help: bool,
+    /// Settings file given with `--config`.
+    pub config: Option<PathBuf>,
+    /// Settings to override, in order: the flag, the cvar, and the value.
+    overrides: Vec<(&'static str, &'static str, String)>,
+}
+
+impl CliArgs {
+    /// Parse the arguments after the program name. Values can follow
+    /// their flag as the next argument, or after an `=`.
+    ///
+    /// # Errors
+    /// Errors on unknown flags, and flags missing their value.
+    /// Values aren't checked until [`Config::load`].
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, ConfigError> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let (name, inline_value) = match arg.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_owned())),
+                None => (arg.as_str(), None),
+            };
+            if matches!(name, "-h" | "--help") {
+                parsed.help = true;
+                continue;
+            }
+            if name == "--config" {
+                let path = inline_value
+                    .or_else(|| args.next())
+                    .ok_or(ConfigError::MissingValue("--config"))?;
+                parsed.config = Some(path.into());
+                continue;
+            }
+            let flag = FLAGS
+                .iter()
+                .find(|flag| flag.name == name)
+                .ok_or_else(|| ConfigError::UnknownArgument(arg.clone()))?;
+            let value = match flag.value {
+                FlagValue::Takes(_) => inline_value
+                    .or_else(|| args.next())
+                    .ok_or(ConfigError::MissingValue(flag.name))?,
+                FlagValue::Fixed(value) => value.to_owned(),
+            };
+            parsed.overrides.push((flag.name, flag.cvar, value));
+        }
+        Ok(parsed)
+    }
+}
+
+/// Where settings came from, to know what to write back.
+pub struct Config {
+    /// The config file, if there's anywhere to keep one.
+    path: Option<PathBuf>,
+    /// Every setting as it was after reading the config file, formatted like the file.
+    loaded: BTreeMap<&'static str, String>,
+    /// Settings the command line changed, formatted like the file.
+    overridden: BTreeMap<&'static str, String>,
+}
+
+/// Every archived cvar and its value, formatted like the config file.
+fn archived_values() -> BTreeMap<&'static str, String> {
+    console::with_cvars(|cvars| {
+        cvars
+            .filter(|cvar| cvar.is_archived())
+            .map(|cvar| (cvar.name(), cvar.value().to_string()))
+            .collect()
+    })
+}
+
+impl Config {
+    /// Read the config file, then apply the command line over it.
+    /// The settings' cvars must be registered first.
+    ///
+    /// Bad lines in the config file are logged and skipped, so a broken setting
+    /// can't stop the engine from starting.
+    ///
+    /// # Errors
+    /// Errors if the config file exists but can't be read, or an argument has a bad value.
+    pub fn load(args: &CliArgs) -> Result<Self, ConfigError> {
+        let path = args
+            .config
+            .clone()
+            .or_else(|| config_dir().map(|dir| dir.join(CONFIG_FILE_NAME)));
+        match &path {
+            Some(path) => read_config_file(path)?,
+            None => log::warn!("couldn't find a config dir, settings won't be kept"),
+        }
+        let loaded = archived_values();
+
+        for (flag, cvar, value) in &args.overrides {
+            console::set_from_str(cvar, value)
+                .map_err(|err| ConfigError::BadArgument(flag, err))?;
+        }
+        let overridden = archived_values()
+            .into_iter()
+            .filter(|(name, value)| loaded.get(name) != Some(value))
+            .collect();
+
+        Ok(Self {
+            path,
+            loaded,
+            overridden,
+        })
+    }
+
+    /// Write the settings back to the config file, if any changed while running.
+    ///
+    /// # Errors
+    /// Errors if the file can't be written.
+    pub fn save(&self) -> Result<(), ConfigError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut values = archived_values();
+        for (name, value) in values.iter_mut() {
+            // still what the command line set, so keep what the file had
+            if self.overridden.get(name) == Some(value)
+                && let Some(loaded) = self.loaded.get(name)
+            {
+                value.clone_from(loaded);
+            }
+        }
+        if values == self.loaded && path.exists() {
+            return Ok(());
+        }
+
+        let mut text = String::from("// written by backshot_engine on exit\n");
+        for (name, value) in &values {
+            text.push_str(&format!("{name} {value}\n"));
+        }
+        let io_err = |err| ConfigError::Io(path.clone(), err);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(io_err)?;
+        }
+        // write then rename, so a crash can't leave half a file
+        let temp = path.with_extension("cfg.tmp");
+        fs::write(&temp, text).map_err(io_err)?;
+        fs::rename(&temp, path).map_err(io_err)?;
+        log::info!("saved settings to {}", path.display());
+        Ok(())
+    }
+}
+
+/// Apply each setting in the config file at `path`. A missing file is fine.
+///
+/// # Errors
+/// Errors if the file exists but can't be read.
+fn read_config_file(path: &Path) -> Result<(), ConfigError> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {
+            log::info!("no config at {}, using defaults", path.display());
+            return Ok(());
+        }
+        Err(err) => return Err(ConfigError::Io(path.to_owned(), err)),
+    };
+    for (index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with("//") || line.starts_with('#') {
+            continue;
+        }
+        let (name, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let value = value.trim();
+        let value = value
+            .strip_prefix('"')
+            .and_then(|value| value.strip_suffix('"'))
+            .unwrap_or(value);
+        let archived = console::with_cvar(name, Cvar::is_archived).unwrap_or(false);
+        let result = if archived {
+            console::set_from_str(name, value)
+        } else {
+            Err(ConsoleError::UnknownCvar(name.to_owned()))
+        };
+        if let Err(err) = result {
+            log::error!("{}:{}: {err}, ignoring it", path.display(), index + 1);
+        }
+    }
+    log::info!("loaded settings from {}", path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CliArgs, ConfigError> {
+        CliArgs::parse(args.iter().map(|arg| (*arg).to_owned()))
+    }
+
+    /// A fresh directory for one test's config file.
+    fn temp_dir(test: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("backshot-config-{}-{test}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn parse_flags() {
+        let args = parse(&["--map", "e1m1", "--width=1024", "--fullscreen", "-h"]).unwrap();
+        assert!(args.help);
+        assert_eq!(args.config, None);
+        assert_eq!(
+            args.overrides,
+            [
+                ("--map", "startmap", "e1m1".to_owned()),
+                ("--width", "vid_width", "1024".to_owned()),
+                ("--fullscreen", "vid_mode", "exclusive".to_owned()),
+            ]
+        );
+        let args = parse(&["--config=a/b.cfg"]).unwrap();
+        assert_eq!(args.config, Some(PathBuf::from("a/b.cfg")));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert!(matches!(
+            parse(&["--nope"]),
+            Err(ConfigError::UnknownArgument(arg)) if arg == "--nope"
+        ));
+        assert!(matches!(
+            parse(&["--width"]),
+            Err(ConfigError::MissingValue("--width"))
+        ));
+        assert!(matches!(
+            parse(&["--config"]),
+            Err(ConfigError::MissingValue("--config"))
+        ));
+    }
+
+    #[test]
+    fn every_flag_is_in_the_usage() {
+        let usage = usage();
+        for flag in &FLAGS {
+            assert!(usage.contains(flag.name), "{} missing", flag.name);
+        }
+    }
+
+    #[test]
+    fn load_applies_the_file_then_the_command_line() {
+        register_cvars();
+        let dir = temp_dir("load");
+        let path = dir.join(CONFIG_FILE_NAME);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            &path,
+            "// comment\n\
+             startmap \"e1m2\"\n\
+             vid_width 1280\n\
+             vid_height 5\n\
+             not_a_cvar 1\n",
+        )
+        .unwrap();
+
+        let args = parse(&["--config", path.to_str().unwrap(), "--width", "1920"]).unwrap();
+        let config = Config::load(&args).unwrap();
+        assert_eq!(console::get::<String>("startmap").as_deref(), Some("e1m2"));
+        assert_eq!(console::get::<i64>("vid_width"), Some(1920));
+        // out of range, so skipped
+        assert_eq!(
+            console::get::<i64>("vid_height"),
+            Some(i64::from(DEFAULT_HEIGHT))
+        );
+
+        // the command line's width isn't saved, but changes made while running are
+        console::set("startmap", "e2m1").unwrap();
+        config.save().unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert!(text.contains("startmap \"e2m1\"\n"));
+        assert!(text.contains("vid_width 1280\n"));
+        assert!(!path.with_extension("cfg.tmp").exists());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn load_rejects_bad_arguments() {
+        register_cvars();
+        let dir = temp_dir("bad-argument");
+        let path = dir.join(CONFIG_FILE_NAME);
+        let args = parse(&["--config", path.to_str().unwrap(), "--pacing", "fast"]).unwrap();
+        assert!(matches!(
+            Config::load(&args),
+            Err(ConfigError::BadArgument(
+                "--pacing",
+                ConsoleError::NotAChoice { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn save_only_writes_changes() {
+        register_cvars();
+        let dir = temp_dir("save");
+        let path = dir.join(CONFIG_FILE_NAME);
+        let args = parse(&["--config", path.to_str().unwrap()]).unwrap();
+        let config = Config::load(&args).unwrap();
+        // no file yet, so the defaults are written
+        config.save().unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+        assert!(written.contains(&format!("vid_width {DEFAULT_WIDTH}\n")));
+
+        fs::write(&path, "startmap \"kept\"\n").unwrap();
+        let config = Config::load(&args).unwrap();
+        config.save().unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "startmap \"kept\"\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
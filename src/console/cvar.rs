@@ -0,0 +1,346 @@
+//! Exports [`Cvar`] and [`CvarValue`].
+use std::fmt::{self, Display};
+
+use super::ConsoleError;
+
+/// The value of a console variable. A cvar keeps the type it was registered with.
+#[derive(Clone, Debug, PartialEq)]
+pub enum CvarValue {
+    /// Written as `0` or `1`, but `true`/`false`/`on`/`off` are accepted too.
+    Bool(bool),
+    /// A whole number.
+    Int(i64),
+    /// A number that may have a fractional part.
+    Float(f64),
+    /// Anything else.
+    String(String),
+}
+
+impl CvarValue {
+    /// Name of the type, for error messages.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Bool(_) => "bool",
+            Self::Int(_) => "int",
+            Self::Float(_) => "float",
+            Self::String(_) => "string",
+        }
+    }
+
+    /// Parse `text` as a value of the same type as `self`.
+    /// `name` is only used in the error.
+    ///
+    /// # Errors
+    /// Errors if `text` isn't a value of that type.
+    pub fn parse_same(&self, name: &str, text: &str) -> Result<Self, ConsoleError> {
+        let bad_value = || ConsoleError::BadValue {
+            name: name.to_owned(),
+            value: text.to_owned(),
+            expected: self.type_name(),
+        };
+        Ok(match self {
+            Self::Bool(_) => Self::Bool(match text.to_ascii_lowercase().as_str() {
+                "1" | "true" | "on" | "yes" => true,
+                "0" | "false" | "off" | "no" => false,
+                _ => return Err(bad_value()),
+            }),
+            Self::Int(_) => Self::Int(text.parse().map_err(|_| bad_value())?),
+            Self::Float(_) => Self::Float(text.parse().map_err(|_| bad_value())?),
+            Self::String(_) => Self::String(text.to_owned()),
+        })
+    }
+
+    /// The value as a number, if it's numeric.
+    fn as_number(&self) -> Option<f64> {
+        match self {
+            Self::Int(value) => Some(*value as f64),
+            Self::Float(value) => Some(*value),
+            Self::Bool(_) | Self::String(_) => None,
+        }
+    }
+}
+
+impl Display for CvarValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(value) => write!(f, "{}", u8::from(*value)),
+            Self::Int(value) => write!(f, "{value}"),
+            Self::Float(value) => write!(f, "{value}"),
+            Self::String(value) => write!(f, "\"{value}\""),
+        }
+    }
+}
+
+impl From<bool> for CvarValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+impl From<i64> for CvarValue {
+    fn from(value: i64) -> Self {
+        Self::Int(value)
+    }
+}
+impl From<f64> for CvarValue {
+    fn from(value: f64) -> Self {
+        Self::Float(value)
+    }
+}
+impl From<&str> for CvarValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_owned())
+    }
+}
+impl From<String> for CvarValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+/// Types a cvar's value can be read as, with [`get`](super::get).
+pub trait FromCvar: Sized {
+    /// Read `value`, or [`None`] if it's a different type.
+    fn from_cvar(value: &CvarValue) -> Option<Self>;
+}
+
+impl FromCvar for bool {
+    fn from_cvar(value: &CvarValue) -> Option<Self> {
+        match value {
+            CvarValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+impl FromCvar for i64 {
+    fn from_cvar(value: &CvarValue) -> Option<Self> {
+        match value {
+            CvarValue::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+impl FromCvar for f64 {
+    fn from_cvar(value: &CvarValue) -> Option<Self> {
+        value.as_number()
+    }
+}
+impl FromCvar for f32 {
+    fn from_cvar(value: &CvarValue) -> Option<Self> {
+        value.as_number().map(|value| value as f32)
+    }
+}
+impl FromCvar for String {
+    fn from_cvar(value: &CvarValue) -> Option<Self> {
+        match value {
+            CvarValue::String(value) => Some(value.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// A console variable: a named, typed setting that can be read and changed at runtime.
+#[derive(Clone, Debug)]
+pub struct Cvar {
+    /// What it's called in the console.
+    name: &'static str,
+    /// One line describing what it does.
+    help: &'static str,
+    /// Its current value.
+    value: CvarValue,
+    /// The value it was registered with.
+    default: CvarValue,
+    /// Inclusive bounds, for numeric cvars.
+    range: Option<(f64, f64)>,
+    /// The only values allowed, for string cvars.
+    choices: Option<&'static [&'static str]>,
+    /// Saved to the config file.
+    archived: bool,
+}
+
+impl Cvar {
+    /// Create a cvar, set to `default`. Register it with [`register_cvar`](super::register_cvar).
+    pub fn new(name: &'static str, default: impl Into<CvarValue>, help: &'static str) -> Self {
+        let default = default.into();
+        Self {
+            name,
+            help,
+            value: default.clone(),
+            default,
+            range: None,
+            choices: None,
+            archived: false,
+        }
+    }
+    /// Only allow values from `min` to `max`, inclusive. Ignored for non-numeric cvars.
+    pub fn range(mut self, min: f64, max: f64) -> Self {
+        self.range = Some((min, max));
+        self
+    }
+    /// Only allow these values. Ignored for non-string cvars.
+    pub fn choices(mut self, choices: &'static [&'static str]) -> Self {
+        self.choices = Some(choices);
+        self
+    }
+    /// Save it to the config file, so it's kept between runs.
+    pub fn archived(mut self) -> Self {
+        self.archived = true;
+        self
+    }
+
+    /// What it's called in the console.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+    /// One line describing what it does.
+    pub fn help(&self) -> &'static str {
+        self.help
+    }
+    /// Its current value.
+    pub fn value(&self) -> &CvarValue {
+        &self.value
+    }
+    /// The value it was registered with.
+    pub fn default(&self) -> &CvarValue {
+        &self.default
+    }
+    /// The only values allowed, if it's limited to some.
+    pub fn allowed_choices(&self) -> Option<&'static [&'static str]> {
+        self.choices
+    }
+    /// Whether it's saved to the config file.
+    pub fn is_archived(&self) -> bool {
+        self.archived
+    }
+
+    /// Change the value.
+    ///
+    /// # Errors
+    /// Errors if `value` is a different type, or out of range.
+    pub fn set(&mut self, value: CvarValue) -> Result<(), ConsoleError> {
+        // ints are fine for float cvars, so `sensitivity 2` works
+        let value = match (&self.value, value) {
+            (CvarValue::Float(_), CvarValue::Int(value)) => CvarValue::Float(value as f64),
+            (_, value) => value,
+        };
+        if std::mem::discriminant(&self.value) != std::mem::discriminant(&value) {
+            return Err(ConsoleError::BadValue {
+                name: self.name.to_owned(),
+                value: value.to_string(),
+                expected: self.value.type_name(),
+            });
+        }
+        if let (Some((min, max)), Some(number)) = (self.range, value.as_number())
+            && !(min..=max).contains(&number)
+        {
+            return Err(ConsoleError::OutOfRange {
+                name: self.name.to_owned(),
+                value: number,
+                min,
+                max,
+            });
+        }
+        if let (Some(choices), CvarValue::String(text)) = (self.choices, &value)
+            && !choices.contains(&text.as_str())
+        {
+            return Err(ConsoleError::NotAChoice {
+                name: self.name.to_owned(),
+                value: text.clone(),
+                choices: choices.join(", "),
+            });
+        }
+        self.value = value;
+        Ok(())
+    }
+    /// Change the value from text typed into the console.
+    ///
+    /// # Errors
+    /// See [`Cvar::set`]. Also errors if `text` isn't a value of the cvar's type.
+    pub fn set_from_str(&mut self, text: &str) -> Result<(), ConsoleError> {
+        let value = self.value.parse_same(self.name, text)?;
+        self.set(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn set_checks_the_type() {
+        let mut cvar = Cvar::new("test", false, "");
+        assert!(matches!(
+            cvar.set(CvarValue::Int(1)),
+            Err(ConsoleError::BadValue {
+                expected: "bool",
+                ..
+            })
+        ));
+        cvar.set(true.into()).unwrap();
+        assert_eq!(cvar.value(), &CvarValue::Bool(true));
+    }
+
+    #[test]
+    fn ints_set_floats() {
+        let mut cvar = Cvar::new("test", 1.5, "");
+        cvar.set(CvarValue::Int(2)).unwrap();
+        assert_eq!(cvar.value(), &CvarValue::Float(2.));
+    }
+
+    #[test]
+    fn set_checks_the_range() {
+        let mut cvar = Cvar::new("test", 5_i64, "").range(0., 10.);
+        cvar.set(CvarValue::Int(0)).unwrap();
+        cvar.set(CvarValue::Int(10)).unwrap();
+        assert_eq!(
+            cvar.set(CvarValue::Int(11)),
+            Err(ConsoleError::OutOfRange {
+                name: "test".to_owned(),
+                value: 11.,
+                min: 0.,
+                max: 10.,
+            })
+        );
+        assert!(cvar.set_from_str("-1").is_err());
+        assert_eq!(cvar.value(), &CvarValue::Int(10));
+
+        let mut cvar = Cvar::new("test", 0.5, "").range(0., 1.);
+        assert!(cvar.set(f64::NAN.into()).is_err());
+    }
+
+    #[test]
+    fn set_checks_the_choices() {
+        let mut cvar = Cvar::new("test", "a", "").choices(&["a", "b"]);
+        cvar.set("b".into()).unwrap();
+        assert_eq!(
+            cvar.set("c".into()),
+            Err(ConsoleError::NotAChoice {
+                name: "test".to_owned(),
+                value: "c".to_owned(),
+                choices: "a, b".to_owned(),
+            })
+        );
+        assert_eq!(cvar.value(), &CvarValue::String("b".to_owned()));
+    }
+
+    #[test]
+    fn parse_from_text() {
+        let mut cvar = Cvar::new("test", false, "");
+        for (text, value) in [("1", true), ("off", false), ("TRUE", true), ("no", false)] {
+            cvar.set_from_str(text).unwrap();
+            assert_eq!(cvar.value(), &CvarValue::Bool(value));
+        }
+        assert!(cvar.set_from_str("2").is_err());
+
+        let mut cvar = Cvar::new("test", 0_i64, "");
+        assert!(matches!(
+            cvar.set_from_str("1.5"),
+            Err(ConsoleError::BadValue {
+                expected: "int",
+                ..
+            })
+        ));
+        let mut cvar = Cvar::new("test", 0., "");
+        cvar.set_from_str("1.5").unwrap();
+        assert_eq!(cvar.value(), &CvarValue::Float(1.5));
+    }
+}
@@ -0,0 +1,513 @@
+//! The developer console: a registry of commands and console variables (cvars),
+//! and the text they print.
+//!
+//! Any module can add to it with [`register_command`] and [`register_cvar`], and read
+//! cvars back with [`get`]. A line like `fps_max 144; r_wireframe 1` is run by [`execute`]:
+//! each `;` separated part either runs a command, prints a cvar (`fps_max`) or sets it
+//! (`fps_max 144`). Arguments with spaces can be quoted.
+//!
+//! Like the rest of the immediate-mode state, the registry is per thread,
+//! so everything here must be used from the main thread.
+pub mod cvar;
+
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, VecDeque},
+    rc::Rc,
+};
+
+use thiserror::Error;
+
+pub use cvar::{Cvar, CvarValue, FromCvar};
+
+/// Number of lines of output kept.
+pub const OUTPUT_CAPACITY: usize = 500;
+
+/// Errors from running console input.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum ConsoleError {
+    /// No command or cvar has this name.
+    #[error("unknown command \"{0}\"")]
+    UnknownCommand(String),
+    /// No cvar has this name.
+    #[error("unknown cvar \"{0}\"")]
+    UnknownCvar(String),
+    /// A value couldn't be parsed as the cvar's type.
+    #[error("\"{value}\" is not a valid {expected} for {name}")]
+    BadValue {
+        /// The cvar.
+        name: String,
+        /// What was given.
+        value: String,
+        /// Name of the type the cvar takes.
+        expected: &'static str,
+    },
+    /// A number was outside the cvar's range.
+    #[error("{name} must be between {min} and {max}, got {value}")]
+    OutOfRange {
+        /// The cvar.
+        name: String,
+        /// What was given.
+        value: f64,
+        /// Smallest allowed value.
+        min: f64,
+        /// Largest allowed value.
+        max: f64,
+    },
+    /// A string wasn't one of the cvar's choices.
+    #[error("{name} must be one of {choices}, got \"{value}\"")]
+    NotAChoice {
+        /// The cvar.
+        name: String,
+        /// What was given.
+        value: String,
+        /// The allowed values, comma separated.
+        choices: String,
+    },
+    /// A command was given the wrong arguments. Carries its usage.
+    #[error("usage: {0}")]
+    Usage(&'static str),
+    /// A command ran, but couldn't do what it was asked.
+    #[error("{0}")]
+    Failed(String),
+}
+
+/// What a command runs. Gets the arguments after the command's name.
+pub type CommandHandler = Rc<dyn Fn(&[&str]) -> Result<(), ConsoleError>>;
+
+/// A registered command.
+struct Command {
+    /// One line describing what it does.
+    help: &'static str,
+    /// What it runs.
+    handler: CommandHandler,
+}
+
+/// How a line of output should be shown.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputKind {
+    /// Input that was run, echoed back.
+    Input,
+    /// Anything a command printed.
+    Info,
+    /// An error from running input.
+    Error,
+}
+
+/// Everything registered, and what's been printed.
+struct Registry {
+    /// Commands, by name.
+    commands: BTreeMap<&'static str, Command>,
+    /// Cvars, by name.
+    cvars: BTreeMap<&'static str, Cvar>,
+    /// The last [`OUTPUT_CAPACITY`] lines of output, oldest first.
+    output: VecDeque<(OutputKind, String)>,
+}
+
+thread_local! {
+    /// The registry for this thread.
+    static REGISTRY: RefCell<Registry> = const {
+        RefCell::new(Registry {
+            commands: BTreeMap::new(),
+            cvars: BTreeMap::new(),
+            output: VecDeque::new(),
+        })
+    };
+}
+
+/// Add a command. Replaces any command with the same name.
+///
+/// The handler may use anything in this module, including running other input.
+pub fn register_command(
+    name: &'static str,
+    help: &'static str,
+    handler: impl Fn(&[&str]) -> Result<(), ConsoleError> + 'static,
+) {
+    REGISTRY.with_borrow_mut(|registry| {
+        if registry.cvars.contains_key(name) {
+            log::warn!("command {name} is hidden by a cvar with the same name");
+        }
+        registry.commands.insert(
+            name,
+            Command {
+                help,
+                handler: Rc::new(handler),
+            },
+        );
+    });
+}
+
+/// Add a cvar. If one with the same name already exists, it's kept as is.
+pub fn register_cvar(cvar: Cvar) {
+    REGISTRY.with_borrow_mut(|registry| {
+        if registry.cvars.contains_key(cvar.name()) {
+            log::warn!("cvar {} registered twice", cvar.name());
+            return;
+        }
+        registry.cvars.insert(cvar.name(), cvar);
+    });
+}
+
+/// The value of the cvar `name`, or [`None`] if there's no such cvar or it's a different type.
+pub fn get<T: FromCvar>(name: &str) -> Option<T> {
+    REGISTRY.with_borrow(|registry| {
+        registry
+            .cvars
+            .get(name)
+            .and_then(|cvar| T::from_cvar(cvar.value()))
+    })
+}
+
+/// Change the cvar `name`.
+///
+/// # Errors
+/// Errors if there's no such cvar, or the value is the wrong type or out of range.
+pub fn set(name: &str, value: impl Into<CvarValue>) -> Result<(), ConsoleError> {
+    REGISTRY.with_borrow_mut(|registry| {
+        registry
+            .cvars
+            .get_mut(name)
+            .ok_or_else(|| ConsoleError::UnknownCvar(name.to_owned()))?
+            .set(value.into())
+    })
+}
+
+/// Change the cvar `name` from text, as if `name text` was typed into the console.
+///
+/// # Errors
+/// Errors if there's no such cvar, or the text isn't a valid value for it.
+pub fn set_from_str(name: &str, text: &str) -> Result<(), ConsoleError> {
+    REGISTRY.with_borrow_mut(|registry| {
+        registry
+            .cvars
+            .get_mut(name)
+            .ok_or_else(|| ConsoleError::UnknownCvar(name.to_owned()))?
+            .set_from_str(text)
+    })
+}
+
+/// Look at a cvar, or [`None`] if there's no such cvar.
+pub fn with_cvar<R>(name: &str, f: impl FnOnce(&Cvar) -> R) -> Option<R> {
+    REGISTRY.with_borrow(|registry| registry.cvars.get(name).map(f))
+}
+
+/// Look at every cvar, sorted by name.
+pub fn with_cvars<R>(f: impl FnOnce(&mut dyn Iterator<Item = &Cvar>) -> R) -> R {
+    REGISTRY.with_borrow(|registry| f(&mut registry.cvars.values()))
+}
+
+/// Add a line of output.
+pub fn print(kind: OutputKind, line: impl Into<String>) {
+    REGISTRY.with_borrow_mut(|registry| {
+        if registry.output.len() == OUTPUT_CAPACITY {
+            registry.output.pop_front();
+        }
+        registry.output.push_back((kind, line.into()));
+    });
+}
+
+/// Add a line of [`OutputKind::Info`] output.
+pub fn info(line: impl Into<String>) {
+    print(OutputKind::Info, line);
+}
+
+/// Look at the output, oldest first.
+pub fn with_output<R>(f: impl FnOnce(&VecDeque<(OutputKind, String)>) -> R) -> R {
+    REGISTRY.with_borrow(|registry| f(&registry.output))
+}
+
+/// Forget all output.
+pub fn clear_output() {
+    REGISTRY.with_borrow_mut(|registry| registry.output.clear());
+}
+
+/// Split a line into `;` separated statements, each split into words.
+/// Quotes group words, and keep `;` from splitting.
+fn tokenize(line: &str) -> Vec<Vec<String>> {
+    let mut statements = vec![];
+    let mut words = vec![];
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut quoted = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                in_word = true;
+            }
+            ';' if !quoted => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+                statements.push(std::mem::take(&mut words));
+            }
+            c if c.is_whitespace() && !quoted => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    statements.push(words);
+    statements.retain(|words| !words.is_empty());
+    statements
+}
+
+/// Run one statement.
+fn execute_statement(words: &[String]) -> Result<(), ConsoleError> {
+    let (name, args) = words.split_first().expect("statements aren't empty");
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    // clone the handler out, so it can use the registry itself
+    let handler = REGISTRY.with_borrow(|registry| {
+        registry
+            .commands
+            .get(name.as_str())
+            .map(|command| command.handler.clone())
+    });
+    if let Some(handler) = handler {
+        return handler(&args);
+    }
+
+    let described = REGISTRY.with_borrow_mut(|registry| {
+        let Some(cvar) = registry.cvars.get_mut(name.as_str()) else {
+            return Err(ConsoleError::UnknownCommand(name.clone()));
+        };
+        match args.as_slice() {
+            [] => Ok(Some(describe_cvar(cvar))),
+            [value] => cvar.set_from_str(value).map(|()| None),
+            // unquoted strings with spaces
+            values => cvar.set_from_str(&values.join(" ")).map(|()| None),
+        }
+    })?;
+    if let Some(description) = described {
+        info(description);
+    }
+    Ok(())
+}
+
+/// A line showing a cvar's value.
+fn describe_cvar(cvar: &Cvar) -> String {
+    format!(
+        "{} is {} (default {}) - {}",
+        cvar.name(),
+        cvar.value(),
+        cvar.default(),
+        cvar.help()
+    )
+}
+
+/// Run a line of input. Every statement is run, even if an earlier one fails.
+///
+/// # Errors
+/// Errors with the first statement that failed.
+pub fn execute(line: &str) -> Result<(), ConsoleError> {
+    let mut first_error = None;
+    for words in tokenize(line) {
+        if let Err(err) = execute_statement(&words) {
+            first_error.get_or_insert(err);
+        }
+    }
+    first_error.map_or(Ok(()), Err)
+}
+
+/// Run a line of input typed by the user, echoing it and printing any error.
+pub fn submit(line: &str) {
+    print(OutputKind::Input, format!("] {line}"));
+    if let Err(err) = execute(line) {
+        print(OutputKind::Error, err.to_string());
+    }
+}
+
+/// Names of commands and cvars starting with `prefix`, sorted.
+pub fn complete(prefix: &str) -> Vec<&'static str> {
+    REGISTRY.with_borrow(|registry| {
+        let mut names: Vec<&'static str> = registry
+            .commands
+            .keys()
+            .chain(registry.cvars.keys())
+            .copied()
+            .filter(|name| name.starts_with(prefix))
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    })
+}
+
+/// Register the commands every console has: `help`, `cmdlist`, `cvarlist`, `echo`,
+/// `toggle`, `reset` and `clear`.
+pub fn register_builtins() {
+    register_command("help", "describe a command or cvar", |args| {
+        let [name] = args else {
+            info("type a command or cvar name, tab to complete. cmdlist and cvarlist list them");
+            return Ok(());
+        };
+        let line = REGISTRY.with_borrow(|registry| {
+            if let Some(command) = registry.commands.get(name) {
+                Ok(format!("{name} - {}", command.help))
+            } else if let Some(cvar) = registry.cvars.get(name) {
+                Ok(describe_cvar(cvar))
+            } else {
+                Err(ConsoleError::UnknownCommand((*name).to_owned()))
+            }
+        })?;
+        info(line);
+        Ok(())
+    });
+    register_command("cmdlist", "list every command", |_| {
+        let lines: Vec<String> = REGISTRY.with_borrow(|registry| {
+            registry
+                .commands
+                .iter()
+                .map(|(name, command)| format!("{name} - {}", command.help))
+                .collect()
+        });
+        lines.into_iter().for_each(info);
+        Ok(())
+    });
+    register_command("cvarlist", "list every cvar and its value", |_| {
+        let lines: Vec<String> = with_cvars(|cvars| cvars.map(describe_cvar).collect());
+        lines.into_iter().for_each(info);
+        Ok(())
+    });
+    register_command("echo", "print the arguments", |args| {
+        info(args.join(" "));
+        Ok(())
+    });
+    register_command("toggle", "flip a bool cvar", |args| {
+        let [name] = args else {
+            return Err(ConsoleError::Usage("toggle <cvar>"));
+        };
+        let value = get::<bool>(name)
+            .ok_or_else(|| ConsoleError::Failed(format!("{name} is not a bool cvar")))?;
+        set(name, !value)?;
+        info(format!("{name} {}", u8::from(!value)));
+        Ok(())
+    });
+    register_command("reset", "set a cvar back to its default", |args| {
+        let [name] = args else {
+            return Err(ConsoleError::Usage("reset <cvar>"));
+        };
+        let default = with_cvar(name, |cvar| cvar.default().clone())
+            .ok_or_else(|| ConsoleError::UnknownCvar((*name).to_owned()))?;
+        set(name, default)
+    });
+    register_command("clear", "clear the console output", |_| {
+        clear_output();
+        Ok(())
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    fn words(statements: &[&[&str]]) -> Vec<Vec<String>> {
+        statements
+            .iter()
+            .map(|words| words.iter().map(|word| (*word).to_owned()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn tokenize_splits_statements_and_words() {
+        assert_eq!(
+            tokenize("fps_max 144; r_wireframe  1;;"),
+            words(&[&["fps_max", "144"], &["r_wireframe", "1"]])
+        );
+        assert_eq!(tokenize("  "), words(&[]));
+    }
+
+    #[test]
+    fn tokenize_quotes() {
+        assert_eq!(
+            tokenize(r#"echo "a b; c" d"#),
+            words(&[&["echo", "a b; c", "d"]])
+        );
+        // an empty quoted argument is still an argument
+        assert_eq!(tokenize(r#"echo """#), words(&[&["echo", ""]]));
+        // an unclosed quote runs to the end of the line
+        assert_eq!(tokenize(r#"echo "a; b"#), words(&[&["echo", "a; b"]]));
+    }
+
+    #[test]
+    fn execute_runs_commands_and_sets_cvars() {
+        let calls = Rc::new(Cell::new(0));
+        let counted = calls.clone();
+        register_command("count", "count calls", move |args| {
+            assert_eq!(args, ["a b"]);
+            counted.set(counted.get() + 1);
+            Ok(())
+        });
+        register_cvar(Cvar::new("test_int", 1_i64, "an int").range(0., 10.));
+
+        execute(r#"count "a b"; test_int 5; count "a b""#).unwrap();
+        assert_eq!(calls.get(), 2);
+        assert_eq!(get::<i64>("test_int"), Some(5));
+
+        execute("test_int").unwrap();
+        assert!(with_output(|output| output
+            .back()
+            .unwrap()
+            .1
+            .starts_with("test_int is 5")));
+    }
+
+    #[test]
+    fn execute_keeps_going_after_errors() {
+        register_cvar(Cvar::new("test_int", 1_i64, "an int").range(0., 10.));
+        let err = execute("nope; test_int 11; test_int 3").unwrap_err();
+        assert_eq!(err, ConsoleError::UnknownCommand("nope".to_owned()));
+        assert_eq!(get::<i64>("test_int"), Some(3));
+    }
+
+    #[test]
+    fn builtins() {
+        register_builtins();
+        register_cvar(Cvar::new("test_bool", false, "a bool"));
+        execute("toggle test_bool").unwrap();
+        assert_eq!(get::<bool>("test_bool"), Some(true));
+        execute("reset test_bool").unwrap();
+        assert_eq!(get::<bool>("test_bool"), Some(false));
+        assert_eq!(execute("toggle"), Err(ConsoleError::Usage("toggle <cvar>")));
+        execute("echo hi there").unwrap();
+        assert_eq!(
+            with_output(|output| output.back().cloned()),
+            Some((OutputKind::Info, "hi there".to_owned()))
+        );
+        execute("clear").unwrap();
+        assert!(with_output(VecDeque::is_empty));
+    }
+
+    #[test]
+    fn complete_commands_and_cvars() {
+        register_command("test_cmd", "a command", |_| Ok(()));
+        register_cvar(Cvar::new("test_cvar", 0_i64, "a cvar"));
+        register_cvar(Cvar::new("other", 0_i64, "another cvar"));
+        assert_eq!(complete("test_"), ["test_cmd", "test_cvar"]);
+        assert_eq!(complete("test_cv"), ["test_cvar"]);
+        assert!(complete("zzz").is_empty());
+    }
+
+    #[test]
+    fn output_is_capped() {
+        for i in 0..OUTPUT_CAPACITY + 5 {
+            info(i.to_string());
+        }
+        with_output(|output| {
+            assert_eq!(output.len(), OUTPUT_CAPACITY);
+            assert_eq!(output.front().unwrap().1, "5");
+        });
+    }
+}
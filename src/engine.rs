@@ -0,0 +1,790 @@
+//! Exports [`Engine`], which runs the window, renderer and main loop,
+//! and [`Game`], which is what it runs.
+//!
+//! A game implements [`Game`], then hands itself to [`Engine::run`]:
+//! ```no_run
+//! use backshot_engine::{Engine, EngineConfig, Game};
+//!
+//! struct MyGame;
+//! impl Game for MyGame {}
+//!
+//! fn main() -> anyhow::Result<()> {
+//!     Engine::run(MyGame, EngineConfig::from_env())
+//! }
+//! ```
+use std::{
+    cell::{Cell, RefCell},
+    fs::File,
+    path::Path,
+    rc::Rc,
+};
+
+use anyhow::{Context as _, Result, format_err};
+use render::{
+    Camera, Render, RenderError, ScreenSpaceMesh,
+    debug_draw::{self, colour},
+    frame_uniforms::PointLight,
+    gl, gl_upd_viewport, glm,
+    gpu_timing::GpuProfiler,
+    imgui::{self, Context},
+    imgui_wrappers::{
+        clipboard::SdlClipboard, renderer::ImguiRenderer,
+        sdlplatform::SdlPlatform as ImguiSdlPlatform,
+    },
+    profile_scope, profiler,
+    render_mode::RenderMode,
+};
+use sdl2::{
+    EventPump, Sdl, VideoSubsystem,
+    event::{Event, WindowEvent},
+    keyboard::Keycode,
+    video::{self, GLContext},
+};
+use world::{
+    World,
+    brush_entity::BrushModel,
+    class::{self, ClassRegistry},
+    component::{Classname, Light, Transform},
+    entity::EntityId,
+};
+
+use crate::{
+    SOFT_FPS_CAP,
+    config::{self, CliArgs, Config, DEFAULT_GL_VERSION},
+    console::{self, ConsoleError, Cvar, OutputKind},
+    frame_pacing::{FramePacer, PacingMode},
+    gl_debug::{self, GlDebugConfig},
+    logging,
+    map::parser::parse_map,
+    timestep::FixedTimestep,
+    ui::{Ui, debug_ui::DebugUi, ui_manager::UiManager},
+    window::WindowManager,
+};
+
+/// Size of imgui's font, in points.
+const IMGUI_FONT_SIZE: f32 = 13.;
+
+/// A game run by the [`Engine`]. Every method does nothing by default.
+///
+/// Each frame, the engine calls [`on_event`](Game::on_event) for each event, then
+/// [`fixed_update`](Game::fixed_update), [`update`](Game::update),
+/// draws the world, calls [`render`](Game::render), and then [`ui`](Game::ui).
+pub trait Game {
+    /// Called once the window and renderer are made, before the start map is loaded and
+    /// the first frame. Register the game's entity classes here, see [`Engine::classes_mut`].
+    ///
+    /// # Errors
+    /// Returning an error stops the engine before it starts.
+    fn init(&mut self, engine: &mut Engine) -> Result<()> {
+        let _ = engine;
+        Ok(())
+    }
+    /// Advance the simulation by one tick of `dt` seconds. Runs `sv_tickrate` times per
+    /// simulated second, so zero or more times a frame, and not at all while paused.
+    /// See [`timestep`](crate::timestep).
+    fn fixed_update(&mut self, engine: &mut Engine, dt: f64) {
+        let _ = (engine, dt);
+    }
+    /// Called once per frame, after the frame's ticks, with the real time the last frame
+    /// took in seconds. Draw things between ticks with [`FixedTimestep::alpha`].
+    fn update(&mut self, engine: &mut Engine, delta_time: f64) {
+        let _ = (engine, delta_time);
+    }
+    /// Called after the world is drawn, to draw anything else, like with [`debug_draw`].
+    fn render(&mut self, engine: &mut Engine) {
+        let _ = engine;
+    }
+    /// Build the game's imgui windows, drawn over the engine's own.
+    fn ui(&mut self, engine: &mut Engine, ui: &imgui::Ui) {
+        let _ = (engine, ui);
+    }
+    /// Called for every SDL event, except the console key.
+    /// The engine still handles it afterwards, so ESC still quits.
+    fn on_event(&mut self, engine: &mut Engine, event: &Event) {
+        let _ = (engine, event);
+    }
+}
+
+/// How to start the [`Engine`].
+#[derive(Clone, Debug, Default)]
+pub struct EngineConfig {
+    /// Command-line arguments, without the program name. See [`config::usage`].
+    pub args: Vec<String>,
+}
+
+impl EngineConfig {
+    /// Take arguments from the process's command line.
+    pub fn from_env() -> Self {
+        Self {
+            args: std::env::args().skip(1).collect(),
+        }
+    }
+}
+
+/// The engine's state that a [`Game`] can see: the world, the camera, and the renderer.
+pub struct Engine {
+    /// The loaded map.
+    world: World,
+    /// The loaded map's mesh.
+    screen_world: ScreenSpaceMesh,
+    /// The mesh of each of the loaded map's brush entities, drawn where the entity is.
+    brush_entity_meshes: Vec<(EntityId, ScreenSpaceMesh)>,
+    /// The camera the world is drawn from.
+    camera: Camera,
+    /// Draws the world.
+    render_ctx: Render,
+    /// State changed by console commands, which the main loop acts on.
+    console_state: ConsoleState,
+    /// Decides when [`Game::fixed_update`] runs.
+    timestep: FixedTimestep,
+    /// The entity classes maps can spawn.
+    classes: ClassRegistry,
+}
+
+/// Everything else the main loop needs, kept apart from [`Engine`] so a
+/// [`Game`] can have that while imgui is borrowed.
+struct Systems {
+    /// Kept alive for as long as SDL is used.
+    _sdl_ctx: Sdl,
+    /// SDL's video subsystem.
+    video_ctx: VideoSubsystem,
+    /// SDL's events.
+    event_pump: EventPump,
+    /// The main window.
+    window: video::Window,
+    /// Keeps the window like the `vid_*` cvars say.
+    window_manager: WindowManager,
+    /// Kept alive for as long as GL is used.
+    _gl_ctx: GLContext,
+    /// imgui's state.
+    imgui: Context,
+    /// Feeds SDL input to imgui.
+    imgui_platform: ImguiSdlPlatform,
+    /// Draws imgui.
+    imgui_renderer: ImguiRenderer,
+    /// Times GPU work.
+    gpu_profiler: GpuProfiler,
+    /// Waits between frames.
+    pacer: FramePacer,
+    /// The engine's own UI.
+    ui: UiManager,
+    /// The config file, saved on exit.
+    config: Config,
+}
+
+impl Engine {
+    /// Start the engine, and run `game` until it quits.
+    ///
+    /// # Errors
+    /// Errors if the command line or config file is bad, if SDL, GL or the start map
+    /// can't be set up, or if [`Game::init`] fails. Errors while running are logged,
+    /// except for the renderer's, which stop the engine.
+    pub fn run(mut game: impl Game, config: EngineConfig) -> Result<()> {
+        logging::init_from_env()?;
+        let console_state = register_console();
+        let timestep = FixedTimestep::register();
+        config::register_cvars();
+        let args = CliArgs::parse(config.args)?;
+        if args.help {
+            print!("{}", config::usage());
+            return Ok(());
+        }
+        let config = Config::load(&args)?;
+        let gl_debug_config =
+            GlDebugConfig::from_env_or(&console::get::<String>("gl_debug").unwrap_or_default())?;
+
+        let (sdl_ctx, video_ctx, event_pump) = init_sdl()?;
+
+        let (window_manager, window, gl_ctx) =
+            WindowManager::create(&video_ctx).map_err(|e| format_err!(e))?;
+
+        // setup gl loading with sdl
+        gl::load_with(|s| video_ctx.gl_get_proc_address(s).cast());
+
+        // on HiDPI displays, and in fullscreen, this isn't the size that was asked for
+        let (frame_width, frame_height) = window_manager.drawable_size();
+        gl_upd_viewport(frame_width, frame_height);
+        gl_debug::setup(&gl_debug_config);
+
+        let mut render_ctx = Render::init(&gl_ctx).context("creating renderer")?;
+
+        render_ctx.clear().context("clearing screen")?;
+        window.gl_swap_window();
+
+        let (imgui, imgui_platform, imgui_renderer) = imgui_create(&video_ctx, &window)?;
+
+        let mut camera = Camera::default();
+        camera.set_position(glm::vec3(0., 0., 1.));
+        camera.set_viewport_size(frame_width, frame_height);
+
+        let pacer = FramePacer::new(window.display_mode().ok().map(|mode| mode.refresh_rate));
+        let mut engine = Engine {
+            world: World::new(),
+            screen_world: ScreenSpaceMesh::new(),
+            brush_entity_meshes: vec![],
+            camera,
+            render_ctx,
+            console_state,
+            timestep,
+            classes: ClassRegistry::with_builtins(),
+        };
+        let mut systems = Systems {
+            _sdl_ctx: sdl_ctx,
+            video_ctx,
+            event_pump,
+            window,
+            window_manager,
+            _gl_ctx: gl_ctx,
+            imgui,
+            imgui_platform,
+            imgui_renderer,
+            gpu_profiler: GpuProfiler::new(),
+            pacer,
+            ui: UiManager::new(),
+            config,
+        };
+        game.init(&mut engine).context("starting game")?;
+        let start_map = console::get::<String>("startmap").unwrap_or_default();
+        engine.switch_map(&start_map)?;
+
+        // everything so far is startup, keep it apart from the first frame
+        profiler::end_frame();
+        let result = engine.main_loop(&mut game, &mut systems);
+        // keep settings changed before an error too
+        let saved = systems.config.save();
+        if let (Err(_), Err(err)) = (&result, &saved) {
+            log::error!("couldn't save settings: {err}");
+        }
+        result?;
+        saved?;
+        Ok(())
+    }
+
+    /// Run frames until something quits.
+    fn main_loop(&mut self, game: &mut impl Game, systems: &mut Systems) -> Result<()> {
+        let main_id = systems.window.id();
+        let (mut frame_width, mut frame_height) = systems.window_manager.drawable_size();
+        // the camera as it was when the frustum was frozen
+        let mut frozen_camera: Option<Camera> = None;
+        // how much time last frame took to render
+        let mut delta_time = 0.;
+        'going: loop {
+            let pacing = console::get::<String>("vid_pacing").unwrap_or_default();
+            systems.pacer.set_mode(
+                PacingMode::from_name(&pacing).unwrap_or_default(),
+                &systems.video_ctx,
+            );
+            let events_scope = profiler::ScopeGuard::new("events");
+            // set when the console key goes down, so the text it types can be dropped
+            let mut swallow_text = false;
+            for event in systems.event_pump.poll_iter() {
+                use sdl2::event::Event as Ev;
+                match &event {
+                    Ev::KeyDown {
+                        keycode: Some(Keycode::BACKQUOTE),
+                        repeat,
+                        ..
+                    } => {
+                        if !repeat {
+                            systems.ui.console.toggle();
+                        }
+                        swallow_text = true;
+                        continue;
+                    }
+                    // the key that opens the console shouldn't type into it
+                    Ev::TextInput { .. } if swallow_text => {
+                        swallow_text = false;
+                        continue;
+                    }
+                    Ev::KeyDown { .. } => swallow_text = false,
+                    _ => {}
+                }
+                systems
+                    .imgui_platform
+                    .handle_event(&mut systems.imgui, &event);
+                game.on_event(self, &event);
+                match event {
+                    Ev::KeyDown {
+                        keycode: Some(Keycode::ESCAPE),
+                        ..
+                    } if systems.ui.console.open => {
+                        systems.ui.console.toggle();
+                    }
+                    Ev::Quit { .. }
+                    | Ev::KeyDown {
+                        keycode: Some(Keycode::ESCAPE),
+                        ..
+                    } => {
+                        break 'going;
+                    }
+                    Ev::Window {
+                        timestamp: _,
+                        window_id,
+                        win_event: WindowEvent::Resized(width, height),
+                    } if window_id == main_id => {
+                        systems.window_manager.resized(width, height);
+                    }
+                    _ => {}
+                }
+            }
+            drop(events_scope);
+
+            // the framebuffer can change size without a resize event, like when the mode changes
+            if let Some((width, height)) = systems
+                .window_manager
+                .update(&mut systems.window, &systems.video_ctx)
+            {
+                frame_width = width;
+                frame_height = height;
+                self.camera.set_viewport_size(frame_width, frame_height);
+            }
+
+            if self.console_state.quit.get() {
+                break 'going;
+            }
+            if let Some(name) = self.console_state.pending_map.take() {
+                match self.switch_map(&name) {
+                    Ok(()) => console::info(format!("loaded map {name}")),
+                    Err(err) => console::print(OutputKind::Error, format!("{err:#}")),
+                }
+            }
+
+            for (target, input, delay) in self.console_state.pending_inputs.take() {
+                self.world.entities_mut().fire_input(&target, &input, delay);
+            }
+
+            {
+                profile_scope!("game");
+                self.timestep.begin_frame(delta_time);
+                while self.timestep.next_tick() {
+                    let dt = self.timestep.dt();
+                    self.world.tick(dt);
+                    game.fixed_update(self, dt);
+                }
+                game.update(self, delta_time);
+            }
+
+            let render_scope = profiler::ScopeGuard::new("render");
+            gl_upd_viewport(frame_width, frame_height);
+            let debug_ui = &systems.ui.debug;
+            self.render_ctx
+                .set_render_mode(if console::get("r_wireframe").unwrap_or(false) {
+                    RenderMode::Wireframe
+                } else {
+                    debug_ui.render_mode
+                });
+            self.render_ctx
+                .set_frustum_culling(debug_ui.frustum_culling);
+            self.render_ctx.set_lights(&world_lights(&self.world));
+            self.render_ctx.clear().context("clearing screen")?;
+            systems
+                .gpu_profiler
+                .scope("world", || {
+                    self.render_ctx
+                        .render_world(&self.screen_world, &self.camera)?;
+                    for (id, mesh) in &self.brush_entity_meshes {
+                        if let Some(transform) = self.world.entities().get::<Transform>(*id) {
+                            self.render_ctx.render_model(
+                                mesh,
+                                &transform.matrix(),
+                                &self.camera,
+                            )?;
+                        }
+                    }
+                    Ok::<_, RenderError>(())
+                })
+                .context("rendering world")?;
+
+            if !debug_ui.freeze_frustum {
+                frozen_camera = None;
+            } else if let Some(frozen) = &frozen_camera {
+                debug_draw::frustum(frozen, colour::YELLOW);
+            } else {
+                frozen_camera = Some(self.camera.clone());
+            }
+            debug_draw_world(&self.world, debug_ui);
+            systems
+                .ui
+                .debug
+                .set_cull_stats(self.render_ctx.cull_stats());
+            game.render(self);
+            systems
+                .gpu_profiler
+                .scope("debug draw", || self.render_ctx.render_debug_draw())
+                .context("drawing debug lines")?;
+            drop(render_scope);
+
+            let ui_scope = profiler::ScopeGuard::new("ui");
+            systems.imgui_platform.prepare_frame(
+                &mut systems.imgui,
+                &systems.window,
+                &systems.event_pump,
+            );
+            let frame = systems.imgui.new_frame();
+
+            systems
+                .ui
+                .event_trace
+                .set_events(self.world.entities().events());
+            systems.ui.update(delta_time);
+            game.ui(self, frame);
+            systems.ui.draw(frame);
+            debug_draw::draw_queued_text(frame, &self.camera);
+
+            let draw_data = systems.imgui.render();
+            systems
+                .gpu_profiler
+                .scope("imgui", || systems.imgui_renderer.render(draw_data))
+                .context("rendering imgui")?;
+            systems
+                .ui
+                .debug
+                .set_render_stats(render::stats::end_frame());
+            systems.gpu_profiler.poll();
+            systems
+                .ui
+                .debug
+                .set_gpu_timings(systems.gpu_profiler.timings());
+            drop(ui_scope);
+
+            {
+                profile_scope!("swap");
+                systems.window.gl_swap_window();
+            }
+            debug_draw::end_frame();
+
+            let fps_max = console::get::<i64>("fps_max")
+                .and_then(|fps| u32::try_from(fps).ok())
+                .unwrap_or(SOFT_FPS_CAP as u32);
+            let frametime = systems.pacer.end_frame(fps_max).as_secs_f64();
+            systems.ui.debug.push(frametime);
+            systems.ui.debug.set_pacing_stats(systems.pacer.stats());
+            systems.ui.debug.set_timestep_stats(self.timestep.stats());
+            delta_time = frametime;
+            profiler::end_frame();
+        }
+        Ok(())
+    }
+
+    /// The loaded map.
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+    /// The fixed tick, for the tick count and where the frame is between ticks.
+    pub fn time(&self) -> &FixedTimestep {
+        &self.timestep
+    }
+    /// The camera the world is drawn from.
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+    /// The camera the world is drawn from, to move it.
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+    /// The renderer, to change how the world is drawn.
+    /// Its lights are replaced with the world's each frame.
+    pub fn renderer_mut(&mut self) -> &mut Render {
+        &mut self.render_ctx
+    }
+    /// The entity classes maps can spawn, to add the game's own.
+    /// Maps loaded afterwards can use them.
+    pub fn classes_mut(&mut self) -> &mut ClassRegistry {
+        &mut self.classes
+    }
+    /// Load the map called `name` from `maps/`, before the next frame.
+    /// Failures are printed to the console.
+    pub fn load_map(&mut self, name: impl Into<String>) {
+        *self.console_state.pending_map.borrow_mut() = Some(name.into());
+    }
+    /// Stop the engine after this frame.
+    pub fn quit(&mut self) {
+        self.console_state.quit.set(true);
+    }
+
+    /// Replace the world with the map called `name`, and move the camera to its player start.
+    fn switch_map(&mut self, name: &str) -> Result<()> {
+        let (world, screen_world) = load_world(name, &self.classes)?;
+        if let Some(start) = player_start(&world) {
+            self.camera.set_position(start.position);
+        }
+        self.brush_entity_meshes = brush_entity_meshes(&world);
+        self.world = world;
+        self.screen_world = screen_world;
+        Ok(())
+    }
+}
+
+/// State changed by console commands, which the main loop acts on.
+struct ConsoleState {
+    /// Set by `quit`.
+    quit: Rc<Cell<bool>>,
+    /// Set by `map`, to load the map between frames.
+    pending_map: Rc<RefCell<Option<String>>>,
+    /// Inputs queued by `ent_fire`: target, input, and delay.
+    pending_inputs: Rc<RefCell<Vec<(String, String, f64)>>>,
+}
+
+/// Register the engine's console commands and cvars.
+fn register_console() -> ConsoleState {
+    console::register_builtins();
+    console::register_cvar(
+        Cvar::new(
+            "fps_max",
+            SOFT_FPS_CAP as i64,
+            "most frames drawn per second, 0 for no limit",
+        )
+        .range(0., 1000.)
+        .archived(),
+    );
+    console::register_cvar(Cvar::new(
+        "r_wireframe",
+        false,
+        "draw the world as wireframe, whatever the debug render mode",
+    ));
+    // the engine has no player collision, so this is only a setting:
+    // a game's player movement reads it with `console::get::<bool>("sv_noclip")`
+    console::register_cvar(Cvar::new(
+        "sv_noclip",
+        false,
+        "let the player fly through walls, for games whose movement reads it",
+    ));
+    console::register_command("noclip", "toggle sv_noclip", |_| {
+        let noclip = !console::get::<bool>("sv_noclip").unwrap_or(false);
+        console::set("sv_noclip", noclip)?;
+        console::info(if noclip { "noclip ON" } else { "noclip OFF" });
+        Ok(())
+    });
+
+    let state = ConsoleState {
+        quit: Rc::new(Cell::new(false)),
+        pending_map: Rc::new(RefCell::new(None)),
+        pending_inputs: Rc::new(RefCell::new(vec![])),
+    };
+    let quit = state.quit.clone();
+    console::register_command("quit", "exit the engine", move |_| {
+        quit.set(true);
+        Ok(())
+    });
+    let pending_map = state.pending_map.clone();
+    console::register_command("map", "load maps/<name>.map", move |args| {
+        let [name] = args else {
+            return Err(ConsoleError::Usage("map <name>"));
+        };
+        let path = map_path(name);
+        if !Path::new(&path).is_file() {
+            return Err(ConsoleError::Failed(format!("no such map: {path}")));
+        }
+        *pending_map.borrow_mut() = Some((*name).to_owned());
+        Ok(())
+    });
+    let pending_inputs = state.pending_inputs.clone();
+    console::register_command(
+        "ent_fire",
+        "send <input> to the entities called <target>, after [delay] seconds",
+        move |args| {
+            let usage = ConsoleError::Usage("ent_fire <target> <input> [delay]");
+            let (target, input, delay) = match args {
+                [target, input] => (target, input, 0.),
+                [target, input, delay] => (
+                    target,
+                    input,
+                    delay
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|delay| delay.is_finite() && *delay >= 0.)
+                        .ok_or(usage)?,
+                ),
+                _ => return Err(usage),
+            };
+            pending_inputs
+                .borrow_mut()
+                .push(((*target).to_owned(), (*input).to_owned(), delay));
+            Ok(())
+        },
+    );
+    state
+}
+
+/// Path of the map file called `name`.
+fn map_path(name: &str) -> String {
+    format!("maps/{name}.map")
+}
+
+/// Load the map called `name`, and build the world and the mesh to draw from it.
+/// Falls back to a placeholder mesh if the map has nothing to draw.
+fn load_world(name: &str, classes: &ClassRegistry) -> Result<(World, ScreenSpaceMesh)> {
+    let map = map_path(name);
+    let map_data = {
+        profile_scope!("load_map");
+        let map_file = File::open(&map).with_context(|| map.clone())?;
+        parse_map(map_file).context(map)?
+    };
+    let mut world = World::new();
+    {
+        profile_scope!("build_world");
+        map_data.add_to_world(&mut world, classes);
+    }
+    let mut screen_world = ScreenSpaceMesh::from_world(&world);
+    if screen_world.is_empty() {
+        screen_world = ScreenSpaceMesh::simple();
+    }
+    Ok((world, screen_world))
+}
+
+/// A mesh of each brush entity's brushes in `world`, in entity space.
+fn brush_entity_meshes(world: &World) -> Vec<(EntityId, ScreenSpaceMesh)> {
+    world
+        .entities()
+        .query::<(EntityId, &BrushModel)>()
+        .map(|(id, model)| {
+            let mut mesh = ScreenSpaceMesh::new();
+            for brush in model.brushes() {
+                mesh.add_brush(brush);
+            }
+            (id, mesh)
+        })
+        .collect()
+}
+
+/// Where the first `info_player_start` in `world` is, if it has one.
+fn player_start(world: &World) -> Option<Transform> {
+    world
+        .entities()
+        .query::<(&Classname, &Transform)>()
+        .find(|(classname, _)| classname.0 == class::INFO_PLAYER_START.classname)
+        .map(|(_, transform)| *transform)
+}
+
+/// The world's lights that are switched on, as the renderer takes them.
+fn world_lights(world: &World) -> Vec<PointLight> {
+    world
+        .entities()
+        .query::<(&Transform, &Light)>()
+        .filter(|(_, light)| light.enabled)
+        .map(|(transform, light)| PointLight {
+            position: transform.position,
+            radius: light.radius,
+            colour: light.colour,
+            intensity: light.intensity,
+        })
+        .collect()
+}
+
+/// Queue debug drawing of the world, for whatever is enabled in `debug_ui`.
+fn debug_draw_world(world: &World, debug_ui: &DebugUi) {
+    let brushes = world.brushes().iter().zip(world.brush_bounds());
+    for (index, (brush, bounds)) in brushes.enumerate() {
+        if debug_ui.show_plane_normals {
+            for plane in brush.planes() {
+                let Some(normal) = plane.normal() else {
+                    continue;
+                };
+                let centroid = plane.centroid();
+                debug_draw::arrow(centroid, centroid + normal * 0.25, colour::CYAN);
+            }
+        }
+        if debug_ui.show_brush_bounds
+            && let Some(bounds) = bounds
+        {
+            debug_draw::aabb(bounds.min, bounds.max, colour::GREEN);
+            debug_draw::text(bounds.center(), format!("brush {index}"), colour::WHITE);
+        }
+    }
+    if debug_ui.show_brush_bounds {
+        let models = world
+            .entities()
+            .query::<(&Classname, &BrushModel, &Transform)>();
+        for (classname, model, transform) in models {
+            let Some(bounds) = model.world_bounds(transform) else {
+                continue;
+            };
+            debug_draw::aabb(bounds.min, bounds.max, colour::MAGENTA);
+            debug_draw::text(bounds.center(), classname.0.clone(), colour::WHITE);
+        }
+    }
+}
+
+/// Initialize all values necessary for SDL.
+fn init_sdl() -> Result<(Sdl, VideoSubsystem, EventPump)> {
+    // otherwise windows scales the whole window up on HiDPI displays, and it comes out blurry
+    sdl2::hint::set("SDL_WINDOWS_DPI_AWARENESS", "permonitorv2");
+    let sdl_ctx = sdl2::init().map_err(|e| format_err!(e))?;
+
+    let video_ctx = sdl_ctx.video().map_err(|e| format_err!(e))?;
+    video_ctx
+        .gl_load_library_default()
+        .map_err(|e| format_err!(e))?;
+
+    video_ctx
+        .gl_attr()
+        .set_context_flags()
+        .forward_compatible()
+        .debug()
+        .set();
+    video_ctx
+        .gl_attr()
+        .set_context_major_version(gl_version_part("gl_version_major", DEFAULT_GL_VERSION.0));
+    video_ctx
+        .gl_attr()
+        .set_context_minor_version(gl_version_part("gl_version_minor", DEFAULT_GL_VERSION.1));
+    video_ctx
+        .gl_attr()
+        .set_context_profile(video::GLProfile::Core);
+
+    let event_pump = sdl_ctx.event_pump().map_err(|e| format_err!(e))?;
+
+    Ok((sdl_ctx, video_ctx, event_pump))
+}
+
+/// The part of the OpenGL version in the cvar `name`.
+fn gl_version_part(name: &str, default: u8) -> u8 {
+    console::get::<i64>(name)
+        .and_then(|part| u8::try_from(part).ok())
+        .unwrap_or(default)
+}
+
+/// Setup and create everything for ImGui.
+/// The font is rasterized for `window`'s pixel density, so it's sharp on HiDPI displays.
+fn imgui_create(
+    video_ctx: &VideoSubsystem,
+    window: &video::Window,
+) -> Result<(Context, ImguiSdlPlatform, ImguiRenderer)> {
+    let mut imgui = Context::create();
+    imgui.set_clipboard_backend(SdlClipboard(video_ctx.clipboard()));
+    /* disable creation of files on disc */
+    imgui.set_ini_filename(None);
+    imgui.set_log_filename(None);
+
+    /* setup platform and renderer, and fonts to imgui */
+    let dpi_scale = window.drawable_size().0 as f32 / window.size().0.max(1) as f32;
+    imgui
+        .fonts()
+        .add_font(&[imgui::FontSource::DefaultFontData {
+            config: Some(imgui::FontConfig {
+                size_pixels: IMGUI_FONT_SIZE * dpi_scale,
+                ..imgui::FontConfig::default()
+            }),
+        }]);
+    // imgui lays out in points, so draw the bigger font at the same size
+    imgui.io_mut().font_global_scale = 1. / dpi_scale;
+
+    let imgui_platform = ImguiSdlPlatform::new(&mut imgui);
+    let imgui_renderer = ImguiRenderer::new(&mut imgui).context("creating imgui renderer")?;
+    Ok((imgui, imgui_platform, imgui_renderer))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn noclip_toggles_its_cvar() {
+        register_console();
+        assert_eq!(console::get::<bool>("sv_noclip"), Some(false));
+        console::execute("noclip").unwrap();
+        assert_eq!(console::get::<bool>("sv_noclip"), Some(true));
+        console::execute("noclip").unwrap();
+        assert_eq!(console::get::<bool>("sv_noclip"), Some(false));
+    }
+}
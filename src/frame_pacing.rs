@@ -0,0 +1,338 @@
+//! Exports [`FramePacer`], which decides when each frame starts.
+//!
+//! The mode is picked at runtime with the `vid_pacing` cvar:
+//! - `vsync`: swapping waits for the display.
+//! - `adaptive`: like vsync, but late frames are shown straight away instead of
+//!   waiting a whole extra refresh. Falls back to `vsync` if the driver can't.
+//! - `uncapped`: frames start as soon as the last one is done.
+//! - `cap`: frames start every `1 / fps_max` seconds. The wait sleeps most of the way,
+//!   then spins for the last [`SPIN_MARGIN`], since sleeping alone can overshoot by
+//!   a millisecond or more.
+use std::{
+    collections::VecDeque,
+    fmt::{self, Display},
+    thread,
+    time::{Duration, Instant},
+};
+
+use render::profile_scope;
+use sdl2::{VideoSubsystem, video::SwapInterval};
+
+/// How long before the deadline [`FramePacer`] stops sleeping and starts spinning.
+pub const SPIN_MARGIN: Duration = Duration::from_micros(1500);
+
+/// Number of frame intervals kept for [`PacingStats`].
+pub const PACING_HISTORY_LEN: usize = 300;
+
+/// How frames are paced.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PacingMode {
+    /// Wait for the display on every swap.
+    Vsync,
+    /// Wait for the display, unless the frame is already late.
+    AdaptiveVsync,
+    /// Don't wait at all.
+    Uncapped,
+    /// Start frames at a fixed rate, from `fps_max`.
+    #[default]
+    Capped,
+}
+
+impl PacingMode {
+    /// Every mode.
+    pub const ALL: [PacingMode; 4] = [
+        PacingMode::Vsync,
+        PacingMode::AdaptiveVsync,
+        PacingMode::Uncapped,
+        PacingMode::Capped,
+    ];
+    /// The name of every mode, as the `vid_pacing` cvar takes them.
+    pub const NAMES: [&'static str; 4] = ["vsync", "adaptive", "uncapped", "cap"];
+
+    /// The name used by the `vid_pacing` cvar.
+    pub fn name(self) -> &'static str {
+        match self {
+            PacingMode::Vsync => "vsync",
+            PacingMode::AdaptiveVsync => "adaptive",
+            PacingMode::Uncapped => "uncapped",
+            PacingMode::Capped => "cap",
+        }
+    }
+    /// The mode called `name`, if there is one.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+}
+
+impl Display for PacingMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// How evenly frames have been paced recently. All times are in milliseconds.
+#[derive(Clone, Debug, Default)]
+pub struct PacingStats {
+    /// The mode in use.
+    pub mode: PacingMode,
+    /// Time each frame should take, if the mode aims for one.
+    pub target_ms: Option<f64>,
+    /// Mean time between frames.
+    pub mean_ms: f64,
+    /// Standard deviation of the time between frames.
+    pub jitter_ms: f64,
+    /// Largest difference between a frame's time and the target, or the mean without one.
+    pub worst_ms: f64,
+    /// The difference of each recent frame from the target, or the mean, oldest first.
+    pub errors_ms: Vec<f32>,
+}
+
+/// Paces frames, and measures how well that's going.
+pub struct FramePacer {
+    /// Mode used this frame.
+    mode: PacingMode,
+    /// Swap interval set on the context, so it's only changed when the mode does.
+    swap_interval: Option<SwapInterval>,
+    /// Time of the display's refresh, if it's known.
+    refresh_period: Option<Duration>,
+    /// When the next capped frame should start.
+    deadline: Option<Instant>,
+    /// When the last frame started.
+    last_frame: Instant,
+    /// Time between recent frames, oldest first.
+    intervals: VecDeque<Duration>,
+    /// What each of `intervals` was aiming for, if anything.
+    targets: VecDeque<Option<Duration>>,
+}
+
+impl FramePacer {
+    /// Create a pacer. `refresh_rate` is the display's, in Hz, if it's known.
+    pub fn new(refresh_rate: Option<i32>) -> Self {
+        Self {
+            mode: PacingMode::default(),
+            swap_interval: None,
+            refresh_period: refresh_rate
+                .filter(|rate| *rate > 0)
+                .map(|rate| Duration::from_secs_f64(1. / f64::from(rate))),
+            deadline: None,
+            last_frame: Instant::now(),
+            intervals: VecDeque::with_capacity(PACING_HISTORY_LEN),
+            targets: VecDeque::with_capacity(PACING_HISTORY_LEN),
+        }
+    }
+
+    /// Use `mode` from now on, changing the swap interval if needed.
+    /// Call from the thread that owns the GL context.
+    pub fn set_mode(&mut self, mode: PacingMode, video_ctx: &VideoSubsystem) {
+        self.mode = mode;
+        let wanted = match mode {
+            PacingMode::Vsync => SwapInterval::VSync,
+            PacingMode::AdaptiveVsync => SwapInterval::LateSwapTearing,
+            PacingMode::Uncapped | PacingMode::Capped => SwapInterval::Immediate,
+        };
+        if self.swap_interval == Some(wanted) {
+            return;
+        }
+        let result = video_ctx.gl_set_swap_interval(wanted).or_else(|err| {
+            if wanted != SwapInterval::LateSwapTearing {
+                return Err(err);
+            }
+            log::warn!("adaptive vsync isn't supported ({err}), using vsync");
+            video_ctx.gl_set_swap_interval(SwapInterval::VSync)
+        });
+        if let Err(err) = result {
+            log::error!("couldn't set swap interval for {mode}: {err}");
+        }
+        // only try once, even if it failed
+        self.swap_interval = Some(wanted);
+    }
+
+    /// The mode in use.
+    pub fn mode(&self) -> PacingMode {
+        self.mode
+    }
+
+    /// Time each frame should take in the current mode, if it aims for one.
+    fn target(&self, fps_max: u32) -> Option<Duration> {
+        match self.mode {
+            PacingMode::Vsync | PacingMode::AdaptiveVsync => self.refresh_period,
+            PacingMode::Capped if fps_max > 0 => {
+                Some(Duration::from_secs_f64(1. / f64::from(fps_max)))
+            }
+            PacingMode::Capped | PacingMode::Uncapped => None,
+        }
+    }
+
+    /// Wait until the next frame should start, and record how long this one took.
+    /// Call once per frame, after swapping. Returns the time since the last call.
+    pub fn end_frame(&mut self, fps_max: u32) -> Duration {
+        let target = self.target(fps_max);
+        if self.mode == PacingMode::Capped
+            && let Some(period) = target
+        {
+            let now = Instant::now();
+            // aim for evenly spaced deadlines, but don't try to catch up after a long frame
+            let deadline = self
+                .deadline
+                .map(|deadline| deadline + period)
+                .filter(|deadline| *deadline > now)
+                .unwrap_or(now + period);
+            wait_until(deadline);
+            self.deadline = Some(deadline);
+        } else {
+            self.deadline = None;
+        }
+
+        let now = Instant::now();
+        let interval = now.duration_since(self.last_frame);
+        self.last_frame = now;
+        if self.intervals.len() == PACING_HISTORY_LEN {
+            self.intervals.pop_front();
+            self.targets.pop_front();
+        }
+        self.intervals.push_back(interval);
+        self.targets.push_back(target);
+        interval
+    }
+
+    /// How evenly recent frames were paced.
+    pub fn stats(&self) -> PacingStats {
+        let count = self.intervals.len().max(1) as f64;
+        let ms = |duration: &Duration| duration.as_secs_f64() * 1000.;
+        let mean_ms = self.intervals.iter().map(ms).sum::<f64>() / count;
+        let variance = self
+            .intervals
+            .iter()
+            .map(|interval| (ms(interval) - mean_ms).powi(2))
+            .sum::<f64>()
+            / count;
+        let errors_ms: Vec<f32> = self
+            .intervals
+            .iter()
+            .zip(&self.targets)
+            .map(|(interval, target)| (ms(interval) - target.as_ref().map_or(mean_ms, ms)) as f32)
+            .collect();
+        PacingStats {
+            mode: self.mode,
+            target_ms: self.targets.back().copied().flatten().as_ref().map(ms),
+            mean_ms,
+            jitter_ms: variance.sqrt(),
+            worst_ms: errors_ms
+                .iter()
+                .map(|error| f64::from(error.abs()))
+                .fold(0., f64::max),
+            errors_ms,
+        }
+    }
+}
+
+/// Wait until `deadline`, sleeping while it's far off and spinning for the last [`SPIN_MARGIN`].
+fn wait_until(deadline: Instant) {
+    profile_scope!("sleep");
+    let now = Instant::now();
+    if let Some(sleep) = deadline
+        .checked_duration_since(now)
+        .and_then(|left| left.checked_sub(SPIN_MARGIN))
+    {
+        thread::sleep(sleep);
+    }
+    while Instant::now() < deadline {
+        std::hint::spin_loop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A pacer that's already recorded `intervals`, each aiming for `target`.
+    fn pacer_with(mode: PacingMode, intervals_ms: &[u64], target_ms: Option<u64>) -> FramePacer {
+        let mut pacer = FramePacer::new(None);
+        pacer.mode = mode;
+        for interval in intervals_ms {
+            pacer.intervals.push_back(Duration::from_millis(*interval));
+            pacer
+                .targets
+                .push_back(target_ms.map(Duration::from_millis));
+        }
+        pacer
+    }
+
+    #[test]
+    fn mode_names() {
+        for (mode, name) in PacingMode::ALL.into_iter().zip(PacingMode::NAMES) {
+            assert_eq!(mode.name(), name);
+            assert_eq!(PacingMode::from_name(name), Some(mode));
+            assert_eq!(mode.to_string(), name);
+        }
+        assert_eq!(PacingMode::from_name("Vsync"), None);
+        assert_eq!(PacingMode::from_name(""), None);
+    }
+
+    #[test]
+    fn targets() {
+        let mut pacer = FramePacer::new(Some(50));
+        assert_eq!(pacer.target(100), Some(Duration::from_millis(10)));
+        assert_eq!(pacer.target(0), None);
+        pacer.mode = PacingMode::Vsync;
+        assert_eq!(pacer.target(100), Some(Duration::from_millis(20)));
+        pacer.mode = PacingMode::Uncapped;
+        assert_eq!(pacer.target(100), None);
+        // unknown refresh rates don't give vsync a target
+        let mut pacer = FramePacer::new(Some(0));
+        pacer.mode = PacingMode::AdaptiveVsync;
+        assert_eq!(pacer.target(100), None);
+    }
+
+    #[test]
+    fn stats_without_frames() {
+        let stats = FramePacer::new(None).stats();
+        assert_eq!(stats.mean_ms, 0.);
+        assert_eq!(stats.jitter_ms, 0.);
+        assert_eq!(stats.worst_ms, 0.);
+        assert!(stats.errors_ms.is_empty());
+    }
+
+    #[test]
+    fn stats_against_a_target() {
+        let stats = pacer_with(PacingMode::Capped, &[10, 12, 8, 10], Some(10)).stats();
+        assert_eq!(stats.target_ms, Some(10.));
+        assert!((stats.mean_ms - 10.).abs() < 1e-9);
+        // deviations of 0, 2, -2 and 0
+        assert!((stats.jitter_ms - 2_f64.sqrt()).abs() < 1e-9);
+        assert_eq!(stats.errors_ms, [0., 2., -2., 0.]);
+        assert!((stats.worst_ms - 2.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn stats_against_the_mean() {
+        let stats = pacer_with(PacingMode::Uncapped, &[4, 8], None).stats();
+        assert_eq!(stats.target_ms, None);
+        assert!((stats.mean_ms - 6.).abs() < 1e-9);
+        assert!((stats.jitter_ms - 2.).abs() < 1e-9);
+        assert_eq!(stats.errors_ms, [-2., 2.]);
+    }
+
+    #[test]
+    fn history_is_capped() {
+        let mut pacer = FramePacer::new(None);
+        pacer.mode = PacingMode::Uncapped;
+        for _ in 0..PACING_HISTORY_LEN + 3 {
+            pacer.end_frame(0);
+        }
+        assert_eq!(pacer.intervals.len(), PACING_HISTORY_LEN);
+        assert_eq!(pacer.targets.len(), PACING_HISTORY_LEN);
+    }
+
+    #[test]
+    fn capped_frames_wait_for_the_period() {
+        let start = Instant::now();
+        let mut pacer = FramePacer::new(None);
+        let period = pacer.target(200).unwrap();
+        for _ in 0..3 {
+            pacer.end_frame(200);
+        }
+        // deadlines are evenly spaced, so one frame can be short if the last one started late
+        assert!(start.elapsed() >= period * 3);
+    }
+}
@@ -0,0 +1,491 @@
+//! OpenGL debug output: the callback the driver reports problems to, and its configuration.
+//!
+//! What gets reported is configured by a [`GlDebugConfig`], read from the `gl_debug` setting
+//! or [`GL_DEBUG_ENV`] by [`GlDebugConfig::from_env_or`] as a spec like
+//! `sync,min=low,mute=131185 131218,on_error=backtrace`.
+//! The part that decides which messages are logged, the [`GlDebugFilter`], can be changed
+//! at runtime with [`set_filter`].
+use std::{
+    backtrace::Backtrace,
+    collections::BTreeSet,
+    ffi::{CStr, c_char, c_void},
+    fmt,
+    panic::{self, AssertUnwindSafe},
+    ptr::null,
+    str::FromStr,
+    sync::Mutex,
+};
+
+use render::gl::{self, types::GLenum};
+use thiserror::Error;
+
+/// Environment variable holding the debug output spec.
+pub const GL_DEBUG_ENV: &str = "BACKSHOT_GL_DEBUG";
+
+/// How serious a debug message is, least serious first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// Not a problem, like a buffer being placed in video memory.
+    Notification,
+    /// Redundant state changes, or unimportant undefined behaviour.
+    Low,
+    /// Major performance warnings, or deprecated functionality.
+    Medium,
+    /// Errors, or undefined behaviour that will cause problems.
+    High,
+}
+
+impl Severity {
+    /// Every severity, least serious first.
+    pub const ALL: [Severity; 4] = [
+        Severity::Notification,
+        Severity::Low,
+        Severity::Medium,
+        Severity::High,
+    ];
+
+    /// Decode a `GL_DEBUG_SEVERITY_*` value.
+    pub fn from_gl(severity: GLenum) -> Option<Self> {
+        match severity {
+            gl::DEBUG_SEVERITY_NOTIFICATION => Some(Severity::Notification),
+            gl::DEBUG_SEVERITY_LOW => Some(Severity::Low),
+            gl::DEBUG_SEVERITY_MEDIUM => Some(Severity::Medium),
+            gl::DEBUG_SEVERITY_HIGH => Some(Severity::High),
+            _ => None,
+        }
+    }
+
+    /// The name used in specs and the UI.
+    pub fn name(self) -> &'static str {
+        match self {
+            Severity::Notification => "notification",
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+        }
+    }
+
+    /// The log level messages of this severity are logged at.
+    fn level(self) -> log::Level {
+        match self {
+            Severity::Notification => log::Level::Debug,
+            Severity::Low => log::Level::Info,
+            Severity::Medium => log::Level::Warn,
+            Severity::High => log::Level::Error,
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// What to do when the driver reports a `GL_DEBUG_TYPE_ERROR` message.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ErrorAction {
+    /// Only log it.
+    #[default]
+    Log,
+    /// Log it along with a backtrace of the call that caused it.
+    /// Only useful with synchronous output.
+    Backtrace,
+    /// Log it, then trap into the debugger.
+    /// Without a debugger attached, this kills the process.
+    Break,
+}
+
+impl ErrorAction {
+    /// Every action.
+    pub const ALL: [ErrorAction; 3] =
+        [ErrorAction::Log, ErrorAction::Backtrace, ErrorAction::Break];
+
+    /// The name used in specs and the UI.
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorAction::Log => "log",
+            ErrorAction::Backtrace => "backtrace",
+            ErrorAction::Break => "break",
+        }
+    }
+}
+
+impl fmt::Display for ErrorAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Which debug messages are logged, and what happens on errors.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GlDebugFilter {
+    /// Messages less serious than this are dropped.
+    pub min_severity: Severity,
+    /// Messages with these ids are dropped.
+    pub muted_ids: BTreeSet<u32>,
+    /// What to do on `GL_DEBUG_TYPE_ERROR` messages that aren't dropped.
+    pub on_error: ErrorAction,
+}
+
+impl GlDebugFilter {
+    /// The filter used before [`setup`] is called.
+    const DEFAULT: Self = Self {
+        min_severity: Severity::Low,
+        muted_ids: BTreeSet::new(),
+        on_error: ErrorAction::Log,
+    };
+
+    /// Whether a message should be logged. Messages with an unknown severity always are.
+    pub fn allows(&self, id: u32, severity: Option<Severity>) -> bool {
+        severity.is_none_or(|severity| severity >= self.min_severity)
+            && !self.muted_ids.contains(&id)
+    }
+}
+
+impl Default for GlDebugFilter {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// How debug output is set up for a context.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GlDebugConfig {
+    /// Ask the driver for debug output at all.
+    pub enabled: bool,
+    /// Report messages from inside the call that caused them, rather than whenever
+    /// the driver gets to it. Slower, but backtraces point at the culprit.
+    pub synchronous: bool,
+    /// Which messages are logged.
+    pub filter: GlDebugFilter,
+}
+
+impl Default for GlDebugConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            synchronous: true,
+            filter: GlDebugFilter::default(),
+        }
+    }
+}
+
+/// Errors parsing a debug output spec.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum GlDebugConfigError {
+    /// A part of the spec isn't a known flag or key.
+    #[error("unknown gl debug option \"{0}\"")]
+    UnknownOption(String),
+    /// A key was given a value it doesn't take. Carries the key and the value.
+    #[error("bad value \"{1}\" for gl debug option \"{0}\"")]
+    BadValue(String, String),
+}
+
+impl GlDebugConfig {
+    /// The config described by [`GL_DEBUG_ENV`], or by `spec` if it isn't set.
+    ///
+    /// # Errors
+    /// Errors if the spec used is invalid.
+    pub fn from_env_or(spec: &str) -> Result<Self, GlDebugConfigError> {
+        std::env::var(GL_DEBUG_ENV).map_or_else(|_| spec.parse(), |spec| spec.parse())
+    }
+}
+
+impl FromStr for GlDebugConfig {
+    type Err = GlDebugConfigError;
+    /// Parse a comma separated list of options, applied over the default config:
+    /// `on`/`off`, `sync`/`async`, `min=<severity>`, `mute=<id> <id>...`, `on_error=log|backtrace|break`.
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let mut config = Self::default();
+        for part in spec
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+        {
+            let bad_value = |key: &str, value: &str| {
+                GlDebugConfigError::BadValue(key.to_owned(), value.to_owned())
+            };
+            match part.split_once('=').map(|(k, v)| (k.trim(), v.trim())) {
+                None => match part {
+                    "on" => config.enabled = true,
+                    "off" => config.enabled = false,
+                    "sync" => config.synchronous = true,
+                    "async" => config.synchronous = false,
+                    _ => return Err(GlDebugConfigError::UnknownOption(part.to_owned())),
+                },
+                Some(("min", value)) => {
+                    config.filter.min_severity = Severity::ALL
+                        .into_iter()
+                        .find(|severity| severity.name() == value)
+                        .ok_or_else(|| bad_value("min", value))?;
+                }
+                Some(("mute", value)) => {
+                    for id in value.split_whitespace() {
+                        let id = id.parse().map_err(|_| bad_value("mute", id))?;
+                        config.filter.muted_ids.insert(id);
+                    }
+                }
+                Some(("on_error", value)) => {
+                    config.filter.on_error = ErrorAction::ALL
+                        .into_iter()
+                        .find(|action| action.name() == value)
+                        .ok_or_else(|| bad_value("on_error", value))?;
+                }
+                Some((key, _)) => return Err(GlDebugConfigError::UnknownOption(key.to_owned())),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// The filter the callback uses, shared with whichever thread the driver calls it from.
+static FILTER: Mutex<GlDebugFilter> = Mutex::new(GlDebugFilter::DEFAULT);
+
+/// The filter the callback currently uses.
+pub fn filter() -> GlDebugFilter {
+    FILTER
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .clone()
+}
+
+/// Change which messages the callback logs. Takes effect immediately.
+pub fn set_filter(filter: GlDebugFilter) {
+    *FILTER
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner()) = filter;
+}
+
+/// Set up debug output for the current context, as described by `config`.
+pub fn setup(config: &GlDebugConfig) {
+    set_filter(config.filter.clone());
+    unsafe {
+        if !config.enabled {
+            gl::Disable(gl::DEBUG_OUTPUT);
+            gl::DebugMessageCallback(None, null());
+            return;
+        }
+        gl::Enable(gl::DEBUG_OUTPUT);
+        if config.synchronous {
+            gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        } else {
+            gl::Disable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        }
+        gl::DebugMessageCallback(Some(gl_debug_output), null());
+        // filtering happens in the callback, so it can change without touching the context
+        gl::DebugMessageControl(
+            gl::DONT_CARE,
+            gl::DONT_CARE,
+            gl::DONT_CARE,
+            0,
+            null(),
+            gl::TRUE,
+        );
+    }
+}
+
+/// Name of a `GL_DEBUG_SOURCE_*` value.
+fn source_name(source: GLenum) -> String {
+    match source {
+        gl::DEBUG_SOURCE_API => "API".into(),
+        gl::DEBUG_SOURCE_WINDOW_SYSTEM => "Window System".into(),
+        gl::DEBUG_SOURCE_SHADER_COMPILER => "Shader Compiler".into(),
+        gl::DEBUG_SOURCE_THIRD_PARTY => "Third Party".into(),
+        gl::DEBUG_SOURCE_APPLICATION => "Application".into(),
+        gl::DEBUG_SOURCE_OTHER => "Other".into(),
+        unknown => format!("Unknown ({unknown:#x})"),
+    }
+}
+
+/// Name of a `GL_DEBUG_TYPE_*` value.
+fn type_name(output_type: GLenum) -> String {
+    match output_type {
+        gl::DEBUG_TYPE_ERROR => "Error".into(),
+        gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR => "Deprecated Behaviour".into(),
+        gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR => "Undefined Behaviour".into(),
+        gl::DEBUG_TYPE_PORTABILITY => "Portability".into(),
+        gl::DEBUG_TYPE_PERFORMANCE => "Performance".into(),
+        gl::DEBUG_TYPE_MARKER => "Marker".into(),
+        gl::DEBUG_TYPE_PUSH_GROUP => "Push Group".into(),
+        gl::DEBUG_TYPE_POP_GROUP => "Pop Group".into(),
+        gl::DEBUG_TYPE_OTHER => "Other".into(),
+        unknown => format!("Unknown ({unknown:#x})"),
+    }
+}
+
+/// Name of a `GL_DEBUG_SEVERITY_*` value.
+fn severity_name(severity: GLenum) -> String {
+    Severity::from_gl(severity).map_or_else(
+        || format!("Unknown ({severity:#x})"),
+        |severity| severity.name().into(),
+    )
+}
+
+/// Copy the message out of the callback's arguments.
+///
+/// # Safety
+/// `message` must be null, or point to `length` bytes, or a nul terminated string if `length` is negative.
+unsafe fn read_message(length: gl::types::GLsizei, message: *const c_char) -> String {
+    if message.is_null() {
+        return String::from("<no message>");
+    }
+    let bytes = match usize::try_from(length) {
+        Ok(length) => unsafe { std::slice::from_raw_parts(message.cast::<u8>(), length) },
+        Err(_) => unsafe { CStr::from_ptr(message) }.to_bytes(),
+    };
+    String::from_utf8_lossy(bytes).trim_end().to_owned()
+}
+
+// void APIENTRY glDebugOutput(GLenum source, GLenum type, unsigned int id, GLenum severity,
+//                            GLsizei length, const char *message, const void *userParam);
+
+/// OpenGL debug output callback.
+extern "system" fn gl_debug_output(
+    source: GLenum,
+    output_type: GLenum,
+    id: gl::types::GLuint,
+    severity: GLenum,
+    length: gl::types::GLsizei,
+    message: *const c_char,
+    _user_param: *mut c_void,
+) {
+    // unwinding into the driver is undefined behaviour, so a panic stops here
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        let decoded_severity = Severity::from_gl(severity);
+        let filter = filter();
+        if !filter.allows(id, decoded_severity) {
+            return;
+        }
+        let message = unsafe { read_message(length, message) };
+        let is_error = output_type == gl::DEBUG_TYPE_ERROR;
+        let level = if is_error {
+            log::Level::Error
+        } else {
+            decoded_severity.map_or(log::Level::Warn, Severity::level)
+        };
+        log::log!(
+            target: "gl",
+            level,
+            "Source: {}, Type: {}, Severity: {}, Id: {id}, Message: {message}",
+            source_name(source),
+            type_name(output_type),
+            severity_name(severity),
+        );
+        if !is_error {
+            return;
+        }
+        match filter.on_error {
+            ErrorAction::Log => {}
+            ErrorAction::Backtrace => {
+                log::error!(target: "gl", "backtrace:\n{}", Backtrace::force_capture());
+            }
+            ErrorAction::Break => core::arch::breakpoint(),
+        }
+    }));
+    if result.is_err() {
+        // the panic hook has already reported it
+        log::error!(target: "gl", "panicked handling debug message {id}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_spec_is_the_default() {
+        assert_eq!("".parse(), Ok(GlDebugConfig::default()));
+    }
+
+    #[test]
+    fn full_spec() {
+        let config: GlDebugConfig = "off, async, min=high, mute=131185 131218, on_error=break"
+            .parse()
+            .unwrap();
+        assert!(!config.enabled);
+        assert!(!config.synchronous);
+        assert_eq!(config.filter.min_severity, Severity::High);
+        assert_eq!(config.filter.muted_ids, BTreeSet::from([131185, 131218]));
+        assert_eq!(config.filter.on_error, ErrorAction::Break);
+    }
+
+    #[test]
+    fn later_options_win() {
+        let config: GlDebugConfig = "off,on,min=high,min=notification".parse().unwrap();
+        assert!(config.enabled);
+        assert_eq!(config.filter.min_severity, Severity::Notification);
+    }
+
+    #[test]
+    fn bad_specs() {
+        assert_eq!(
+            "loud".parse::<GlDebugConfig>(),
+            Err(GlDebugConfigError::UnknownOption("loud".to_owned()))
+        );
+        assert_eq!(
+            "volume=11".parse::<GlDebugConfig>(),
+            Err(GlDebugConfigError::UnknownOption("volume".to_owned()))
+        );
+        assert_eq!(
+            "min=severe".parse::<GlDebugConfig>(),
+            Err(GlDebugConfigError::BadValue(
+                "min".to_owned(),
+                "severe".to_owned()
+            ))
+        );
+        assert_eq!(
+            "mute=1 x".parse::<GlDebugConfig>(),
+            Err(GlDebugConfigError::BadValue(
+                "mute".to_owned(),
+                "x".to_owned()
+            ))
+        );
+        assert_eq!(
+            "on_error=panic".parse::<GlDebugConfig>(),
+            Err(GlDebugConfigError::BadValue(
+                "on_error".to_owned(),
+                "panic".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn filter_allows() {
+        let filter = GlDebugFilter {
+            min_severity: Severity::Medium,
+            muted_ids: BTreeSet::from([7]),
+            on_error: ErrorAction::Log,
+        };
+        assert!(filter.allows(1, Some(Severity::High)));
+        assert!(filter.allows(1, Some(Severity::Medium)));
+        assert!(!filter.allows(1, Some(Severity::Low)));
+        assert!(filter.allows(1, None));
+        assert!(!filter.allows(7, Some(Severity::High)));
+        assert!(!filter.allows(7, None));
+    }
+
+    #[test]
+    fn severities_from_gl() {
+        assert_eq!(
+            Severity::from_gl(gl::DEBUG_SEVERITY_HIGH),
+            Some(Severity::High)
+        );
+        assert_eq!(
+            Severity::from_gl(gl::DEBUG_SEVERITY_NOTIFICATION),
+            Some(Severity::Notification)
+        );
+        assert_eq!(Severity::from_gl(0), None);
+    }
+
+    #[test]
+    fn messages_with_and_without_length() {
+        let message = c"buffer detailed info\n";
+        // SAFETY: both point into `message`, which is nul terminated
+        unsafe {
+            assert_eq!(read_message(-1, message.as_ptr()), "buffer detailed info");
+            assert_eq!(read_message(6, message.as_ptr()), "buffer");
+            assert_eq!(read_message(3, std::ptr::null()), "<no message>");
+        }
+    }
+}
@@ -0,0 +1,39 @@
+//! A rendering engine, which can load maps into memory, and render them to a screen.
+//!
+//! ## What is this/what will this be?
+//! - [x] A 3d renderer
+//! - [ ] A map loader
+//! - [ ] Some form of backing for a game
+//!
+//! Games implement [`Game`], and are run with [`Engine::run`].
+//! ## What is this NOT?
+//! * A portable interface for you to make your own games
+//!   * at least, not yet
+//! * A real project that will have an end
+#![feature(breakpoint)]
+#![allow(dead_code, clippy::let_and_return)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+pub extern crate render;
+pub extern crate world;
+
+pub mod config;
+pub mod console;
+pub mod engine;
+mod frame_pacing;
+mod gl_debug;
+mod logging;
+mod map;
+pub mod timestep;
+mod ui;
+pub mod window;
+
+pub use engine::{Engine, EngineConfig, Game};
+pub use sdl2;
+
+/// Default for the `fps_max` cvar.
+///
+/// Note: this is SOFT due to the fact that we may or may not sleep
+/// less, since we do calculations to not over-sleep, which may not
+/// be perfect if the frame was rendered very quickly
+pub const SOFT_FPS_CAP: u64 = 30;
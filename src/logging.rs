@@ -0,0 +1,286 @@
+//! Logging, through the [`log`] macros.
+//!
+//! Records go to stderr, and to a log file if one was given. Which records are
+//! kept is decided per target (by default, the module path of the caller) by a
+//! filter spec like `info,backshot_engine::map=trace,gl=warn`: a default level,
+//! then `target=level` overrides. The longest matching target wins.
+//!
+//! The last [`RECENT_CAPACITY`] kept records are also held in memory for the log console,
+//! see [`with_recent`].
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, LineWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Mutex,
+    time::Instant,
+};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use thiserror::Error;
+
+/// Environment variable holding the filter spec.
+pub const LOG_FILTER_ENV: &str = "BACKSHOT_LOG";
+/// Environment variable holding a path to also log to.
+pub const LOG_FILE_ENV: &str = "BACKSHOT_LOG_FILE";
+/// Filter spec used when [`LOG_FILTER_ENV`] isn't set.
+pub const DEFAULT_LOG_FILTER: &str = "info";
+
+/// Number of records kept in memory.
+pub const RECENT_CAPACITY: usize = 2000;
+
+/// A record kept in memory.
+#[derive(Clone, Debug)]
+pub struct LogEntry {
+    /// Counts up with each record, and is never reused.
+    pub seq: u64,
+    /// Seconds since the logger was installed.
+    pub time: f64,
+    /// Severity.
+    pub level: Level,
+    /// Usually the module path of the caller.
+    pub target: String,
+    /// The formatted message.
+    pub message: String,
+}
+
+/// Records kept in memory, oldest first.
+struct RecentLog {
+    /// The records.
+    entries: VecDeque<LogEntry>,
+    /// `seq` of the next record.
+    next_seq: u64,
+}
+
+/// The recent records, shared by every thread.
+static RECENT: Mutex<RecentLog> = Mutex::new(RecentLog {
+    entries: VecDeque::new(),
+    next_seq: 0,
+});
+
+/// Look at the most recent records, oldest first.
+///
+/// New records wait for `f` to return, so `f` must not log, or call anything that might
+/// (like imgui, whose clipboard logs failures): that would deadlock. Copy out what's needed.
+pub fn with_recent<R>(f: impl FnOnce(&VecDeque<LogEntry>) -> R) -> R {
+    let recent = RECENT
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    f(&recent.entries)
+}
+
+/// Errors setting up logging.
+#[derive(Error, Debug)]
+pub enum LogInitError {
+    /// A filter spec named a level that doesn't exist. Carries the spec and the level.
+    #[error("bad log filter \"{0}\": unknown level \"{1}\"")]
+    BadLevel(String, String),
+    /// The log file couldn't be opened.
+    #[error("failed to open log file {0}: {1}")]
+    File(PathBuf, io::Error),
+    /// [`init`] was called twice.
+    #[error("a logger is already set")]
+    AlreadySet,
+}
+
+/// Which levels are kept, per target.
+#[derive(Clone, Debug)]
+pub struct LogFilter {
+    /// Level for targets without an override.
+    default: LevelFilter,
+    /// Overrides, sorted longest target first so the first match is the most specific.
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilter {
+    /// The level kept for `target`.
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.targets
+            .iter()
+            .find(|(prefix, _)| {
+                target
+                    .strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .map_or(self.default, |(_, level)| *level)
+    }
+    /// The most verbose level kept for any target.
+    pub fn max_level(&self) -> LevelFilter {
+        self.targets
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, |max, level| max.max(level))
+    }
+}
+
+impl FromStr for LogFilter {
+    type Err = LogInitError;
+    fn from_str(spec: &str) -> Result<Self, Self::Err> {
+        let parse_level = |level: &str| {
+            level
+                .trim()
+                .parse::<LevelFilter>()
+                .map_err(|_| LogInitError::BadLevel(spec.to_owned(), level.trim().to_owned()))
+        };
+        let mut filter = LogFilter {
+            default: LevelFilter::Info,
+            targets: vec![],
+        };
+        for part in spec
+            .split(',')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+        {
+            match part.split_once('=') {
+                Some((target, level)) => filter
+                    .targets
+                    .push((target.trim().to_owned(), parse_level(level)?)),
+                None => filter.default = parse_level(part)?,
+            }
+        }
+        filter
+            .targets
+            .sort_by_key(|(target, _)| std::cmp::Reverse(target.len()));
+        Ok(filter)
+    }
+}
+
+/// The logger installed by [`init`].
+struct Logger {
+    /// Which records to keep.
+    filter: LogFilter,
+    /// The log file, if there is one.
+    file: Option<Mutex<LineWriter<File>>>,
+    /// Timestamps are relative to this.
+    start: Instant,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter.level_for(metadata.target())
+    }
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let time = self.start.elapsed().as_secs_f64();
+        let message = record.args().to_string();
+        let line = format!(
+            "[{time:>9.3} {:<5} {}] {message}",
+            record.level(),
+            record.target(),
+        );
+        // there's nowhere left to report a failure to log to
+        let _ = writeln!(io::stderr().lock(), "{line}");
+        if let Some(file) = &self.file
+            && let Ok(mut file) = file.lock()
+        {
+            let _ = writeln!(file, "{line}");
+        }
+
+        let mut recent = RECENT
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        if recent.entries.len() == RECENT_CAPACITY {
+            recent.entries.pop_front();
+        }
+        let seq = recent.next_seq;
+        recent.next_seq += 1;
+        recent.entries.push_back(LogEntry {
+            seq,
+            time,
+            level: record.level(),
+            target: record.target().to_owned(),
+            message,
+        });
+    }
+    fn flush(&self) {
+        if let Some(file) = &self.file
+            && let Ok(mut file) = file.lock()
+        {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// Install the logger, keeping records allowed by `filter` and
+/// appending them to `file` as well as stderr.
+///
+/// # Errors
+/// Errors if the file can't be opened, or a logger was already installed.
+pub fn init(filter: LogFilter, file: Option<&Path>) -> Result<(), LogInitError> {
+    let file = file
+        .map(|path| {
+            File::options()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map(|file| Mutex::new(LineWriter::new(file)))
+                .map_err(|err| LogInitError::File(path.to_owned(), err))
+        })
+        .transpose()?;
+    let max_level = filter.max_level();
+    let logger = Logger {
+        filter,
+        file,
+        start: Instant::now(),
+    };
+    log::set_boxed_logger(Box::new(logger)).map_err(|_| LogInitError::AlreadySet)?;
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+/// Install the logger configured by [`LOG_FILTER_ENV`] and [`LOG_FILE_ENV`].
+///
+/// # Errors
+/// See [`init`]. Also errors if the filter spec is invalid.
+pub fn init_from_env() -> Result<(), LogInitError> {
+    let spec = std::env::var(LOG_FILTER_ENV).unwrap_or_else(|_| DEFAULT_LOG_FILTER.to_owned());
+    let file = std::env::var_os(LOG_FILE_ENV).map(PathBuf::from);
+    init(spec.parse()?, file.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_level_only() {
+        let filter: LogFilter = "warn".parse().unwrap();
+        assert_eq!(filter.level_for("anything"), LevelFilter::Warn);
+        assert_eq!(filter.max_level(), LevelFilter::Warn);
+    }
+
+    #[test]
+    fn empty_spec_is_info() {
+        let filter: LogFilter = " , ".parse().unwrap();
+        assert_eq!(filter.level_for("gl"), LevelFilter::Info);
+    }
+
+    #[test]
+    fn longest_target_wins() {
+        let filter: LogFilter = "info, a::b=trace ,a=error,gl=off".parse().unwrap();
+        assert_eq!(filter.level_for("a"), LevelFilter::Error);
+        assert_eq!(filter.level_for("a::c"), LevelFilter::Error);
+        assert_eq!(filter.level_for("a::b"), LevelFilter::Trace);
+        assert_eq!(filter.level_for("a::b::c"), LevelFilter::Trace);
+        assert_eq!(filter.level_for("gl"), LevelFilter::Off);
+        assert_eq!(filter.level_for("other"), LevelFilter::Info);
+        assert_eq!(filter.max_level(), LevelFilter::Trace);
+    }
+
+    #[test]
+    fn targets_match_whole_path_segments() {
+        let filter: LogFilter = "info,gl=off".parse().unwrap();
+        assert_eq!(filter.level_for("glow"), LevelFilter::Info);
+    }
+
+    #[test]
+    fn unknown_levels_are_errors() {
+        let err = "info,gl=loud".parse::<LogFilter>().unwrap_err();
+        assert!(
+            matches!(err, LogInitError::BadLevel(spec, level) if spec == "info,gl=loud" && level == "loud")
+        );
+    }
+}
@@ -5,9 +5,13 @@ use std::{
     io::{self, Read},
 };
 
-use render::glm::vec3;
+use render::{glm::vec3, profile_scope};
 use thiserror::Error;
-use world::Vertex;
+use world::{
+    Vertex, World,
+    brush::{BrushPlane, NGonPlane, PolyBrush, TriPlane},
+    class::{ClassRegistry, Properties},
+};
 
 /// It is guaranteed that all `PlaneDatas`
 /// have 3+ vertices.
@@ -15,7 +19,14 @@ use world::Vertex;
 pub struct PlaneData {
     verts: Box<[Vertex]>,
 }
-pub struct EntityData;
+/// One entity from the map's `.E` section.
+#[derive(Debug)]
+pub struct EntityData {
+    /// The line its opening `{` is on, for errors.
+    pub line: usize,
+    /// Its keys and values.
+    pub properties: Properties,
+}
 
 pub struct BrushData {
     pub planes: Box<[PlaneData]>,
@@ -30,6 +41,117 @@ impl MapData {
     pub fn new(entities: Box<[EntityData]>, brushes: Box<[BrushData]>) -> Self {
         Self { entities, brushes }
     }
+    /// All brushes declared in the map.
+    pub fn brushes(&self) -> &[BrushData] {
+        &self.brushes
+    }
+    /// All entities declared in the map.
+    pub fn entities(&self) -> &[EntityData] {
+        &self.entities
+    }
+    /// Spawn every entity in this map into `world` with the class in `classes` its `classname`
+    /// names, and add every brush no entity claimed with its `brushes` key as static geometry.
+    ///
+    /// Entities that can't be spawned are skipped with a warning, rather than failing the map.
+    /// Their brushes are added as static geometry instead, so the map keeps its shape.
+    pub fn add_to_world(&self, world: &mut World, classes: &ClassRegistry) {
+        let mut claimed = vec![false; self.brushes.len()];
+        let entity_brushes: Vec<Option<Vec<usize>>> = self
+            .entities
+            .iter()
+            .map(|entity| {
+                self.claim_brushes(entity, &mut claimed)
+                    .inspect_err(|err| log::warn!("line {}: skipping entity: {err}", entity.line))
+                    .ok()
+            })
+            .collect();
+
+        for (brush, claimed) in self.brushes.iter().zip(claimed) {
+            if !claimed {
+                world.add_brush(Box::new(brush.to_brush()));
+            }
+        }
+        for (entity, indices) in self.entities.iter().zip(entity_brushes) {
+            let Some(indices) = indices else {
+                continue;
+            };
+            let brushes: Vec<PolyBrush> = indices
+                .iter()
+                .map(|&index| self.brushes[index].to_brush())
+                .collect();
+            if let Err(err) =
+                classes.spawn_with_brushes(world.entities_mut(), &entity.properties, &brushes)
+            {
+                if brushes.is_empty() {
+                    log::warn!("line {}: skipping entity: {err}", entity.line);
+                    continue;
+                }
+                log::warn!(
+                    "line {}: skipping entity, keeping its brushes as static geometry: {err}",
+                    entity.line
+                );
+                for brush in brushes {
+                    world.add_brush(Box::new(brush));
+                }
+            }
+        }
+    }
+    /// The indices in `entity`'s `brushes` key, marking each one in `claimed`.
+    ///
+    /// # Errors
+    /// Errors if the key is malformed, or names a brush that doesn't exist or that another
+    /// entity already claimed. Nothing is marked then.
+    fn claim_brushes(
+        &self,
+        entity: &EntityData,
+        claimed: &mut [bool],
+    ) -> Result<Vec<usize>, String> {
+        let indices = entity
+            .properties
+            .parse::<Vec<u32>>("brushes")
+            .map_err(|err| err.to_string())?
+            .unwrap_or_default();
+        let mut indices: Vec<usize> = indices.into_iter().map(|index| index as usize).collect();
+        indices.sort_unstable();
+        indices.dedup();
+        for &index in &indices {
+            match claimed.get(index) {
+                None => {
+                    return Err(format!(
+                        "brush {index} does not exist, the map has {}",
+                        claimed.len()
+                    ));
+                }
+                Some(true) => return Err(format!("brush {index} belongs to another entity")),
+                Some(false) => {}
+            }
+        }
+        for &index in &indices {
+            claimed[index] = true;
+        }
+        Ok(indices)
+    }
+}
+
+impl PlaneData {
+    /// The vertices of this plane. There are always 3 or more.
+    pub fn verts(&self) -> &[Vertex] {
+        &self.verts
+    }
+    /// Convert this to a plane the world can hold.
+    pub fn to_plane(&self) -> BrushPlane {
+        match &*self.verts {
+            [a, b, c] => BrushPlane::Triangle(TriPlane([a.clone(), b.clone(), c.clone()])),
+            verts => BrushPlane::NGon(NGonPlane(verts.into())),
+        }
+    }
+}
+
+impl BrushData {
+    /// Convert this to a brush the world can hold.
+    pub fn to_brush(&self) -> PolyBrush {
+        PolyBrush(self.planes.iter().map(PlaneData::to_plane).collect())
+    }
 }
 
 #[derive(Error, Debug)]
@@ -44,6 +166,11 @@ pub enum ParseError {
     UnexpectedEOF(usize, String),
 }
 
+/// Log target for a trace of every byte the parser looks at. It's very noisy, so it has its own
+/// target outside this module's path, and tracing the parser doesn't turn it on. Turn it on with
+/// `map_parser_bytes=trace`.
+const BYTES_TARGET: &str = "map_parser_bytes";
+
 const MIN_SECTION_HEADER_SIZE_PART_LEN: usize = "0\n".len();
 
 #[cfg(target_pointer_width = "32")]
@@ -96,6 +223,7 @@ type LeftoverBytes = Box<[u8]>;
 type Buffer = Box<[u8]>;
 
 pub fn parse_map(mut input: impl Read) -> Result<MapData, ParseError> {
+    profile_scope!("parse_map");
     check_header_valid(&mut input)?;
     // the header is correct, parse map as normal
 
@@ -193,8 +321,7 @@ pub fn parse_map(mut input: impl Read) -> Result<MapData, ParseError> {
             }
         }
 
-        dbg!(buf_ptr);
-        dbg!(ended_at);
+        log::trace!("section header ends at byte {buf_ptr} ({ended_at} into size part)");
         // eprintln!(
         //     "if we cut size_part to ended_at: '{}'",
         //     String::from_utf8_lossy(&size_part[..ended_at])
@@ -220,7 +347,7 @@ pub fn parse_map(mut input: impl Read) -> Result<MapData, ParseError> {
             .parse()
             .expect("digits checked earlier should be able to make usize");
 
-        dbg!(size_of_section);
+        log::trace!("section size: {size_of_section}");
         err_ctx.line_count += 1;
 
         let leftover_bytes = &buf_safe[buf_ptr..];
@@ -233,7 +360,7 @@ pub fn parse_map(mut input: impl Read) -> Result<MapData, ParseError> {
                     size_of_section,
                     leftover_bytes,
                 )?;
-                eprintln!("parsed brushes section successfully");
+                log::debug!("parsed brushes section successfully");
                 brushes = Some(result);
                 leftover
             }
@@ -244,7 +371,7 @@ pub fn parse_map(mut input: impl Read) -> Result<MapData, ParseError> {
                     size_of_section,
                     leftover_bytes,
                 )?;
-                eprintln!("parsed entities section successfully");
+                log::debug!("parsed entities section successfully");
                 entities = Some(result);
                 leftover
             }
@@ -279,14 +406,14 @@ fn read_until_after_byte(
         )))?;
         // dbg!(byte_at_idx as char);
         if byte != b'\n' && is_comment(buf, our_ptr) {
-            eprintln!("byte is comment, reading until newline");
+            log::trace!("byte is comment, reading until newline");
             read_until_after_newline(buf, err_ctx, &mut our_ptr)?;
             // ensure we don't accidentally skip another character
             // fixes breaking if newline goes directly into a comment
             continue 'reading;
         }
         if byte_at_idx == byte {
-            eprintln!("matched byte {:?}", byte as char);
+            log::trace!("matched byte {:?}", byte as char);
             break 'reading;
         } else if byte_at_idx == b'\n' {
             err_ctx.line_count += 1;
@@ -311,8 +438,63 @@ fn parse_entities_save_leftovers(
     if size == 0 {
         return Ok((Box::new([]), leftover_bytes.into()));
     }
-    let _buf = read_section_and_alloc_with_leftovers(input, err_ctx, size, leftover_bytes)?;
-    todo!()
+    let (buf, ret_leftover) =
+        read_section_and_alloc_with_leftovers(input, err_ctx, size, leftover_bytes)?;
+    let text = str::from_utf8(&buf)
+        .map_err(|err| err_ctx.bad_input(format!("entities section is not valid utf8: {err}")))?;
+
+    let mut entities = vec![];
+    // the entity being read, if we're between its braces
+    let mut current: Option<EntityData> = None;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with("//") {
+            // nothing to read
+        } else if trimmed == "{" {
+            if current.is_some() {
+                return Err(err_ctx.bad_input("'{' inside an entity, missing a '}'?"));
+            }
+            current = Some(EntityData {
+                line: err_ctx.line_count,
+                properties: Properties::new(),
+            });
+        } else if trimmed == "}" {
+            let entity = current
+                .take()
+                .ok_or_else(|| err_ctx.bad_input("'}' without a matching '{'"))?;
+            entities.push(entity);
+        } else {
+            let entity = current
+                .as_mut()
+                .ok_or_else(|| err_ctx.bad_input("key and value outside of an entity"))?;
+            let (key, value) = parse_key_value(trimmed).ok_or_else(|| {
+                err_ctx.bad_input(format!(
+                    "expected \"key\" \"value\" in entity, found {trimmed:?}"
+                ))
+            })?;
+            entity.properties.insert(key, value);
+        }
+        if line.ends_with('\n') {
+            err_ctx.line_count += 1;
+        }
+    }
+    if let Some(entity) = current {
+        return Err(err_ctx.unexpected_eof(format!(
+            "entity starting on line {} is missing its '}}'",
+            entity.line
+        )));
+    }
+    Ok((entities.into_boxed_slice(), ret_leftover))
+}
+
+/// Split a `"key" "value"` line. Neither can hold a `"`.
+fn parse_key_value(line: &str) -> Option<(&str, &str)> {
+    let rest = line.strip_prefix('"')?;
+    let (key, rest) = rest.split_once('"')?;
+    let rest = rest.trim_start().strip_prefix('"')?;
+    let (value, rest) = rest.split_once('"')?;
+    let rest = rest.trim_start();
+    (rest.is_empty() || rest.starts_with("//")).then_some((key, value))
 }
 
 /// Returns data, new ptr index.
@@ -345,10 +527,7 @@ fn parse_brush_data(
                     } else {
                         off_by_one_was_prevented = true;
                     }
-                    #[cfg(feature = "parser_debug")]
-                    {
-                        dbg!(byte as char);
-                    }
+                    log::trace!(target: BYTES_TARGET, "next byte: {:?}", byte as char);
                     byte
                 },
                 None => {
@@ -364,28 +543,22 @@ fn parse_brush_data(
         let mut found_newline = false;
         'parsing_vertex: loop {
             if found_newline {
-                eprintln!("broke on newline in the last loop, breaking early");
+                log::trace!("broke on newline in the last loop, breaking early");
                 err_ctx.line_count += 1;
                 break 'parsing_vertex;
             }
             if is_comment(buf, ptr) {
                 // loop until newline
-                if cfg!(feature = "parser_debug") {
-                    eprintln!("'parsing_vertex: is_comment true, looping till newline");
-                }
+                log::trace!("'parsing_vertex: is_comment true, looping till newline");
                 while next!() != b'\n' {}
-                if cfg!(feature = "parser_debug") {
-                    eprintln!("matched, stopping and skipping");
-                }
+                log::trace!("matched, stopping and skipping");
                 err_ctx.line_count += 1;
                 // avoid reparsing if theres two comments in a row
                 continue 'parsing_vertex;
             }
             let mut nums = vec![];
             'nums_loop: for _i in 0..3 {
-                if cfg!(feature = "parser_debug") {
-                    eprintln!("'nums_loop: iteration {_i}");
-                }
+                log::trace!("'nums_loop: iteration {_i}");
                 match next!() {
                     b'+' => (),
                     b'-' => (),
@@ -419,17 +592,13 @@ fn parse_brush_data(
                 }
                 let num_str = str::from_utf8(&buf[old_ptr..ptr])
                     .expect("ascii digits should make valid utf8");
-                if cfg!(feature = "parser_debug") {
-                    dbg!(num_str);
-                }
+                log::trace!("num_str: {num_str:?}");
                 let num: f32 = num_str.parse().map_err(|err| {
                     err_ctx.bad_input(format!(
                         "failed parsing number: {err:?} (num_str: {num_str})"
                     ))
                 })?;
-                if cfg!(feature = "parser_debug") {
-                    dbg!(num);
-                }
+                log::trace!("num: {num}");
                 nums.push(num);
                 // BELOW UNNECESSARY: we skip the space naturally doing next!() on the next loop
                 // // skip over space
@@ -438,9 +607,7 @@ fn parse_brush_data(
             let vertex = Vertex {
                 pos: vec3(nums[0], nums[1], nums[2]),
             };
-            if cfg!(feature = "parser_debug") {
-                eprintln!("end of nums loop, vertex: {:?}", vertex);
-            }
+            log::trace!("end of nums loop, vertex: {:?}", vertex);
             vertices.push(vertex);
         }
         let plane = match vertices.len() {
@@ -489,19 +656,16 @@ fn parse_brushes_save_leftovers(
             Some(byte) => *byte,
             None => break 'parse,
         };
-        if cfg!(feature = "parser_debug") {
-            dbg!(byte as char);
-            dbg!(ptr);
-        }
+        log::trace!(target: BYTES_TARGET, "byte {:?} at {ptr}", byte as char);
         if byte == b'\n' {
-            eprintln!("reached newline");
+            log::trace!("reached newline");
             // we're at a new line, skip to parsable byte
             ptr += 1;
             err_ctx.line_count += 1;
             continue 'parse;
         }
         if is_comment(&buf, ptr) {
-            eprintln!("skipping comment");
+            log::trace!("skipping comment");
             read_until_after_newline(&buf, err_ctx, &mut ptr)?;
             continue 'parse;
         }
@@ -526,8 +690,8 @@ fn parse_brushes_save_leftovers(
 }
 
 fn is_comment(buf: &[u8], ptr: usize) -> bool {
-    // if we're running in debug mode, print extra information to aid debugging
-    if cfg!(feature = "parser_debug") {
+    // print extra information to aid debugging, but only build it if it'll be shown
+    if log::log_enabled!(target: BYTES_TARGET, log::Level::Trace) {
         let len = buf.len();
         let neighbourhood = &buf[ptr.saturating_sub(5)..ptr.saturating_add(6).clamp(0, len)];
 
@@ -541,20 +705,19 @@ fn is_comment(buf: &[u8], ptr: usize) -> bool {
         }
         let neighborhood_string = String::from_utf8_lossy(&sanitized_neighbors);
 
-        eprintln!("is_comment called: ptr: {ptr}");
-        eprintln!("buf.get(ptr): '{:?}'", buf.get(ptr).map(|x| *x as char));
-        eprintln!(
+        log::trace!(target: BYTES_TARGET, "is_comment called: ptr: {ptr}");
+        log::trace!(target: BYTES_TARGET, "buf.get(ptr): '{:?}'", buf.get(ptr).map(|x| *x as char));
+        log::trace!(
+            target: BYTES_TARGET,
             "buf.get(ptr+1): '{:?}",
             buf.get(ptr + 1).map(|x| *x as char)
         );
-        eprintln!("ptr neighborhood: \n'{neighborhood_string}'\n",);
+        log::trace!(target: BYTES_TARGET, "ptr neighborhood: \n'{neighborhood_string}'\n",);
     }
 
     let ret =
         buf.get(ptr).is_some_and(|b| *b == b'/') && buf.get(ptr + 1).is_some_and(|b| *b == b'/');
-    if cfg!(feature = "parser_debug") {
-        dbg!(ret);
-    }
+    log::trace!(target: BYTES_TARGET, "is_comment: {ret}");
     ret
 }
 
@@ -601,3 +764,205 @@ fn check_header_valid(input: &mut impl Read) -> Result<(), ParseError> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A map file with these sections.
+    fn map(entities: &str, brushes: &str) -> String {
+        format!(
+            "1113be_map\n.E {}\n{entities}.B {}\n{brushes}",
+            entities.len(),
+            brushes.len()
+        )
+    }
+
+    fn parse_entities(entities: &str) -> Result<MapData, ParseError> {
+        parse_map(map(entities, "").as_bytes())
+    }
+
+    #[test]
+    fn byte_trace_has_its_own_target() {
+        let filter: crate::logging::LogFilter =
+            "info,backshot_engine::map::parser=trace".parse().unwrap();
+        assert_eq!(filter.level_for(module_path!()), log::LevelFilter::Trace);
+        assert_eq!(filter.level_for(BYTES_TARGET), log::LevelFilter::Info);
+    }
+
+    #[test]
+    fn key_values() {
+        assert_eq!(
+            parse_key_value(r#""classname" "light""#),
+            Some(("classname", "light"))
+        );
+        assert_eq!(
+            parse_key_value(r#""origin"   "0 3 0"  // above"#),
+            Some(("origin", "0 3 0"))
+        );
+        assert_eq!(parse_key_value(r#""key""""#), Some(("key", "")));
+        assert_eq!(parse_key_value(r#""key""#), None);
+        assert_eq!(parse_key_value(r#"key "value""#), None);
+        assert_eq!(parse_key_value(r#""key" value"#), None);
+        assert_eq!(parse_key_value(r#""key" "value"#), None);
+        assert_eq!(parse_key_value(r#""key" "value" extra"#), None);
+    }
+
+    #[test]
+    fn entities() {
+        let data = parse_entities(
+            "// comment\n\
+             {\n\
+             \"classname\" \"light\"\n\
+             \"origin\" \"0 3 0\"\n\
+             }\n\
+             \n\
+             {\n\
+             \"classname\" \"logic_relay\"\n\
+             \"output\" \"OnTrigger -> lamp.TurnOff\"\n\
+             \"output\" \"OnTrigger -> lamp.TurnOn, delay 1\"\n\
+             }\n",
+        )
+        .unwrap();
+        let entities = data.entities();
+        assert_eq!(entities.len(), 2);
+        assert_eq!(entities[0].properties.classname(), Some("light"));
+        assert_eq!(entities[0].properties.get("origin"), Some("0 3 0"));
+        assert_eq!(entities[1].properties.iter().count(), 3);
+        // the header is line 1, the section header line 2
+        assert_eq!((entities[0].line, entities[1].line), (4, 9));
+        assert!(data.brushes().is_empty());
+    }
+
+    #[test]
+    fn empty_sections() {
+        let data = parse_entities("").unwrap();
+        assert!(data.entities().is_empty());
+        assert!(data.brushes().is_empty());
+    }
+
+    #[test]
+    fn entity_errors() {
+        let bad_input = |entities: &str| match parse_entities(entities) {
+            Err(ParseError::BadInput(line, message)) => (line, message),
+            Err(err) => panic!("expected bad input, got {err}"),
+            Ok(_) => panic!("expected bad input, but it parsed"),
+        };
+        assert_eq!(bad_input("{\n{\n").0, 4);
+        assert_eq!(bad_input("}\n").1, "'}' without a matching '{'");
+        assert_eq!(
+            bad_input("\"a\" \"b\"\n").1,
+            "key and value outside of an entity"
+        );
+        let (line, message) = bad_input("{\n\"classname\" \"light\"\norigin 0\n}\n");
+        assert_eq!(line, 5);
+        assert!(message.contains("expected \"key\" \"value\""));
+        assert!(matches!(
+            parse_entities("{\n\"classname\" \"light\"\n"),
+            Err(ParseError::UnexpectedEOF(_, message)) if message.contains("line 3")
+        ));
+    }
+
+    #[test]
+    fn bad_headers() {
+        assert!(matches!(
+            parse_map("1113be_mop\n".as_bytes()),
+            Err(ParseError::BadHeader(_))
+        ));
+        assert!(matches!(
+            parse_map("1113be_map\n.X 0\n".as_bytes()),
+            Err(ParseError::BadInput(_, _))
+        ));
+    }
+
+    /// Map data with `brush_count` triangles along X, and entities from `pairs`.
+    fn map_data(brush_count: usize, entities: &[&[(&str, &str)]]) -> MapData {
+        let brushes = (0..brush_count).map(|index| {
+            let x = index as f32 * 2.;
+            let verts = [vec3(x, 0., 0.), vec3(x + 1., 0., 0.), vec3(x, 1., 1.)];
+            BrushData {
+                planes: Box::new([PlaneData {
+                    verts: verts.map(|pos| Vertex { pos }).into(),
+                }]),
+            }
+        });
+        let entities = entities.iter().enumerate().map(|(line, pairs)| EntityData {
+            line,
+            properties: pairs.iter().copied().collect(),
+        });
+        MapData::new(entities.collect(), brushes.collect())
+    }
+
+    #[test]
+    fn claiming_brushes() {
+        let data = map_data(
+            4,
+            &[
+                &[("brushes", "2 0 2")],
+                &[],
+                &[("brushes", "0")],
+                &[("brushes", "1 4")],
+                &[("brushes", "one")],
+                &[("brushes", "1 3")],
+            ],
+        );
+        let mut claimed = vec![false; 4];
+        let mut claim = |index: usize| data.claim_brushes(&data.entities()[index], &mut claimed);
+        assert_eq!(claim(0), Ok(vec![0, 2]));
+        assert_eq!(claim(1), Ok(vec![]));
+        assert_eq!(
+            claim(2),
+            Err("brush 0 belongs to another entity".to_owned())
+        );
+        assert_eq!(
+            claim(3),
+            Err("brush 4 does not exist, the map has 4".to_owned())
+        );
+        assert!(claim(4).is_err());
+        // the failed claims didn't take brush 1
+        assert_eq!(claim(5), Ok(vec![1, 3]));
+        assert_eq!(claimed, [true; 4]);
+    }
+
+    #[test]
+    fn adding_to_a_world() {
+        let data = map_data(
+            4,
+            &[
+                &[("classname", "func_door"), ("brushes", "0")],
+                // spawning fails, so its brush is kept as static geometry
+                &[
+                    ("classname", "func_door"),
+                    ("brushes", "1"),
+                    ("speed", "-1"),
+                ],
+                // claiming fails, so brush 2 isn't taken
+                &[("classname", "func_door"), ("brushes", "0 2")],
+                &[("classname", "nope")],
+                &[("classname", "light"), ("origin", "0 0 0")],
+            ],
+        );
+        let mut world = World::new();
+        data.add_to_world(&mut world, &ClassRegistry::with_builtins());
+        assert_eq!(world.entities().len(), 2);
+        let static_xs: Vec<f32> = world
+            .brush_bounds()
+            .iter()
+            .map(|bounds| bounds.unwrap().min.x)
+            .collect();
+        assert_eq!(static_xs, [4., 6., 2.]);
+    }
+
+    #[test]
+    fn the_test_map_parses() {
+        let file = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/maps/test.map")).unwrap();
+        let data = parse_map(file.as_slice()).unwrap();
+        assert!(!data.entities().is_empty());
+        assert!(!data.brushes().is_empty());
+        let classes = ClassRegistry::with_builtins();
+        for entity in data.entities() {
+            let classname = entity.properties.classname().unwrap();
+            assert!(classes.get(classname).is_some(), "{classname}");
+        }
+    }
+}
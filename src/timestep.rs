@@ -0,0 +1,317 @@
+//! Exports [`FixedTimestep`], which runs the simulation at a fixed rate whatever the frame rate.
+//!
+//! Each frame's time, scaled by `host_timescale`, goes into an accumulator, and one tick of
+//! `1 / sv_tickrate` seconds is run for every whole tick in it. What's left over is
+//! [`FixedTimestep::alpha`], how far the frame is between the last tick and the next, for
+//! drawing things between where they were and where they are.
+//!
+//! After a long frame (a hitch, or a breakpoint) at most [`MAX_TICKS_PER_FRAME`] ticks are
+//! run and the rest of the time is dropped, so slow ticks can't snowball.
+//!
+//! `pause` stops ticking, and `step` runs one tick at a time while paused. Steps beyond
+//! [`MAX_TICKS_PER_FRAME`] are run over the next frames rather than dropped.
+use std::{cell::Cell, rc::Rc};
+
+use crate::console::{self, ConsoleError, Cvar};
+
+/// Ticks per second, unless `sv_tickrate` says otherwise.
+pub const DEFAULT_TICK_RATE: u32 = 64;
+
+/// Most ticks run in one frame. Time beyond that is dropped.
+pub const MAX_TICKS_PER_FRAME: u32 = 8;
+
+/// What the timestep did last frame, for the debug UI.
+#[derive(Clone, Debug, Default)]
+pub struct TimestepStats {
+    /// Ticks run since the start.
+    pub tick: u64,
+    /// Ticks per second.
+    pub tick_rate: u32,
+    /// Ticks run last frame.
+    pub ticks_last_frame: u32,
+    /// Ticks skipped since the start, because frames took too long.
+    pub dropped_ticks: u64,
+    /// How far the frame was between ticks, from 0 to 1.
+    pub alpha: f64,
+    /// How fast simulated time passes compared to real time.
+    pub time_scale: f64,
+    /// Whether ticking is paused.
+    pub paused: bool,
+}
+
+/// Decides how many fixed ticks to run each frame.
+pub struct FixedTimestep {
+    /// Ticks per second, from `sv_tickrate`.
+    tick_rate: u32,
+    /// From `host_timescale`.
+    time_scale: f64,
+    /// From `host_paused`.
+    paused: bool,
+    /// Simulated seconds not yet ticked.
+    accumulator: f64,
+    /// Ticks run since the start.
+    tick: u64,
+    /// Ticks still to run this frame.
+    pending: u32,
+    /// Ticks run this frame.
+    ticks_this_frame: u32,
+    /// Ticks skipped since the start.
+    dropped: u64,
+    /// Ticks asked for by `step`, run on the next frame while paused.
+    queued_steps: Rc<Cell<u32>>,
+}
+
+impl FixedTimestep {
+    /// Create the timestep, and register its cvars and commands.
+    pub fn register() -> Self {
+        let timestep = Self {
+            tick_rate: DEFAULT_TICK_RATE,
+            time_scale: 1.,
+            paused: false,
+            accumulator: 0.,
+            tick: 0,
+            pending: 0,
+            ticks_this_frame: 0,
+            dropped: 0,
+            queued_steps: Rc::new(Cell::new(0)),
+        };
+        timestep.register_console();
+        timestep
+    }
+
+    /// Register `sv_tickrate`, `host_timescale`, `host_paused`, `pause` and `step`.
+    fn register_console(&self) {
+        console::register_cvar(
+            Cvar::new(
+                "sv_tickrate",
+                i64::from(DEFAULT_TICK_RATE),
+                "simulation ticks per second",
+            )
+            .range(1., 1000.),
+        );
+        console::register_cvar(
+            Cvar::new(
+                "host_timescale",
+                1.,
+                "how fast simulated time passes, 1 for real time",
+            )
+            .range(0., 10.),
+        );
+        console::register_cvar(Cvar::new(
+            "host_paused",
+            false,
+            "stop ticking the simulation",
+        ));
+        console::register_command("pause", "pause or unpause the simulation", |_| {
+            let paused = !console::get::<bool>("host_paused").unwrap_or(false);
+            console::set("host_paused", paused)?;
+            console::info(if paused { "paused" } else { "unpaused" });
+            Ok(())
+        });
+        let queued_steps = self.queued_steps.clone();
+        console::register_command(
+            "step",
+            "run <count> ticks while paused, 1 by default",
+            move |args| {
+                let count = match args {
+                    [] => 1,
+                    [count] => count
+                        .parse::<u32>()
+                        .map_err(|_| ConsoleError::Usage("step [count]"))?,
+                    _ => return Err(ConsoleError::Usage("step [count]")),
+                };
+                if !console::get::<bool>("host_paused").unwrap_or(false) {
+                    return Err(ConsoleError::Failed(
+                        "not paused, use pause first".to_owned(),
+                    ));
+                }
+                queued_steps.set(queued_steps.get().saturating_add(count));
+                Ok(())
+            },
+        );
+    }
+
+    /// Start a frame that took `frame_time` real seconds, working out how many ticks to run.
+    /// Run them with [`next_tick`](Self::next_tick).
+    pub fn begin_frame(&mut self, frame_time: f64) {
+        self.tick_rate = console::get::<i64>("sv_tickrate")
+            .and_then(|rate| u32::try_from(rate).ok())
+            .filter(|rate| *rate > 0)
+            .unwrap_or(DEFAULT_TICK_RATE);
+        self.time_scale = console::get("host_timescale").unwrap_or(1.);
+        self.paused = console::get("host_paused").unwrap_or(false);
+
+        let ticks = if self.paused {
+            // time stands still, but `step` still runs ticks, the cap's worth each frame
+            let steps = self.queued_steps.get().min(MAX_TICKS_PER_FRAME);
+            self.queued_steps.set(self.queued_steps.get() - steps);
+            u64::from(steps)
+        } else {
+            self.queued_steps.set(0);
+            self.accumulator += frame_time * self.time_scale;
+            let ticks = (self.accumulator / self.dt()).floor();
+            self.accumulator -= ticks * self.dt();
+            ticks as u64
+        };
+        let capped = ticks.min(u64::from(MAX_TICKS_PER_FRAME));
+        if capped < ticks {
+            log::debug!("frame too long, dropping {} ticks", ticks - capped);
+            self.dropped += ticks - capped;
+        }
+        self.pending = capped as u32;
+        self.ticks_this_frame = 0;
+    }
+
+    /// Whether there's another tick to run this frame. Counts it as run if there is.
+    pub fn next_tick(&mut self) -> bool {
+        if self.pending == 0 {
+            return false;
+        }
+        self.pending -= 1;
+        self.tick += 1;
+        self.ticks_this_frame += 1;
+        true
+    }
+
+    /// Length of a tick in simulated seconds. The same for every tick, until `sv_tickrate` changes.
+    pub fn dt(&self) -> f64 {
+        1. / f64::from(self.tick_rate)
+    }
+    /// How far the frame is between the last tick and the next, from 0 to 1.
+    pub fn alpha(&self) -> f64 {
+        (self.accumulator / self.dt()).clamp(0., 1.)
+    }
+    /// Ticks run since the start.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+    /// Whether ticking is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+    /// How fast simulated time passes compared to real time.
+    pub fn time_scale(&self) -> f64 {
+        self.time_scale
+    }
+
+    /// What the timestep did this frame.
+    pub fn stats(&self) -> TimestepStats {
+        TimestepStats {
+            tick: self.tick,
+            tick_rate: self.tick_rate,
+            ticks_last_frame: self.ticks_this_frame,
+            dropped_ticks: self.dropped,
+            alpha: self.alpha(),
+            time_scale: self.time_scale,
+            paused: self.paused,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Length of a tick at the default rate. A power of two, so sums of it are exact.
+    const DT: f64 = 1. / DEFAULT_TICK_RATE as f64;
+
+    /// Run a frame, returning how many ticks it ran.
+    fn frame(timestep: &mut FixedTimestep, frame_time: f64) -> u32 {
+        timestep.begin_frame(frame_time);
+        let mut ticks = 0;
+        while timestep.next_tick() {
+            ticks += 1;
+        }
+        ticks
+    }
+
+    #[test]
+    fn accumulates_partial_ticks() {
+        let mut timestep = FixedTimestep::register();
+        assert_eq!(frame(&mut timestep, DT * 2.), 2);
+        assert_eq!(timestep.alpha(), 0.);
+
+        assert_eq!(frame(&mut timestep, DT * 1.5), 1);
+        assert_eq!(timestep.alpha(), 0.5);
+        assert_eq!(frame(&mut timestep, DT * 0.25), 0);
+        assert_eq!(timestep.alpha(), 0.75);
+        assert_eq!(frame(&mut timestep, DT * 0.25), 1);
+        assert_eq!(timestep.alpha(), 0.);
+
+        assert_eq!(timestep.tick(), 4);
+        assert_eq!(timestep.stats().ticks_last_frame, 1);
+    }
+
+    #[test]
+    fn long_frames_are_capped() {
+        let mut timestep = FixedTimestep::register();
+        assert_eq!(frame(&mut timestep, DT * 20.5), MAX_TICKS_PER_FRAME);
+        let stats = timestep.stats();
+        assert_eq!(stats.dropped_ticks, 20 - u64::from(MAX_TICKS_PER_FRAME));
+        // the dropped time is gone, not run later
+        assert_eq!(stats.alpha, 0.5);
+        assert_eq!(frame(&mut timestep, 0.), 0);
+    }
+
+    #[test]
+    fn time_scale_and_tick_rate() {
+        let mut timestep = FixedTimestep::register();
+        console::set("host_timescale", 0.5).unwrap();
+        assert_eq!(frame(&mut timestep, DT * 4.), 2);
+        console::set("host_timescale", 1.).unwrap();
+        console::set("sv_tickrate", 128_i64).unwrap();
+        assert_eq!(frame(&mut timestep, DT * 4.), 8);
+        assert_eq!(timestep.dt(), DT / 2.);
+    }
+
+    #[test]
+    fn paused_time_stands_still() {
+        let mut timestep = FixedTimestep::register();
+        frame(&mut timestep, DT * 0.5);
+        console::execute("pause").unwrap();
+        assert_eq!(frame(&mut timestep, DT * 10.), 0);
+        assert!(timestep.is_paused());
+        assert_eq!(timestep.alpha(), 0.5);
+        console::execute("pause").unwrap();
+        assert_eq!(frame(&mut timestep, DT * 0.5), 1);
+    }
+
+    #[test]
+    fn steps_run_while_paused() {
+        let mut timestep = FixedTimestep::register();
+        assert!(console::execute("step").is_err());
+        console::execute("pause; step; step 2").unwrap();
+        assert_eq!(frame(&mut timestep, DT * 10.), 3);
+        assert_eq!(frame(&mut timestep, DT * 10.), 0);
+        assert_eq!(
+            console::execute("step x"),
+            Err(ConsoleError::Usage("step [count]"))
+        );
+    }
+
+    #[test]
+    fn big_steps_are_spread_over_frames() {
+        let mut timestep = FixedTimestep::register();
+        console::execute("pause; step 100").unwrap();
+        let mut ticks = 0;
+        while ticks < 100 {
+            let ran = frame(&mut timestep, 0.);
+            assert!(ran > 0 && ran <= MAX_TICKS_PER_FRAME);
+            ticks += ran;
+        }
+        assert_eq!(ticks, 100);
+        assert_eq!(frame(&mut timestep, 0.), 0);
+        assert_eq!(timestep.stats().dropped_ticks, 0);
+    }
+
+    #[test]
+    fn unpausing_forgets_steps() {
+        let mut timestep = FixedTimestep::register();
+        console::execute("pause; step 20").unwrap();
+        frame(&mut timestep, 0.);
+        console::execute("pause").unwrap();
+        assert_eq!(frame(&mut timestep, 0.), 0);
+        console::execute("pause").unwrap();
+        assert_eq!(frame(&mut timestep, 0.), 0);
+    }
+}
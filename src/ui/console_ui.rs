@@ -0,0 +1,196 @@
+//! Exports [`ConsoleUi`].
+use render::imgui::{
+    self, HistoryDirection, InputTextCallback, InputTextCallbackHandler, TextCallbackData,
+};
+
+use crate::{
+    console::{self, OutputKind},
+    ui::Ui,
+};
+
+/// Fraction of the screen height the console covers.
+const HEIGHT_FRACTION: f32 = 0.4;
+
+/// Most lines kept in the input history.
+const HISTORY_LEN: usize = 100;
+
+/// Colour of each kind of output.
+fn output_colour(kind: OutputKind) -> [f32; 4] {
+    match kind {
+        OutputKind::Input => [0.6, 0.8, 1., 1.],
+        OutputKind::Info => [0.9, 0.9, 0.9, 1.],
+        OutputKind::Error => [1., 0.35, 0.35, 1.],
+    }
+}
+
+/// The drop-down developer console, running input through [`console`].
+pub struct ConsoleUi {
+    /// Whether the console is shown.
+    pub open: bool,
+    /// The line being typed.
+    input: String,
+    /// Lines that were run, oldest first.
+    history: Vec<String>,
+    /// Index into `history` of the line being shown, while browsing it.
+    history_pos: Option<usize>,
+    /// Give the input keyboard focus next frame.
+    focus_input: bool,
+    /// Number of output lines last frame, to scroll when new ones arrive.
+    output_len: usize,
+}
+
+impl ConsoleUi {
+    /// Create the UI, with the console closed.
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            input: String::new(),
+            history: vec![],
+            history_pos: None,
+            focus_input: false,
+            output_len: 0,
+        }
+    }
+
+    /// Open the console if it's closed, and close it if it's open.
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.focus_input = self.open;
+    }
+
+    /// Run the line in the input, and remember it.
+    fn submit(&mut self) {
+        let line = std::mem::take(&mut self.input);
+        let line = line.trim();
+        self.history_pos = None;
+        if line.is_empty() {
+            return;
+        }
+        if self.history.last().is_none_or(|last| last != line) {
+            if self.history.len() == HISTORY_LEN {
+                self.history.remove(0);
+            }
+            self.history.push(line.to_owned());
+        }
+        console::submit(line);
+    }
+}
+
+impl Ui for ConsoleUi {
+    fn update(&mut self, _: f64) {}
+    fn draw(&mut self, ui: &mut imgui::Ui) {
+        if !self.open {
+            return;
+        }
+        let [width, height] = ui.io().display_size;
+        ui.window("console")
+            .position([0., 0.], imgui::Condition::Always)
+            .size([width, height * HEIGHT_FRACTION], imgui::Condition::Always)
+            .title_bar(false)
+            .movable(false)
+            .resizable(false)
+            .collapsible(false)
+            .focused(self.focus_input)
+            .build(|| {
+                let footer = ui.frame_height_with_spacing();
+                ui.child_window("##output").size([0., -footer]).build(|| {
+                    console::with_output(|output| {
+                        for (kind, line) in output {
+                            ui.text_colored(output_colour(*kind), line);
+                        }
+                        // follow new output, like a terminal
+                        if output.len() != self.output_len {
+                            ui.set_scroll_here_y_with_ratio(1.);
+                        }
+                        self.output_len = output.len();
+                    });
+                });
+
+                ui.set_next_item_width(-1.);
+                if self.focus_input {
+                    ui.set_keyboard_focus_here();
+                    self.focus_input = false;
+                }
+                let callbacks = InputCallbacks {
+                    history: &self.history,
+                    history_pos: &mut self.history_pos,
+                };
+                let entered = ui
+                    .input_text("##input", &mut self.input)
+                    .hint("command")
+                    .enter_returns_true(true)
+                    .callback(
+                        InputTextCallback::COMPLETION | InputTextCallback::HISTORY,
+                        callbacks,
+                    )
+                    .build();
+                if entered {
+                    self.submit();
+                    // enter drops focus, take it back to keep typing
+                    self.focus_input = true;
+                }
+            });
+    }
+}
+
+/// Tab completion and history browsing for the console input.
+struct InputCallbacks<'a> {
+    /// Lines that were run, oldest first.
+    history: &'a [String],
+    /// Index into `history` of the line being shown.
+    history_pos: &'a mut Option<usize>,
+}
+
+impl InputTextCallbackHandler for InputCallbacks<'_> {
+    fn on_completion(&mut self, mut data: TextCallbackData) {
+        let text = data.str().to_owned();
+        // complete the name of the statement being typed at the cursor
+        let start = text[..data.cursor_pos()]
+            .rfind(';')
+            .map_or(0, |semicolon| semicolon + 1);
+        let statement = &text[start..data.cursor_pos()];
+        let word_start = start + (statement.len() - statement.trim_start().len());
+        let prefix = &text[word_start..data.cursor_pos()];
+        if prefix.contains(char::is_whitespace) {
+            // already past the name, there's nothing to complete
+            return;
+        }
+        let candidates = console::complete(prefix);
+        let completed = match candidates.as_slice() {
+            [] => return,
+            [only] => format!("{only} "),
+            [first, rest @ ..] => {
+                console::info(candidates.join("  "));
+                // extend to what every candidate has in common
+                let common = rest.iter().fold(first.len(), |len, candidate| {
+                    first
+                        .bytes()
+                        .zip(candidate.bytes())
+                        .take(len)
+                        .take_while(|(a, b)| a == b)
+                        .count()
+                });
+                first[..common].to_owned()
+            }
+        };
+        data.remove_chars(word_start, prefix.chars().count());
+        data.insert_chars(word_start, &completed);
+    }
+
+    fn on_history(&mut self, direction: HistoryDirection, mut data: TextCallbackData) {
+        if self.history.is_empty() {
+            return;
+        }
+        let newest = self.history.len() - 1;
+        *self.history_pos = match (direction, *self.history_pos) {
+            (HistoryDirection::Up, None) => Some(newest),
+            (HistoryDirection::Up, Some(pos)) => Some(pos.saturating_sub(1)),
+            (HistoryDirection::Down, Some(pos)) if pos < newest => Some(pos + 1),
+            (HistoryDirection::Down, _) => None,
+        };
+        data.clear();
+        if let Some(pos) = *self.history_pos {
+            data.push_str(&self.history[pos]);
+        }
+    }
+}
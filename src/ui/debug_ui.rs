@@ -1,14 +1,86 @@
 //! Exports [`DebugUi`].
-use std::time::Instant;
+use std::{collections::VecDeque, time::Instant};
 
-use crate::{SOFT_FPS_CAP, ui::Ui};
-use render::imgui;
+use crate::{
+    SOFT_FPS_CAP, console,
+    frame_pacing::{PacingMode, PacingStats},
+    gl_debug::{self, ErrorAction, Severity},
+    timestep::TimestepStats,
+    ui::Ui,
+};
+use render::{
+    CullStats, gpu_timing::PassTiming, imgui, render_mode::RenderMode, stats::RenderStats,
+};
+
+/// Number of frames kept for the frametime plot.
+const FRAMETIME_HISTORY_LEN: usize = 300;
+
+/// Summary of the frametimes in the plot, in milliseconds.
+struct FrametimeSummary {
+    /// Fastest frame.
+    min: f32,
+    /// Mean of every frame.
+    avg: f32,
+    /// Slowest frame.
+    max: f32,
+    /// Mean of the slowest 1% of frames.
+    one_percent_low: f32,
+}
+
+impl FrametimeSummary {
+    /// Summarize `frametimes`, or [`None`] if there are none.
+    fn new(frametimes: &[f32]) -> Option<Self> {
+        if frametimes.is_empty() {
+            return None;
+        }
+        let mut sorted = frametimes.to_vec();
+        sorted.sort_by(f32::total_cmp);
+        let slowest = &sorted[sorted.len() - sorted.len().div_ceil(100)..];
+        let mean = |values: &[f32]| values.iter().sum::<f32>() / values.len() as f32;
+        Some(Self {
+            min: sorted[0],
+            avg: mean(&sorted),
+            max: sorted[sorted.len() - 1],
+            one_percent_low: mean(slowest),
+        })
+    }
+}
 
 /// UI showing frametime.
 pub struct DebugUi {
     frametime_collector: Vec<f64>,
     last_debug_check: Instant,
     formatted_str: Option<String>,
+    /// How the world should be drawn.
+    pub render_mode: RenderMode,
+    /// Draw an arrow along the normal of every brush plane.
+    pub show_plane_normals: bool,
+    /// Draw the bounding box of every brush.
+    pub show_brush_bounds: bool,
+    /// Keep drawing the camera frustum from where it was when this was ticked.
+    pub freeze_frustum: bool,
+    /// Skip drawing brushes outside the camera's view.
+    pub frustum_culling: bool,
+    /// Show the CPU profiler window.
+    pub show_profiler: bool,
+    /// Show the log console window.
+    pub show_log: bool,
+    /// Show the entity event trace window.
+    pub show_event_trace: bool,
+    /// Brushes drawn and culled last frame.
+    cull_stats: CullStats,
+    /// What the renderer did last frame.
+    render_stats: RenderStats,
+    /// Latest GPU time of each render pass.
+    gpu_timings: Vec<PassTiming>,
+    /// The last [`FRAMETIME_HISTORY_LEN`] frametimes in milliseconds, oldest first.
+    frametime_history: VecDeque<f32>,
+    /// Text of the GL debug message id to mute.
+    mute_id_input: String,
+    /// How evenly recent frames were paced.
+    pacing_stats: PacingStats,
+    /// What the fixed timestep did last frame.
+    timestep_stats: TimestepStats,
 }
 
 impl Ui for DebugUi {
@@ -33,7 +105,7 @@ impl Ui for DebugUi {
                 1. / avg_time,
                 self.frametime_collector.len()
             );
-            eprintln!("{}", &formatted);
+            log::debug!("{formatted}");
 
             self.formatted_str = Some(formatted);
             self.frametime_collector.clear();
@@ -46,13 +118,79 @@ impl Ui for DebugUi {
             None => "no frametime data yet",
         };
         ui.window("debug frametime")
-            .size([400., 100.], imgui::Condition::Once)
+            .size([400., 320.], imgui::Condition::Once)
             .position([0., 0.], imgui::Condition::Once)
             .build(|| {
                 ui.bullet_text(render_str);
+                ui.bullet_text(format!(
+                    "brushes drawn: {}, culled: {}",
+                    self.cull_stats.drawn, self.cull_stats.culled
+                ));
                 ui.tree_node_config("Details").build(|| {
-                    ui.text("none yet");
-                })
+                    let stats = &self.render_stats;
+                    ui.text(format!("draw calls: {}", stats.draw_calls));
+                    ui.text(format!("triangles: {}", stats.triangles));
+                    ui.text(format!("vertices: {}", stats.vertices));
+                    ui.text(format!(
+                        "uploaded: {:.1} KiB",
+                        stats.bytes_uploaded as f64 / 1024.
+                    ));
+                    ui.text(format!("programs bound: {}", stats.programs_bound));
+                    ui.text(format!("textures bound: {}", stats.textures_bound));
+
+                    let gpu_total: f64 = self.gpu_timings.iter().filter_map(|pass| pass.ms).sum();
+                    ui.text(format!("GPU: {gpu_total:.3}ms"));
+                    for pass in self.gpu_timings.iter() {
+                        match pass.ms {
+                            Some(ms) => ui.bullet_text(format!("{}: {ms:.3}ms", pass.name)),
+                            None => ui.bullet_text(format!("{}: waiting", pass.name)),
+                        }
+                    }
+
+                    let history = self.frametime_history.make_contiguous();
+                    if let Some(summary) = FrametimeSummary::new(history) {
+                        ui.plot_lines("##frametime", history)
+                            .overlay_text("frametime (ms)")
+                            .scale_min(0.)
+                            .scale_max(summary.max.max(1.))
+                            .graph_size([0., 60.])
+                            .build();
+                        ui.text(format!(
+                            "min {:.2} / avg {:.2} / max {:.2} ms, 1% low: {:.2} ms ({:.0} FPS)",
+                            summary.min,
+                            summary.avg,
+                            summary.max,
+                            summary.one_percent_low,
+                            1000. / summary.one_percent_low
+                        ));
+                    }
+                });
+                ui.tree_node_config("Frame pacing")
+                    .build(|| draw_pacing(ui, &self.pacing_stats));
+                ui.tree_node_config("Simulation")
+                    .build(|| draw_timestep(ui, &self.timestep_stats));
+                let mut mode_index = RenderMode::ALL
+                    .iter()
+                    .position(|mode| *mode == self.render_mode)
+                    .unwrap_or_default();
+                if ui.combo("Render mode", &mut mode_index, &RenderMode::ALL, |mode| {
+                    mode.name().into()
+                }) {
+                    self.render_mode = RenderMode::ALL[mode_index];
+                }
+                ui.checkbox("Frustum culling", &mut self.frustum_culling);
+                ui.checkbox("CPU profiler", &mut self.show_profiler);
+                ui.same_line();
+                ui.checkbox("Log", &mut self.show_log);
+                ui.same_line();
+                ui.checkbox("Entity events", &mut self.show_event_trace);
+                ui.tree_node_config("Debug draw").build(|| {
+                    ui.checkbox("Plane normals", &mut self.show_plane_normals);
+                    ui.checkbox("Brush bounds", &mut self.show_brush_bounds);
+                    ui.checkbox("Freeze camera frustum", &mut self.freeze_frustum);
+                });
+                ui.tree_node_config("GL debug output")
+                    .build(|| draw_gl_debug_filter(ui, &mut self.mute_id_input));
             });
     }
 }
@@ -65,9 +203,221 @@ impl DebugUi {
             frametime_collector,
             last_debug_check: Instant::now(),
             formatted_str: None,
+            render_mode: RenderMode::default(),
+            show_plane_normals: false,
+            show_brush_bounds: false,
+            freeze_frustum: false,
+            frustum_culling: true,
+            show_profiler: false,
+            show_log: false,
+            show_event_trace: false,
+            cull_stats: CullStats::default(),
+            render_stats: RenderStats::default(),
+            gpu_timings: vec![],
+            frametime_history: VecDeque::with_capacity(FRAMETIME_HISTORY_LEN),
+            mute_id_input: String::new(),
+            pacing_stats: PacingStats::default(),
+            timestep_stats: TimestepStats::default(),
         }
     }
     pub fn push(&mut self, frametime: f64) {
-        self.frametime_collector.push(frametime)
+        self.frametime_collector.push(frametime);
+        if self.frametime_history.len() == FRAMETIME_HISTORY_LEN {
+            self.frametime_history.pop_front();
+        }
+        self.frametime_history.push_back((frametime * 1000.) as f32);
+    }
+    /// Show what the renderer did last frame.
+    pub fn set_render_stats(&mut self, stats: RenderStats) {
+        self.render_stats = stats;
+    }
+    /// Show the GPU time of each render pass.
+    pub fn set_gpu_timings(&mut self, timings: Vec<PassTiming>) {
+        self.gpu_timings = timings;
+    }
+    /// Show how evenly frames are being paced.
+    pub fn set_pacing_stats(&mut self, stats: PacingStats) {
+        self.pacing_stats = stats;
+    }
+    /// Show what the fixed timestep did last frame.
+    pub fn set_timestep_stats(&mut self, stats: TimestepStats) {
+        self.timestep_stats = stats;
+    }
+    /// Show how many brushes were drawn and culled last frame.
+    pub fn set_cull_stats(&mut self, stats: CullStats) {
+        self.cull_stats = stats;
+    }
+}
+
+/// Controls for which GL debug messages are logged, with `mute_id_input` holding the id being typed.
+fn draw_gl_debug_filter(ui: &imgui::Ui, mute_id_input: &mut String) {
+    let mut filter = gl_debug::filter();
+    let old_filter = filter.clone();
+
+    let mut severity_index = Severity::ALL
+        .iter()
+        .position(|severity| *severity == filter.min_severity)
+        .unwrap_or_default();
+    if ui.combo(
+        "Min severity",
+        &mut severity_index,
+        &Severity::ALL,
+        |severity| severity.name().into(),
+    ) {
+        filter.min_severity = Severity::ALL[severity_index];
+    }
+    let mut action_index = ErrorAction::ALL
+        .iter()
+        .position(|action| *action == filter.on_error)
+        .unwrap_or_default();
+    if ui.combo("On error", &mut action_index, &ErrorAction::ALL, |action| {
+        action.name().into()
+    }) {
+        filter.on_error = ErrorAction::ALL[action_index];
+    }
+
+    ui.set_next_item_width(120.);
+    let entered = ui
+        .input_text("##mute_id", mute_id_input)
+        .hint("message id")
+        .chars_decimal(true)
+        .enter_returns_true(true)
+        .build();
+    ui.same_line();
+    if (ui.button("Mute") || entered)
+        && let Ok(id) = mute_id_input.trim().parse()
+    {
+        filter.muted_ids.insert(id);
+        mute_id_input.clear();
+    }
+    let mut unmuted = None;
+    for id in &filter.muted_ids {
+        ui.bullet_text(id.to_string());
+        ui.same_line();
+        if ui.small_button(format!("unmute##{id}")) {
+            unmuted = Some(*id);
+        }
+    }
+    if let Some(id) = unmuted {
+        filter.muted_ids.remove(&id);
+    }
+
+    if filter != old_filter {
+        gl_debug::set_filter(filter);
+    }
+}
+
+/// Frame pacing controls and how even the pacing has been.
+fn draw_pacing(ui: &imgui::Ui, stats: &PacingStats) {
+    let mut mode_index = PacingMode::ALL
+        .iter()
+        .position(|mode| *mode == stats.mode)
+        .unwrap_or_default();
+    if ui.combo("Pacing", &mut mode_index, &PacingMode::ALL, |mode| {
+        mode.name().into()
+    }) && let Err(err) = console::set("vid_pacing", PacingMode::ALL[mode_index].name())
+    {
+        log::error!("{err}");
+    }
+    if stats.mode == PacingMode::Capped {
+        let mut fps_max = console::get::<i64>("fps_max").unwrap_or_default();
+        if ui.input_scalar("fps_max", &mut fps_max).step(10).build()
+            && let Err(err) = console::set("fps_max", fps_max.clamp(0, 1000))
+        {
+            log::error!("{err}");
+        }
+    }
+
+    match stats.target_ms {
+        Some(target) => ui.text(format!("target {target:.2}ms, mean {:.2}ms", stats.mean_ms)),
+        None => ui.text(format!("no target, mean {:.2}ms", stats.mean_ms)),
+    }
+    ui.text(format!(
+        "jitter {:.3}ms, worst {:.3}ms off",
+        stats.jitter_ms, stats.worst_ms
+    ));
+    let scale = stats.worst_ms.max(0.5) as f32;
+    ui.plot_lines("##pacing", &stats.errors_ms)
+        .overlay_text("frame time - target (ms)")
+        .scale_min(-scale)
+        .scale_max(scale)
+        .graph_size([0., 50.])
+        .build();
+}
+
+/// Pause, step and time scale controls, and what the fixed timestep is doing.
+fn draw_timestep(ui: &imgui::Ui, stats: &TimestepStats) {
+    ui.text(format!(
+        "tick {} at {} Hz, {} last frame",
+        stats.tick, stats.tick_rate, stats.ticks_last_frame
+    ));
+    ui.text(format!(
+        "alpha {:.2}, {} ticks dropped",
+        stats.alpha, stats.dropped_ticks
+    ));
+    let mut paused = stats.paused;
+    if ui.checkbox("Paused", &mut paused)
+        && let Err(err) = console::set("host_paused", paused)
+    {
+        log::error!("{err}");
+    }
+    if paused {
+        ui.same_line();
+        if ui.button("Step")
+            && let Err(err) = console::execute("step")
+        {
+            log::error!("{err}");
+        }
+    }
+    let mut time_scale = stats.time_scale as f32;
+    if ui.slider("Time scale", 0., 4., &mut time_scale)
+        && let Err(err) = console::set("host_timescale", f64::from(time_scale))
+    {
+        log::error!("{err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Summarize the frametimes 1 to `len`, shuffled so sorting matters.
+    fn summary(len: usize) -> FrametimeSummary {
+        let mut frametimes: Vec<f32> = (1..=len).map(|ms| ms as f32).collect();
+        frametimes.reverse();
+        frametimes.rotate_left(len / 3);
+        FrametimeSummary::new(&frametimes).unwrap()
+    }
+
+    #[test]
+    fn no_frames() {
+        assert!(FrametimeSummary::new(&[]).is_none());
+    }
+
+    #[test]
+    fn one_frame() {
+        let summary = summary(1);
+        assert_eq!(
+            (
+                summary.min,
+                summary.avg,
+                summary.max,
+                summary.one_percent_low
+            ),
+            (1., 1., 1., 1.)
+        );
+    }
+
+    #[test]
+    fn one_percent_lows() {
+        // 1% of 100 frames is just the slowest
+        let summary = self::summary(100);
+        assert_eq!((summary.min, summary.avg, summary.max), (1., 50.5, 100.));
+        assert_eq!(summary.one_percent_low, 100.);
+
+        // past 100 frames, rounds up to the slowest 2
+        let summary = self::summary(101);
+        assert_eq!((summary.min, summary.avg, summary.max), (1., 51., 101.));
+        assert_eq!(summary.one_percent_low, 100.5);
     }
 }
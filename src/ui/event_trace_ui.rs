@@ -0,0 +1,136 @@
+//! Exports [`EventTraceUi`].
+use render::imgui;
+use world::{
+    entity::EntityId,
+    io::{EventQueue, PendingEvent, TraceEntry},
+};
+
+use crate::ui::Ui;
+
+/// Colour of inputs that reached no entity, usually a typo in the map.
+const UNMATCHED_COLOUR: [f32; 4] = [1., 0.35, 0.35, 1.];
+/// Colour of inputs that reached at least one entity.
+const MATCHED_COLOUR: [f32; 4] = [0.9, 0.9, 0.9, 1.];
+
+/// UI showing inputs sent between entities, and the ones waiting on a delay.
+pub struct EventTraceUi {
+    /// Whether the window is shown.
+    pub open: bool,
+    /// The event queue's time when it was last looked at.
+    time: f64,
+    /// Inputs that were sent, oldest first.
+    trace: Vec<TraceEntry>,
+    /// Inputs waiting to be sent, soonest first.
+    pending: Vec<PendingEvent>,
+    /// Entries with a `seq` below this were cleared.
+    cleared_before: u64,
+    /// Only show inputs that reached no entity.
+    only_unmatched: bool,
+}
+
+impl EventTraceUi {
+    /// Create the UI, with its window closed.
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            time: 0.,
+            trace: vec![],
+            pending: vec![],
+            cleared_before: 0,
+            only_unmatched: false,
+        }
+    }
+    /// Show what's in `events`. Does nothing while the window is closed.
+    pub fn set_events(&mut self, events: &EventQueue) {
+        if !self.open {
+            return;
+        }
+        self.time = events.time();
+        self.trace.clear();
+        self.trace.extend(events.trace().iter().cloned());
+        self.pending.clear();
+        self.pending.extend_from_slice(events.pending());
+        self.pending.sort_by(|a, b| a.fire_at.total_cmp(&b.fire_at));
+    }
+}
+
+/// Where an input came from: the entity and its output, or the console.
+fn format_source(caller: Option<EntityId>, output: Option<&str>) -> String {
+    match (caller, output) {
+        (Some(caller), Some(output)) => format!("{caller} {output}"),
+        (Some(caller), None) => caller.to_string(),
+        _ => "(console)".to_owned(),
+    }
+}
+
+/// Format `entry` as a single line.
+fn format_entry(entry: &TraceEntry) -> String {
+    let mut line = format!(
+        "[{:>9.3}] {} -> {}.{}",
+        entry.time,
+        format_source(entry.caller, entry.output.as_deref()),
+        entry.target,
+        entry.input
+    );
+    if entry.delay > 0. {
+        line += &format!(", delay {}", entry.delay);
+    }
+    if entry.receivers == 0 {
+        line += " (no receivers)";
+    } else if entry.receivers > 1 {
+        line += &format!(" (x{})", entry.receivers);
+    }
+    line
+}
+
+impl Ui for EventTraceUi {
+    fn update(&mut self, _: f64) {}
+    fn draw(&mut self, ui: &mut imgui::Ui) {
+        if !self.open {
+            return;
+        }
+        let mut open = self.open;
+        ui.window("entity events")
+            .size([500., 300.], imgui::Condition::Once)
+            .position([410., 310.], imgui::Condition::Once)
+            .opened(&mut open)
+            .build(|| {
+                ui.text(format!("time: {:.3}s", self.time));
+                ui.same_line();
+                ui.checkbox("only unmatched", &mut self.only_unmatched);
+                ui.same_line();
+                if ui.button("clear") {
+                    self.cleared_before = self.trace.last().map_or(0, |entry| entry.seq + 1);
+                }
+
+                ui.tree_node_config(format!("pending ({})###pending", self.pending.len()))
+                    .build(|| {
+                        for event in &self.pending {
+                            ui.text(format!(
+                                "in {:.3}s: {} -> {}.{}",
+                                event.fire_at - self.time,
+                                format_source(event.caller, event.output.as_deref()),
+                                event.target,
+                                event.input
+                            ));
+                        }
+                    });
+
+                ui.child_window("##trace").build(|| {
+                    let shown = self.trace.iter().filter(|entry| {
+                        entry.seq >= self.cleared_before
+                            && (!self.only_unmatched || entry.receivers == 0)
+                    });
+                    for entry in shown {
+                        let colour = if entry.receivers == 0 {
+                            UNMATCHED_COLOUR
+                        } else {
+                            MATCHED_COLOUR
+                        };
+                        ui.text_colored(colour, format_entry(entry));
+                    }
+                });
+            });
+        self.open = open;
+    }
+}
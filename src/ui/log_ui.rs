@@ -0,0 +1,166 @@
+//! Exports [`LogUi`].
+use std::collections::BTreeSet;
+
+use log::Level;
+use render::imgui;
+
+use crate::{
+    logging::{self, LogEntry},
+    ui::Ui,
+};
+
+/// Levels in the order they're offered in the minimum level combo.
+const LEVELS: [Level; 5] = [
+    Level::Error,
+    Level::Warn,
+    Level::Info,
+    Level::Debug,
+    Level::Trace,
+];
+
+/// Colour of each level's records.
+fn level_colour(level: Level) -> [f32; 4] {
+    match level {
+        Level::Error => [1., 0.35, 0.35, 1.],
+        Level::Warn => [1., 0.8, 0.3, 1.],
+        Level::Info => [0.9, 0.9, 0.9, 1.],
+        Level::Debug => [0.5, 0.75, 1., 1.],
+        Level::Trace => [0.6, 0.6, 0.6, 1.],
+    }
+}
+
+/// UI showing recent log records.
+pub struct LogUi {
+    /// Whether the window is shown.
+    pub open: bool,
+    /// Least severe level shown.
+    min_level: Level,
+    /// Targets whose records are hidden.
+    hidden_targets: BTreeSet<String>,
+    /// Only records containing this are shown, if it isn't empty.
+    search: String,
+    /// Keep the newest record in view.
+    auto_scroll: bool,
+    /// Records with a `seq` below this were cleared.
+    cleared_before: u64,
+    /// `seq` of the newest record seen, to scroll when new ones arrive.
+    newest_seen: Option<u64>,
+}
+
+impl LogUi {
+    /// Create the UI, with its window closed.
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            min_level: Level::Info,
+            hidden_targets: BTreeSet::new(),
+            search: String::new(),
+            auto_scroll: true,
+            cleared_before: 0,
+            newest_seen: None,
+        }
+    }
+
+    /// Whether `entry` passes the filters.
+    fn shows(&self, entry: &LogEntry) -> bool {
+        entry.seq >= self.cleared_before
+            && entry.level <= self.min_level
+            && !self.hidden_targets.contains(&entry.target)
+            && (self.search.is_empty()
+                || entry.message.contains(&self.search)
+                || entry.target.contains(&self.search))
+    }
+}
+
+/// Format `entry` as a single line.
+fn format_entry(entry: &LogEntry) -> String {
+    format!(
+        "[{:>9.3} {:<5} {}] {}",
+        entry.time, entry.level, entry.target, entry.message
+    )
+}
+
+impl Ui for LogUi {
+    fn update(&mut self, _: f64) {}
+    fn draw(&mut self, ui: &mut imgui::Ui) {
+        if !self.open {
+            return;
+        }
+        let mut open = self.open;
+        ui.window("log")
+            .size([700., 300.], imgui::Condition::Once)
+            .position([410., 0.], imgui::Condition::Once)
+            .opened(&mut open)
+            .build(|| {
+                let mut level_index = LEVELS
+                    .iter()
+                    .position(|level| *level == self.min_level)
+                    .unwrap_or_default();
+                ui.set_next_item_width(90.);
+                if ui.combo("level", &mut level_index, &LEVELS, |level| {
+                    level.as_str().into()
+                }) {
+                    self.min_level = LEVELS[level_index];
+                }
+                ui.same_line();
+                ui.set_next_item_width(200.);
+                ui.input_text("##search", &mut self.search)
+                    .hint("search")
+                    .build();
+                ui.same_line();
+                ui.checkbox("auto-scroll", &mut self.auto_scroll);
+                ui.same_line();
+                let copy = ui.button("copy");
+                ui.same_line();
+                if ui.button("clear") {
+                    self.cleared_before = logging::with_recent(|entries| {
+                        entries.back().map_or(0, |entry| entry.seq + 1)
+                    });
+                }
+
+                // copy what's needed out, so nothing drawn below can log while the lock is held
+                let (targets, shown, newest) = logging::with_recent(|entries| {
+                    let targets: BTreeSet<String> =
+                        entries.iter().map(|entry| entry.target.clone()).collect();
+                    let shown: Vec<LogEntry> = entries
+                        .iter()
+                        .filter(|entry| self.shows(entry))
+                        .cloned()
+                        .collect();
+                    (targets, shown, entries.back().map(|entry| entry.seq))
+                });
+
+                ui.tree_node_config("targets").build(|| {
+                    for target in targets {
+                        let mut shown = !self.hidden_targets.contains(&target);
+                        if ui.checkbox(&target, &mut shown) {
+                            if shown {
+                                self.hidden_targets.remove(&target);
+                            } else {
+                                self.hidden_targets.insert(target);
+                            }
+                        }
+                    }
+                });
+
+                if copy {
+                    let text: Vec<String> = shown.iter().map(format_entry).collect();
+                    ui.set_clipboard_text(text.join("\n"));
+                }
+
+                let has_new = newest != self.newest_seen;
+                self.newest_seen = newest;
+                ui.child_window("##records").build(|| {
+                    // only follow new records if already at the bottom, so scrolling up to read works
+                    let at_bottom = ui.scroll_y() >= ui.scroll_max_y() - 1.;
+                    for entry in &shown {
+                        ui.text_colored(level_colour(entry.level), format_entry(entry));
+                    }
+                    if self.auto_scroll && has_new && at_bottom {
+                        ui.set_scroll_here_y_with_ratio(1.);
+                    }
+                });
+            });
+        self.open = open;
+    }
+}
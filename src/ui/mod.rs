@@ -1,8 +1,12 @@
 //! UI, UI elements and associated functions.
+pub mod console_ui;
 pub mod debug_ui;
+pub mod event_trace_ui;
+pub mod log_ui;
+pub mod profiler_ui;
 pub mod ui_manager;
 
-use crate::imgui;
+use render::imgui;
 
 /// A drawable UI element.
 pub trait Ui {
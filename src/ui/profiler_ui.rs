@@ -0,0 +1,157 @@
+//! Exports [`ProfilerUi`].
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+};
+
+use crate::ui::Ui;
+use render::{imgui, profiler};
+
+/// Where "Export" writes the Chrome trace.
+const TRACE_EXPORT_PATH: &str = "profile.json";
+
+/// Height of one row of the flame graph, in pixels.
+const ROW_HEIGHT: f32 = 18.;
+
+/// UI showing the CPU profiler's recent frames as a flame graph.
+pub struct ProfilerUi {
+    /// Whether the window is shown.
+    pub open: bool,
+    /// Index of the frame being inspected, in the profiler history.
+    /// [`None`] follows the newest frame.
+    selected: Option<usize>,
+    /// Result of the last export, shown under the button.
+    export_status: Option<String>,
+}
+
+impl Ui for ProfilerUi {
+    fn update(&mut self, _: f64) {}
+    fn draw(&mut self, ui: &mut imgui::Ui) {
+        if !self.open {
+            return;
+        }
+        let mut open = self.open;
+        ui.window("profiler")
+            .size([600., 320.], imgui::Condition::Once)
+            .position([0., 330.], imgui::Condition::Once)
+            .opened(&mut open)
+            .build(|| {
+                let mut paused = profiler::is_paused();
+                if ui.checkbox("Pause", &mut paused) {
+                    profiler::set_paused(paused);
+                    if !paused {
+                        self.selected = None;
+                    }
+                }
+                ui.same_line();
+                if ui.button("Export Chrome trace") {
+                    self.export_status = Some(export_trace());
+                }
+                if let Some(status) = &self.export_status {
+                    ui.same_line();
+                    ui.text(status);
+                }
+
+                profiler::with_history(|frames| {
+                    let Some(last) = frames.len().checked_sub(1) else {
+                        ui.text("no frames recorded yet");
+                        return;
+                    };
+                    let frame_ms: Vec<f32> = frames
+                        .iter()
+                        .map(|frame| frame.duration.as_secs_f32() * 1000.)
+                        .collect();
+                    ui.plot_histogram("##frames", &frame_ms)
+                        .overlay_text("frame time (ms)")
+                        .scale_min(0.)
+                        .graph_size([0., 50.])
+                        .build();
+
+                    let mut selected = self.selected.unwrap_or(last).min(last);
+                    if ui.slider("frame", 0, last, &mut selected) {
+                        // picking a frame only makes sense if it stays put
+                        profiler::set_paused(true);
+                        self.selected = Some(selected);
+                    }
+                    draw_flame_graph(ui, &frames[selected]);
+                });
+            });
+        self.open = open;
+    }
+}
+
+/// Draw the scopes of `frame` as stacked bars, with the frame filling the width.
+fn draw_flame_graph(ui: &imgui::Ui, frame: &profiler::FrameRecord) {
+    ui.text(format!(
+        "frame {}: {:.3}ms",
+        frame.index,
+        frame.duration.as_secs_f64() * 1000.
+    ));
+    let rows = frame
+        .scopes
+        .iter()
+        .map(|scope| scope.depth + 1)
+        .max()
+        .unwrap_or(1);
+    let [width, _] = ui.content_region_avail();
+    let height = rows as f32 * ROW_HEIGHT;
+    let origin = ui.cursor_screen_pos();
+    ui.dummy([width, height]);
+
+    let frame_secs = frame.duration.as_secs_f32().max(f32::EPSILON);
+    let draw_list = ui.get_window_draw_list();
+    for (i, scope) in frame.scopes.iter().enumerate() {
+        let offset = (scope.start.saturating_sub(frame.start)).as_secs_f32() / frame_secs;
+        let length = scope.duration.as_secs_f32() / frame_secs;
+        let min = [
+            origin[0] + offset * width,
+            origin[1] + scope.depth as f32 * ROW_HEIGHT,
+        ];
+        let max = [
+            (min[0] + length * width).max(min[0] + 1.),
+            min[1] + ROW_HEIGHT - 1.,
+        ];
+        // alternate colours so neighbouring scopes can be told apart
+        let colour = if i % 2 == 0 {
+            [0.85, 0.45, 0.2, 1.]
+        } else {
+            [0.9, 0.6, 0.25, 1.]
+        };
+        draw_list.add_rect(min, max, colour).filled(true).build();
+        draw_list.with_clip_rect_intersect(min, max, || {
+            draw_list.add_text([min[0] + 2., min[1] + 2.], [0., 0., 0., 1.], scope.name);
+        });
+        if ui.is_mouse_hovering_rect(min, max) {
+            ui.tooltip_text(format!(
+                "{}: {:.3}ms",
+                scope.name,
+                scope.duration.as_secs_f64() * 1000.
+            ));
+        }
+    }
+}
+
+/// Write every recorded frame to [`TRACE_EXPORT_PATH`], returning a status message.
+fn export_trace() -> String {
+    let result = File::create(TRACE_EXPORT_PATH).and_then(|file| {
+        let mut out = BufWriter::new(file);
+        profiler::with_history(|frames| profiler::write_chrome_trace(frames.iter(), &mut out))?;
+        // dropping the writer would flush it too, but throw away any error
+        out.flush()
+    });
+    match result {
+        Ok(()) => format!("wrote {TRACE_EXPORT_PATH}"),
+        Err(err) => format!("export failed: {err}"),
+    }
+}
+
+impl ProfilerUi {
+    /// Create the UI, with its window closed.
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            selected: None,
+            export_status: None,
+        }
+    }
+}
@@ -1,4 +1,7 @@
-use crate::ui::{Ui, debug_ui::DebugUi};
+use crate::ui::{
+    Ui, console_ui::ConsoleUi, debug_ui::DebugUi, event_trace_ui::EventTraceUi, log_ui::LogUi,
+    profiler_ui::ProfilerUi,
+};
 
 use render::imgui;
 
@@ -6,14 +9,38 @@ use render::imgui;
 /// to render in one call.
 pub struct UiManager {
     pub debug: DebugUi,
+    /// The CPU profiler window.
+    pub profiler: ProfilerUi,
+    /// The log console window.
+    pub log: LogUi,
+    /// The entity event trace window.
+    pub event_trace: EventTraceUi,
+    /// The drop-down developer console.
+    pub console: ConsoleUi,
 }
 
 impl Ui for UiManager {
     fn update(&mut self, delta_time: f64) {
         self.debug.update(delta_time);
+        self.profiler.update(delta_time);
+        self.log.update(delta_time);
+        self.event_trace.update(delta_time);
+        self.console.update(delta_time);
     }
     fn draw(&mut self, context: &mut imgui::Ui) {
         self.debug.draw(context);
+        // the checkbox in the debug window and the window's close button both toggle it
+        self.profiler.open = self.debug.show_profiler;
+        self.profiler.draw(context);
+        self.debug.show_profiler = self.profiler.open;
+        self.log.open = self.debug.show_log;
+        self.log.draw(context);
+        self.debug.show_log = self.log.open;
+        self.event_trace.open = self.debug.show_event_trace;
+        self.event_trace.draw(context);
+        self.debug.show_event_trace = self.event_trace.open;
+        // drawn last so it's on top
+        self.console.draw(context);
     }
 }
 
@@ -21,6 +48,10 @@ impl UiManager {
     pub fn new() -> Self {
         Self {
             debug: DebugUi::new(),
+            profiler: ProfilerUi::new(),
+            log: LogUi::new(),
+            event_trace: EventTraceUi::new(),
+            console: ConsoleUi::new(),
         }
     }
 }
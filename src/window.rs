@@ -0,0 +1,329 @@
+//! Exports [`WindowManager`], which keeps the main window the way the `vid_*` cvars say.
+//!
+//! - `vid_mode`: `windowed`, `borderless` (a window covering the whole display) or
+//!   `exclusive` (the display switches to `vid_width` by `vid_height`).
+//! - `vid_display`: which display to use, counting from 0.
+//! - `vid_width`, `vid_height`: size of the window, or the resolution when exclusive.
+//!   Resizing the window by hand writes them back.
+//!
+//! Changes are applied between frames. Sizes in the cvars are in points, which on HiDPI
+//! displays are smaller than pixels, so anything GL should use [`WindowManager::drawable_size`].
+use std::fmt::{self, Display};
+
+use sdl2::{
+    VideoSubsystem,
+    pixels::PixelFormatEnum,
+    video::{DisplayMode, FullscreenType, GLContext, Window, WindowPos},
+};
+
+use crate::{
+    config::{DEFAULT_HEIGHT, DEFAULT_WIDTH},
+    console,
+};
+
+/// Title of the main window.
+pub const WINDOW_TITLE: &str = "backshot engine";
+
+/// Smallest size the window can be made, in points.
+pub const MIN_WINDOW_SIZE: (u32, u32) = (320, 240);
+
+/// How the main window covers the screen.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WindowMode {
+    /// A normal, resizable window.
+    #[default]
+    Windowed,
+    /// A borderless window the size of the display, at the display's resolution.
+    Borderless,
+    /// Fullscreen, with the display switched to the window's resolution.
+    Exclusive,
+}
+
+impl WindowMode {
+    /// Every mode.
+    pub const ALL: [WindowMode; 3] = [
+        WindowMode::Windowed,
+        WindowMode::Borderless,
+        WindowMode::Exclusive,
+    ];
+    /// The name of every mode, as the `vid_mode` cvar takes them.
+    pub const NAMES: [&'static str; 3] = ["windowed", "borderless", "exclusive"];
+
+    /// The name used by the `vid_mode` cvar.
+    pub fn name(self) -> &'static str {
+        match self {
+            WindowMode::Windowed => "windowed",
+            WindowMode::Borderless => "borderless",
+            WindowMode::Exclusive => "exclusive",
+        }
+    }
+    /// The mode called `name`, if there is one.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|mode| mode.name() == name)
+    }
+}
+
+impl Display for WindowMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// What the `vid_*` cvars ask the window to be.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WindowSettings {
+    /// From `vid_mode`.
+    pub mode: WindowMode,
+    /// From `vid_display`.
+    pub display: i32,
+    /// From `vid_width`, in points.
+    pub width: u32,
+    /// From `vid_height`, in points.
+    pub height: u32,
+}
+
+impl WindowSettings {
+    /// Read the settings from the cvars, using defaults for any that aren't registered.
+    pub fn from_cvars() -> Self {
+        let size = |name, default| {
+            console::get::<i64>(name)
+                .and_then(|size| u32::try_from(size).ok())
+                .unwrap_or(default)
+        };
+        Self {
+            mode: console::get::<String>("vid_mode")
+                .and_then(|mode| WindowMode::from_name(&mode))
+                .unwrap_or_default(),
+            display: console::get::<i64>("vid_display")
+                .and_then(|display| i32::try_from(display).ok())
+                .unwrap_or(0),
+            width: size("vid_width", DEFAULT_WIDTH),
+            height: size("vid_height", DEFAULT_HEIGHT),
+        }
+    }
+}
+
+/// Creates the main window, and changes it when the `vid_*` cvars do.
+pub struct WindowManager {
+    /// Settings the window was last changed to.
+    applied: WindowSettings,
+    /// Size of the window's framebuffer, in pixels, when it was last checked.
+    drawable_size: (u32, u32),
+}
+
+impl WindowManager {
+    /// Create the main window from the cvars, with its OpenGL context.
+    ///
+    /// # Errors
+    /// Errors if SDL can't create the window or context.
+    pub fn create(video_ctx: &VideoSubsystem) -> Result<(Self, Window, GLContext), String> {
+        let settings = WindowSettings::from_cvars();
+        let mut window = video_ctx
+            .window(WINDOW_TITLE, settings.width, settings.height)
+            .position_centered()
+            .opengl()
+            .resizable()
+            .allow_highdpi()
+            .build()
+            .map_err(|err| format!("creating window: {err}"))?;
+        window
+            .set_minimum_size(MIN_WINDOW_SIZE.0, MIN_WINDOW_SIZE.1)
+            .map_err(|err| format!("setting minimum window size: {err}"))?;
+        let gl_ctx = window.gl_create_context()?;
+
+        let mut manager = Self {
+            applied: settings,
+            drawable_size: window.drawable_size(),
+        };
+        manager.apply(&mut window, video_ctx, settings);
+        Ok((manager, window, gl_ctx))
+    }
+
+    /// Size of the window's framebuffer, in pixels, when it was last checked.
+    pub fn drawable_size(&self) -> (u32, u32) {
+        self.drawable_size
+    }
+
+    /// The settings the window was last changed to.
+    pub fn settings(&self) -> WindowSettings {
+        self.applied
+    }
+
+    /// Apply any changes to the `vid_*` cvars, then check the framebuffer size.
+    /// Returns the new size in pixels, if it changed since the last call.
+    pub fn update(
+        &mut self,
+        window: &mut Window,
+        video_ctx: &VideoSubsystem,
+    ) -> Option<(u32, u32)> {
+        let settings = WindowSettings::from_cvars();
+        if settings != self.applied {
+            self.apply(window, video_ctx, settings);
+        }
+        let drawable_size = window.drawable_size();
+        if drawable_size == self.drawable_size {
+            return None;
+        }
+        self.drawable_size = drawable_size;
+        Some(drawable_size)
+    }
+
+    /// The window was resized from outside, to `width` by `height` points.
+    /// Keeps the size for next time, if it's a normal window.
+    pub fn resized(&mut self, width: i32, height: i32) {
+        if self.applied.mode != WindowMode::Windowed {
+            return;
+        }
+        let (Ok(width), Ok(height)) = (u32::try_from(width), u32::try_from(height)) else {
+            return;
+        };
+        for (name, size) in [("vid_width", width), ("vid_height", height)] {
+            if let Err(err) = console::set(name, i64::from(size)) {
+                log::warn!("couldn't keep window size: {err}");
+                return;
+            }
+        }
+        // it's already this size, so there's nothing to apply
+        self.applied.width = width;
+        self.applied.height = height;
+    }
+
+    /// Change the window to match `settings`. Logs anything that goes wrong, and carries on.
+    fn apply(&mut self, window: &mut Window, video_ctx: &VideoSubsystem, settings: WindowSettings) {
+        let displays = video_ctx.num_video_displays().unwrap_or(1);
+        let display = if (0..displays).contains(&settings.display) {
+            settings.display
+        } else {
+            log::warn!(
+                "there's no display {}, using display 0 of {displays}",
+                settings.display
+            );
+            0
+        };
+        let result = match settings.mode {
+            WindowMode::Windowed => make_windowed(window, video_ctx, display, &settings),
+            WindowMode::Borderless => make_borderless(window, video_ctx, display),
+            WindowMode::Exclusive => make_exclusive(window, video_ctx, display, &settings),
+        };
+        match result {
+            Ok(()) => log::info!(
+                "window is {} on display {display}, {}x{} points, {}x{} pixels",
+                settings.mode,
+                window.size().0,
+                window.size().1,
+                window.drawable_size().0,
+                window.drawable_size().1,
+            ),
+            Err(err) => log::error!("couldn't make the window {}: {err}", settings.mode),
+        }
+        // only try once, even if it failed
+        self.applied = settings;
+    }
+}
+
+/// Make `window` a normal window, centred on `display`.
+fn make_windowed(
+    window: &mut Window,
+    video_ctx: &VideoSubsystem,
+    display: i32,
+    settings: &WindowSettings,
+) -> Result<(), String> {
+    window.set_fullscreen(FullscreenType::Off)?;
+    window.set_bordered(true);
+    window
+        .set_size(settings.width, settings.height)
+        .map_err(|err| err.to_string())?;
+    centre_on(window, video_ctx, display)
+}
+
+/// Make `window` cover `display`, without changing its resolution.
+fn make_borderless(
+    window: &mut Window,
+    video_ctx: &VideoSubsystem,
+    display: i32,
+) -> Result<(), String> {
+    // SDL fills whichever display the window is on, so move it there first
+    window.set_fullscreen(FullscreenType::Off)?;
+    centre_on(window, video_ctx, display)?;
+    window.set_fullscreen(FullscreenType::Desktop)
+}
+
+/// Make `window` fullscreen on `display`, switching it to the closest resolution it has.
+fn make_exclusive(
+    window: &mut Window,
+    video_ctx: &VideoSubsystem,
+    display: i32,
+    settings: &WindowSettings,
+) -> Result<(), String> {
+    let wanted = DisplayMode::new(
+        PixelFormatEnum::Unknown,
+        settings.width as i32,
+        settings.height as i32,
+        0,
+    );
+    let mode = video_ctx.closest_display_mode(display, &wanted)?;
+    if (mode.w, mode.h) != (wanted.w, wanted.h) {
+        log::warn!(
+            "display {display} can't do {}x{}, using {}x{}",
+            wanted.w,
+            wanted.h,
+            mode.w,
+            mode.h
+        );
+    }
+    window.set_fullscreen(FullscreenType::Off)?;
+    centre_on(window, video_ctx, display)?;
+    window.set_display_mode(mode)?;
+    window.set_fullscreen(FullscreenType::True)
+}
+
+/// Move `window` to the middle of `display`.
+fn centre_on(window: &mut Window, video_ctx: &VideoSubsystem, display: i32) -> Result<(), String> {
+    let bounds = video_ctx.display_bounds(display)?;
+    let (width, height) = window.size();
+    let x = bounds.x() + (bounds.width() as i32 - width as i32) / 2;
+    let y = bounds.y() + (bounds.height() as i32 - height as i32) / 2;
+    window.set_position(WindowPos::Positioned(x), WindowPos::Positioned(y));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config;
+
+    #[test]
+    fn mode_names() {
+        for (mode, name) in WindowMode::ALL.into_iter().zip(WindowMode::NAMES) {
+            assert_eq!(mode.name(), name);
+            assert_eq!(WindowMode::from_name(name), Some(mode));
+            assert_eq!(mode.to_string(), name);
+        }
+        assert_eq!(WindowMode::from_name("fullscreen"), None);
+        assert_eq!(WindowMode::from_name("Windowed"), None);
+    }
+
+    #[test]
+    fn settings_default_without_cvars() {
+        assert_eq!(
+            WindowSettings::from_cvars(),
+            WindowSettings {
+                mode: WindowMode::Windowed,
+                display: 0,
+                width: DEFAULT_WIDTH,
+                height: DEFAULT_HEIGHT,
+            }
+        );
+    }
+
+    #[test]
+    fn settings_from_cvars() {
+        config::register_cvars();
+        console::execute("vid_mode borderless; vid_display 1; vid_width 1024").unwrap();
+        let settings = WindowSettings::from_cvars();
+        assert_eq!(settings.mode, WindowMode::Borderless);
+        assert_eq!(settings.display, 1);
+        assert_eq!(settings.width, 1024);
+        assert_eq!(settings.height, DEFAULT_HEIGHT);
+    }
+}
@@ -0,0 +1,156 @@
+//! Compares moving entities through [`World`]'s component storage against the naive
+//! alternative, a `Vec<Box<dyn Entity>>` where each entity moves itself.
+//!
+//! Run with `cargo bench`. Only some entities move, like in a real map, so the naive
+//! version also pays for visiting the ones that don't.
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use glm::Vec3;
+use world::{
+    World,
+    component::{Health, Light, Transform, Velocity},
+    systems,
+};
+
+/// Ticks timed for each run.
+const TICKS: u32 = 200;
+/// Seconds per tick.
+const DT: f64 = 1. / 64.;
+/// One in this many entities moves.
+const MOVING_EVERY: usize = 4;
+
+/// The naive model: every entity is a boxed trait object that updates itself.
+trait Entity {
+    /// Advance by `dt` seconds.
+    fn tick(&mut self, dt: f32);
+}
+
+/// An entity that moves.
+struct Mover {
+    /// Where it is.
+    position: Vec3,
+    /// How fast it's going.
+    velocity: Vec3,
+    /// Carried along, like a real entity would.
+    _health: Health,
+}
+
+impl Entity for Mover {
+    fn tick(&mut self, dt: f32) {
+        self.position = self.position + self.velocity * dt;
+    }
+}
+
+/// An entity that doesn't do anything.
+struct Lamp {
+    /// Where it is.
+    _position: Vec3,
+    /// Its light.
+    _light: Light,
+}
+
+impl Entity for Lamp {
+    fn tick(&mut self, _: f32) {}
+}
+
+/// A light to give entities that don't move.
+fn lamp_light() -> Light {
+    Light {
+        colour: glm::to_vec3(1.),
+        intensity: 1.,
+        radius: 10.,
+        enabled: true,
+    }
+}
+
+/// `count` naive entities.
+fn naive(count: usize) -> Vec<Box<dyn Entity>> {
+    (0..count)
+        .map(|index| -> Box<dyn Entity> {
+            let position = glm::vec3(index as f32, 0., 0.);
+            if index % MOVING_EVERY == 0 {
+                Box::new(Mover {
+                    position,
+                    velocity: glm::vec3(0., 1., 0.),
+                    _health: Health::new(100.),
+                })
+            } else {
+                Box::new(Lamp {
+                    _position: position,
+                    _light: lamp_light(),
+                })
+            }
+        })
+        .collect()
+}
+
+/// A world with `count` entities, made of components.
+fn ecs(count: usize) -> World {
+    let mut world = World::new();
+    for index in 0..count {
+        let id = world.spawn();
+        let entities = world.entities_mut();
+        entities.insert(
+            id,
+            Transform::from_position(glm::vec3(index as f32, 0., 0.)),
+        );
+        if index % MOVING_EVERY == 0 {
+            entities.insert(
+                id,
+                Velocity {
+                    linear: glm::vec3(0., 1., 0.),
+                    ..Velocity::default()
+                },
+            );
+            entities.insert(id, Health::new(100.));
+        } else {
+            entities.insert(id, lamp_light());
+        }
+    }
+    world
+}
+
+/// Time `tick` over [`TICKS`] ticks, returning the mean time per tick.
+fn time(mut tick: impl FnMut()) -> Duration {
+    // warm up caches and the branch predictor first
+    for _ in 0..TICKS / 10 {
+        tick();
+    }
+    let start = Instant::now();
+    for _ in 0..TICKS {
+        tick();
+    }
+    start.elapsed() / TICKS
+}
+
+fn main() {
+    println!(
+        "{:>8}  {:>12}  {:>12}  {:>7}",
+        "entities", "naive", "ecs", "speedup"
+    );
+    for count in [1_000, 10_000, 100_000] {
+        let mut entities = naive(count);
+        let naive_time = time(|| {
+            for entity in &mut entities {
+                entity.tick(DT as f32);
+            }
+            black_box(&mut entities);
+        });
+
+        let mut world = ecs(count);
+        let ecs_time = time(|| {
+            systems::integrate_velocity(world.entities_mut().components_mut(), DT);
+            black_box(&mut world);
+        });
+
+        println!(
+            "{count:>8}  {:>10.1}us  {:>10.1}us  {:>6.2}x",
+            naive_time.as_secs_f64() * 1e6,
+            ecs_time.as_secs_f64() * 1e6,
+            naive_time.as_secs_f64() / ecs_time.as_secs_f64()
+        );
+    }
+}
@@ -0,0 +1,164 @@
+//! Exports [`Aabb`].
+use glm::Vec3;
+
+/// An axis-aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    /// The corner with the smallest coordinates.
+    pub min: Vec3,
+    /// The corner with the largest coordinates.
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Create a box from two opposite corners, in any order.
+    pub fn new(a: Vec3, b: Vec3) -> Self {
+        Self {
+            min: glm::min(a, b),
+            max: glm::max(a, b),
+        }
+    }
+    /// The smallest box containing every point, or [`None`] if there are none.
+    pub fn from_points(points: impl IntoIterator<Item = Vec3>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        Some(points.fold(Self::new(first, first), |aabb, point| aabb.grow(point)))
+    }
+    /// This box, grown to contain `point`.
+    pub fn grow(self, point: Vec3) -> Self {
+        Self {
+            min: glm::min(self.min, point),
+            max: glm::max(self.max, point),
+        }
+    }
+    /// The smallest box containing both boxes.
+    pub fn union(self, other: Aabb) -> Self {
+        Self {
+            min: glm::min(self.min, other.min),
+            max: glm::max(self.max, other.max),
+        }
+    }
+    /// The point in the middle of the box.
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+    /// Half the size of the box along each axis.
+    pub fn half_extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+    /// This box, moved by `offset`.
+    pub fn translate(self, offset: Vec3) -> Self {
+        Self {
+            min: self.min + offset,
+            max: self.max + offset,
+        }
+    }
+    /// The eight corners of the box.
+    pub fn corners(&self) -> [Vec3; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            glm::vec3(min.x, min.y, min.z),
+            glm::vec3(max.x, min.y, min.z),
+            glm::vec3(min.x, max.y, min.z),
+            glm::vec3(max.x, max.y, min.z),
+            glm::vec3(min.x, min.y, max.z),
+            glm::vec3(max.x, min.y, max.z),
+            glm::vec3(min.x, max.y, max.z),
+            glm::vec3(max.x, max.y, max.z),
+        ]
+    }
+    /// Whether the boxes overlap by more than touching. A flat box overlaps boxes that
+    /// cross it.
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.x < other.max.x
+            && other.min.x < self.max.x
+            && self.min.y < other.max.y
+            && other.min.y < self.max.y
+            && self.min.z < other.max.z
+            && other.min.z < self.max.z
+    }
+    /// Whether `point` is inside or on the edge of the box.
+    pub fn contains(&self, point: Vec3) -> bool {
+        point.x >= self.min.x
+            && point.y >= self.min.y
+            && point.z >= self.min.z
+            && point.x <= self.max.x
+            && point.y <= self.max.y
+            && point.z <= self.max.z
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unit_at(x: f32, y: f32, z: f32) -> Aabb {
+        Aabb::new(glm::vec3(x, y, z), glm::vec3(x + 1., y + 1., z + 1.))
+    }
+
+    #[test]
+    fn corners_in_any_order() {
+        let aabb = Aabb::new(glm::vec3(1., -2., 3.), glm::vec3(-1., 2., 0.));
+        assert_eq!(aabb.min, glm::vec3(-1., -2., 0.));
+        assert_eq!(aabb.max, glm::vec3(1., 2., 3.));
+        assert_eq!(aabb.center(), glm::vec3(0., 0., 1.5));
+        assert_eq!(aabb.half_extents(), glm::vec3(1., 2., 1.5));
+    }
+
+    #[test]
+    fn eight_corners() {
+        let aabb = Aabb::new(glm::vec3(0., 0., 0.), glm::vec3(1., 2., 3.));
+        let corners = aabb.corners();
+        for (index, a) in corners.iter().enumerate() {
+            assert!(aabb.contains(*a));
+            assert!(corners[index + 1..].iter().all(|b| a != b));
+        }
+        assert_eq!(Aabb::from_points(corners), Some(aabb));
+        assert_eq!(corners[0], aabb.min);
+        assert_eq!(corners[7], aabb.max);
+    }
+
+    #[test]
+    fn overlapping() {
+        let a = unit_at(0., 0., 0.);
+        assert!(a.overlaps(&a));
+        assert!(a.overlaps(&unit_at(0.5, 0.5, 0.5)));
+        assert!(unit_at(0.5, 0.5, 0.5).overlaps(&a));
+        // touching faces, edges and corners don't count
+        assert!(!a.overlaps(&unit_at(1., 0., 0.)));
+        assert!(!a.overlaps(&unit_at(1., 1., 0.)));
+        assert!(!a.overlaps(&unit_at(-1., -1., -1.)));
+        // apart on one axis is enough
+        assert!(!a.overlaps(&unit_at(0., 0., 2.)));
+        // a box inside another
+        let inner = Aabb::new(glm::to_vec3(0.25), glm::to_vec3(0.75));
+        assert!(a.overlaps(&inner));
+        assert!(inner.overlaps(&a));
+    }
+
+    #[test]
+    fn flat_boxes_overlap_what_crosses_them() {
+        let floor = Aabb::new(glm::vec3(0., 0., 0.), glm::vec3(2., 0., 2.));
+        assert!(!floor.overlaps(&unit_at(0., 0., 0.)));
+        assert!(floor.overlaps(&unit_at(0., -0.5, 0.)));
+        assert!(!floor.overlaps(&floor));
+    }
+
+    #[test]
+    fn growing() {
+        assert_eq!(Aabb::from_points([]), None);
+        let point = Aabb::from_points([glm::to_vec3(1.)]).unwrap();
+        assert_eq!(point.min, point.max);
+        let grown = point.grow(glm::vec3(-1., 2., 1.));
+        assert_eq!(grown, Aabb::new(glm::vec3(-1., 1., 1.), glm::vec3(1., 2., 1.)));
+        assert_eq!(
+            unit_at(0., 0., 0.).union(unit_at(2., 0., 0.)),
+            Aabb::new(glm::to_vec3(0.), glm::vec3(3., 1., 1.))
+        );
+        assert_eq!(
+            unit_at(0., 0., 0.).translate(glm::vec3(0., 2., 0.)),
+            unit_at(0., 2., 0.)
+        );
+        assert!(!unit_at(0., 0., 0.).contains(glm::vec3(0.5, 1.5, 0.5)));
+    }
+}
@@ -1,6 +1,8 @@
 use std::ops::Index;
 
-use crate::vertex::Vertex;
+use glm::Vec3;
+
+use crate::{bounds::Aabb, vertex::Vertex};
 
 use paste::paste;
 
@@ -19,6 +21,49 @@ pub enum BrushPlane {
     /// Plane must be convex.
     NGon(NGonPlane),
 }
+impl BrushPlane {
+    /// All vertices of this plane, in winding order.
+    pub fn vertices(&self) -> &[Vertex] {
+        match self {
+            Self::Triangle(plane) => &plane.0,
+            Self::NGon(plane) => &plane.0,
+        }
+    }
+    /// The average of all vertices.
+    pub fn centroid(&self) -> Vec3 {
+        let verts = self.vertices();
+        let sum = verts
+            .iter()
+            .fold(glm::to_vec3(0.), |acc, vert| acc + vert.pos);
+        sum / verts.len() as f32
+    }
+    /// The bounding box of all vertices.
+    pub fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(self.vertices().iter().map(|vert| vert.pos))
+    }
+    /// The normalized normal of this plane, following the winding order.
+    ///
+    /// Returns [`None`] for degenerate planes, where all vertices are collinear.
+    pub fn normal(&self) -> Option<Vec3> {
+        // Newell's method, robust to duplicate vertices
+        let verts = self.vertices();
+        let mut normal = glm::to_vec3(0.);
+        for (i, current) in verts.iter().enumerate() {
+            let next = &verts[(i + 1) % verts.len()];
+            let (a, b) = (current.pos, next.pos);
+            normal.x += (a.y - b.y) * (a.z + b.z);
+            normal.y += (a.z - b.z) * (a.x + b.x);
+            normal.z += (a.x - b.x) * (a.y + b.y);
+        }
+        let length = glm::length(normal);
+        if length <= f32::EPSILON {
+            None
+        } else {
+            Some(normal / length)
+        }
+    }
+}
+
 impl Index<usize> for BrushPlane {
     type Output = Vertex;
     fn index(&self, index: usize) -> &Self::Output {
@@ -51,7 +96,26 @@ macro_rules! brush_decl {
 // A Brush-like object that has renderable planes.
 pub trait Brush {
     fn planes(&self) -> &[BrushPlane];
+    /// The bounding box of every plane, or [`None`] if there are no vertices.
+    fn bounds(&self) -> Option<Aabb> {
+        Aabb::from_points(
+            self.planes()
+                .iter()
+                .flat_map(|plane| plane.vertices())
+                .map(|vert| vert.pos),
+        )
+    }
 }
 
 brush_decl!(Brush3, 3);
 brush_decl!(Brush4, 4);
+
+/// A brush with any number of planes, e.g. one loaded from a map.
+#[derive(Clone, Debug)]
+pub struct PolyBrush(pub Box<[BrushPlane]>);
+
+impl Brush for PolyBrush {
+    fn planes(&self) -> &[BrushPlane] {
+        &self.0
+    }
+}
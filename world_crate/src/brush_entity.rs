@@ -0,0 +1,813 @@
+//! Exports [`BrushModel`], brushes that belong to an entity, and the classes that move them:
+//! [`FUNC_DOOR`], [`FUNC_PLAT`] and [`FUNC_ROTATING`].
+//!
+//! A brush entity's brushes are kept around its [`Transform::position`], so moving or turning
+//! the entity moves its brushes without touching their vertices. Maps give brushes to an
+//! entity with its `brushes` key, see [`ClassRegistry::spawn_with_brushes`].
+//!
+//! Movers are kinematic: nothing slows them down. Anything with a solid [`Collider`] that a
+//! [`Mover`] moves into, or that stands on top of it, is pushed along with it. If that would
+//! push it into static brushes or another brush entity, the mover is blocked instead: it fires
+//! `OnBlocked`, and goes back the way it came. A [`Rotator`] that would turn into something
+//! stops until it's out of the way. Rotating brushes don't carry what stands on them.
+//! All of this goes by bounding boxes, so a turning brush is stopped a little early.
+//!
+//! The engine gives nothing a [`Collider`] itself, and has no player entity, so only what a
+//! game gives a solid collider is pushed.
+//!
+//! [`ClassRegistry::spawn_with_brushes`]: crate::class::ClassRegistry::spawn_with_brushes
+use glm::Vec3;
+
+use crate::{
+    bounds::Aabb,
+    brush::{Brush, BrushPlane, NGonPlane, PolyBrush},
+    class::{EntityClass, Properties, PropertyError},
+    component::{Collider, Transform},
+    entity::{Entities, EntityId},
+    io::Input,
+    vertex::Vertex,
+};
+
+/// How far above a mover's top something can be and still be standing on it.
+const RIDE_TOLERANCE: f32 = 0.01;
+
+/// The brushes an entity is made of, around its [`Transform::position`].
+#[derive(Clone, Debug)]
+pub struct BrushModel {
+    /// The brushes, in entity space.
+    brushes: Vec<PolyBrush>,
+    /// The bounding box of every brush, in entity space.
+    bounds: Option<Aabb>,
+}
+
+impl BrushModel {
+    /// A model of `brushes`, which are already in entity space.
+    pub fn new(brushes: Vec<PolyBrush>) -> Self {
+        let bounds = brushes.iter().filter_map(Brush::bounds).reduce(Aabb::union);
+        Self { brushes, bounds }
+    }
+    /// A model of `brushes`, which are in world space, for an entity at `origin`.
+    pub fn from_world_space(brushes: &[PolyBrush], origin: Vec3) -> Self {
+        let to_entity_space = |plane: &BrushPlane| {
+            let verts = plane.vertices().iter().map(|vert| Vertex {
+                pos: vert.pos - origin,
+            });
+            BrushPlane::NGon(NGonPlane(verts.collect()))
+        };
+        Self::new(
+            brushes
+                .iter()
+                .map(|brush| PolyBrush(brush.planes().iter().map(to_entity_space).collect()))
+                .collect(),
+        )
+    }
+    /// The brushes, in entity space.
+    pub fn brushes(&self) -> &[PolyBrush] {
+        &self.brushes
+    }
+    /// The bounding box of every brush, in entity space, or [`None`] if there are no vertices.
+    pub fn bounds(&self) -> Option<Aabb> {
+        self.bounds
+    }
+    /// The bounding box of every brush for an entity at `transform`, in world space.
+    pub fn world_bounds(&self, transform: &Transform) -> Option<Aabb> {
+        let corners = self.bounds?.corners();
+        Aabb::from_points(corners.map(|corner| transform.apply(corner)))
+    }
+}
+
+/// Where a [`Mover`] is going.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MoverState {
+    /// At its closed position.
+    Closed,
+    /// On its way to its open position.
+    Opening,
+    /// At its open position.
+    Open,
+    /// On its way to its closed position.
+    Closing,
+}
+
+/// Slides an entity between two positions, like a door or a platform.
+///
+/// Fires `OnFullyOpen` and `OnFullyClosed` when it gets to either end, and `OnBlocked`
+/// when something's in the way.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Mover {
+    /// Where the entity is when closed.
+    pub closed_position: Vec3,
+    /// Where the entity is when open.
+    pub open_position: Vec3,
+    /// Units per second.
+    pub speed: f32,
+    /// Seconds it stays open before closing by itself, or negative to stay open.
+    pub wait: f64,
+    /// Where it's going.
+    pub state: MoverState,
+    /// Seconds left before it closes by itself, while open.
+    wait_left: f64,
+}
+
+impl Mover {
+    /// A closed mover at `closed_position`.
+    pub fn new(closed_position: Vec3, open_position: Vec3, speed: f32, wait: f64) -> Self {
+        Self {
+            closed_position,
+            open_position,
+            speed,
+            wait,
+            state: MoverState::Closed,
+            wait_left: 0.,
+        }
+    }
+    /// Start opening, or wait longer before closing if it's already open.
+    pub fn open(&mut self) {
+        match self.state {
+            MoverState::Closed | MoverState::Closing => self.state = MoverState::Opening,
+            MoverState::Open => self.wait_left = self.wait,
+            MoverState::Opening => {}
+        }
+    }
+    /// Start closing.
+    pub fn close(&mut self) {
+        if matches!(self.state, MoverState::Open | MoverState::Opening) {
+            self.state = MoverState::Closing;
+        }
+    }
+    /// Open if it's closed or closing, otherwise close.
+    pub fn toggle(&mut self) {
+        match self.state {
+            MoverState::Closed | MoverState::Closing => self.open(),
+            MoverState::Open | MoverState::Opening => self.close(),
+        }
+    }
+    /// Where it's going, if it's moving.
+    pub fn destination(&self) -> Option<Vec3> {
+        match self.state {
+            MoverState::Opening => Some(self.open_position),
+            MoverState::Closing => Some(self.closed_position),
+            MoverState::Open | MoverState::Closed => None,
+        }
+    }
+}
+
+/// Turns an entity at a steady speed, like a fan.
+///
+/// Fires `OnBlocked` when something stops it turning.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rotator {
+    /// Degrees per second, around each axis like [`Transform::rotation`].
+    pub speed: Vec3,
+    /// Whether it's turning.
+    pub on: bool,
+    /// Whether it was stopped by something last tick.
+    blocked: bool,
+}
+
+impl Rotator {
+    /// A rotator turning at `speed` degrees per second if `on`.
+    pub fn new(speed: Vec3, on: bool) -> Self {
+        Self {
+            speed,
+            on,
+            blocked: false,
+        }
+    }
+    /// Whether it was stopped by something last tick.
+    pub fn is_blocked(&self) -> bool {
+        self.blocked
+    }
+}
+
+/// The model the registry gave the brush entity with `id`.
+fn model_of(entities: &Entities, id: EntityId) -> Result<(BrushModel, Transform), PropertyError> {
+    let missing = || PropertyError::Missing {
+        key: "brushes".to_owned(),
+    };
+    let model = entities.get::<BrushModel>(id).ok_or_else(missing)?;
+    let transform = entities.get::<Transform>(id).ok_or_else(missing)?;
+    Ok((model.clone(), *transform))
+}
+
+/// `key` as a speed above 0, or `default` if it isn't set.
+fn positive(properties: &Properties, key: &str, default: f32) -> Result<f32, PropertyError> {
+    let value = properties.parse_or(key, default)?;
+    if value > 0. {
+        Ok(value)
+    } else {
+        Err(PropertyError::Bad {
+            key: key.to_owned(),
+            value: value.to_string(),
+            expected: "a number above 0",
+        })
+    }
+}
+
+/// Change the [`Mover`] of the entity with `id`, if it has one.
+fn with_mover(entities: &mut Entities, id: EntityId, change: fn(&mut Mover)) {
+    if let Some(mover) = entities.get_mut::<Mover>(id) {
+        change(mover);
+    }
+}
+
+/// Change the [`Rotator`] of the entity with `id`, if it has one.
+fn with_rotator(entities: &mut Entities, id: EntityId, change: fn(&mut Rotator)) {
+    if let Some(rotator) = entities.get_mut::<Rotator>(id) {
+        change(rotator);
+    }
+}
+
+/// A door that slides along `movedir` by its own size. Keys: `brushes` (required),
+/// `movedir` (default up), `lip` (units left showing when open, default 0),
+/// `speed` (default 2), `wait` (default 3, -1 to stay open).
+/// Inputs: `Open`, `Close`, `Toggle`.
+pub const FUNC_DOOR: EntityClass = EntityClass {
+    classname: "func_door",
+    required: &["brushes"],
+    inputs: &[
+        Input {
+            name: "Open",
+            handler: |entities, id, _| with_mover(entities, id, Mover::open),
+        },
+        Input {
+            name: "Close",
+            handler: |entities, id, _| with_mover(entities, id, Mover::close),
+        },
+        Input {
+            name: "Toggle",
+            handler: |entities, id, _| with_mover(entities, id, Mover::toggle),
+        },
+    ],
+    spawn: |entities, id, properties| {
+        let (model, transform) = model_of(entities, id)?;
+        let movedir: Vec3 = properties.parse_or("movedir", glm::vec3(0., 1., 0.))?;
+        if glm::length(movedir) <= f32::EPSILON {
+            return Err(PropertyError::Bad {
+                key: "movedir".to_owned(),
+                value: properties.get("movedir").unwrap_or_default().to_owned(),
+                expected: "a direction that isn't \"0 0 0\"",
+            });
+        }
+        let movedir = glm::normalize(movedir);
+        let size = model
+            .world_bounds(&transform)
+            .map_or(glm::to_vec3(0.), |bounds| bounds.max - bounds.min);
+        let lip: f32 = properties.parse_or("lip", 0.)?;
+        let distance = (glm::dot(glm::abs(movedir), size) - lip).max(0.);
+        let mover = Mover::new(
+            transform.position,
+            transform.position + movedir * distance,
+            positive(properties, "speed", 2.)?,
+            f64::from(properties.parse_or::<f32>("wait", 3.)?),
+        );
+        entities.insert(id, mover);
+        Ok(())
+    },
+};
+
+/// A platform that rises by `height` and comes back down. Keys: `brushes` (required),
+/// `height` (default its own height), `speed` (default 2), `wait` (default -1, stays up).
+/// Inputs: `Raise`, `Lower`, `Toggle`.
+pub const FUNC_PLAT: EntityClass = EntityClass {
+    classname: "func_plat",
+    required: &["brushes"],
+    inputs: &[
+        Input {
+            name: "Raise",
+            handler: |entities, id, _| with_mover(entities, id, Mover::open),
+        },
+        Input {
+            name: "Lower",
+            handler: |entities, id, _| with_mover(entities, id, Mover::close),
+        },
+        Input {
+            name: "Toggle",
+            handler: |entities, id, _| with_mover(entities, id, Mover::toggle),
+        },
+    ],
+    spawn: |entities, id, properties| {
+        let (model, transform) = model_of(entities, id)?;
+        let own_height = model
+            .world_bounds(&transform)
+            .map_or(0., |bounds| bounds.max.y - bounds.min.y);
+        let height: f32 = properties.parse_or("height", own_height)?;
+        let mover = Mover::new(
+            transform.position,
+            transform.position + glm::vec3(0., height, 0.),
+            positive(properties, "speed", 2.)?,
+            f64::from(properties.parse_or::<f32>("wait", -1.)?),
+        );
+        entities.insert(id, mover);
+        Ok(())
+    },
+};
+
+/// Something that turns around its `origin`. Keys: `brushes` (required), `origin`
+/// (default its centre), `speed` (degrees per second, default 90), `axis` (`x`, `y` or `z`,
+/// default `y`), `start_on` (default 1). Inputs: `Start`, `Stop`, `Toggle`.
+pub const FUNC_ROTATING: EntityClass = EntityClass {
+    classname: "func_rotating",
+    required: &["brushes"],
+    inputs: &[
+        Input {
+            name: "Start",
+            handler: |entities, id, _| with_rotator(entities, id, |rotator| rotator.on = true),
+        },
+        Input {
+            name: "Stop",
+            handler: |entities, id, _| with_rotator(entities, id, |rotator| rotator.on = false),
+        },
+        Input {
+            name: "Toggle",
+            handler: |entities, id, _| {
+                with_rotator(entities, id, |rotator| rotator.on = !rotator.on);
+            },
+        },
+    ],
+    spawn: |entities, id, properties| {
+        model_of(entities, id)?;
+        let speed: f32 = properties.parse_or("speed", 90.)?;
+        // rotation is pitch, yaw, roll, which turn around X, Y and Z
+        let speed = match properties.get("axis").unwrap_or("y").trim() {
+            "x" => glm::vec3(speed, 0., 0.),
+            "y" => glm::vec3(0., speed, 0.),
+            "z" => glm::vec3(0., 0., speed),
+            other => {
+                return Err(PropertyError::Bad {
+                    key: "axis".to_owned(),
+                    value: other.to_owned(),
+                    expected: "x, y or z",
+                });
+            }
+        };
+        let on = properties.parse_or("start_on", true)?;
+        entities.insert(id, Rotator::new(speed, on));
+        Ok(())
+    },
+};
+
+/// Where every brush entity but `except` is.
+fn brush_entity_bounds(entities: &Entities, except: EntityId) -> Vec<Aabb> {
+    entities
+        .query::<(EntityId, &BrushModel, &Transform)>()
+        .filter(|(id, _, _)| *id != except)
+        .filter_map(|(_, model, transform)| model.world_bounds(transform))
+        .collect()
+}
+
+/// Where every entity with a solid [`Collider`] is, except brush entities.
+fn solid_bounds(entities: &Entities) -> Vec<(EntityId, Aabb)> {
+    entities
+        .query::<(EntityId, &Transform, &Collider, Option<&BrushModel>)>()
+        .filter(|(_, _, collider, model)| collider.solid && model.is_none())
+        .map(|(id, transform, collider, _)| (id, collider.bounds(transform)))
+        .collect()
+}
+
+/// Whether `rider` is standing on top of `platform`.
+fn rides(rider: &Aabb, platform: &Aabb) -> bool {
+    (rider.min.y - platform.max.y).abs() <= RIDE_TOLERANCE
+        && rider.min.x < platform.max.x
+        && platform.min.x < rider.max.x
+        && rider.min.z < platform.max.z
+        && platform.min.z < rider.max.z
+}
+
+/// Move the [`Mover`] with `id` on by `dt` seconds, pushing what's in its way.
+/// `static_bounds` are the bounds of the world's static brushes.
+pub(crate) fn move_mover(
+    entities: &mut Entities,
+    id: EntityId,
+    static_bounds: &[Option<Aabb>],
+    dt: f64,
+) {
+    let (Some(&mover), Some(&transform)) =
+        (entities.get::<Mover>(id), entities.get::<Transform>(id))
+    else {
+        return;
+    };
+    let mut mover = mover;
+    let Some(destination) = mover.destination() else {
+        if mover.state == MoverState::Open && mover.wait >= 0. {
+            mover.wait_left -= dt;
+            if mover.wait_left <= 0. {
+                mover.close();
+            }
+            entities.insert(id, mover);
+        }
+        return;
+    };
+
+    let to_go = destination - transform.position;
+    let distance = glm::length(to_go);
+    let max_step = mover.speed * dt as f32;
+    let arrived = distance <= max_step;
+    let step = if arrived {
+        to_go
+    } else {
+        to_go * (max_step / distance)
+    };
+
+    let before = entities
+        .get::<BrushModel>(id)
+        .and_then(|model| model.world_bounds(&transform));
+    let pushed: Vec<(EntityId, Aabb)> = match before {
+        Some(before) => {
+            let after = before.translate(step);
+            solid_bounds(entities)
+                .into_iter()
+                .filter(|(_, bounds)| bounds.overlaps(&after) || rides(bounds, &before))
+                .collect()
+        }
+        None => vec![],
+    };
+    let others = brush_entity_bounds(entities, id);
+    let blocked = pushed.iter().any(|(_, bounds)| {
+        let moved = bounds.translate(step);
+        static_bounds
+            .iter()
+            .flatten()
+            .chain(&others)
+            .any(|solid| moved.overlaps(solid))
+    });
+    if blocked {
+        mover.toggle();
+        entities.insert(id, mover);
+        entities.fire_output(id, "OnBlocked");
+        return;
+    }
+
+    for (pushed_id, _) in pushed {
+        if let Some(pushed) = entities.get_mut::<Transform>(pushed_id) {
+            pushed.position = pushed.position + step;
+        }
+    }
+    if let Some(transform) = entities.get_mut::<Transform>(id) {
+        transform.position = transform.position + step;
+    }
+    if arrived {
+        let output = if mover.state == MoverState::Opening {
+            mover.state = MoverState::Open;
+            mover.wait_left = mover.wait;
+            "OnFullyOpen"
+        } else {
+            mover.state = MoverState::Closed;
+            "OnFullyClosed"
+        };
+        entities.insert(id, mover);
+        entities.fire_output(id, output);
+    } else {
+        entities.insert(id, mover);
+    }
+}
+
+/// Turn the [`Rotator`] with `id` on by `dt` seconds, unless that would turn it into something.
+pub(crate) fn turn_rotator(entities: &mut Entities, id: EntityId, dt: f64) {
+    let (Some(&rotator), Some(&transform)) =
+        (entities.get::<Rotator>(id), entities.get::<Transform>(id))
+    else {
+        return;
+    };
+    if !rotator.on {
+        return;
+    }
+    let rotation = transform.rotation + rotator.speed * dt as f32;
+    let turned = Transform {
+        rotation: glm::vec3(rotation.x % 360., rotation.y % 360., rotation.z % 360.),
+        ..transform
+    };
+
+    let blocked = entities.get::<BrushModel>(id).is_some_and(|model| {
+        let (Some(before), Some(after)) =
+            (model.world_bounds(&transform), model.world_bounds(&turned))
+        else {
+            return false;
+        };
+        // only what it would turn into, so something already inside the box doesn't jam it
+        solid_bounds(entities)
+            .iter()
+            .any(|(_, bounds)| bounds.overlaps(&after) && !bounds.overlaps(&before))
+    });
+    if blocked {
+        if !rotator.blocked {
+            entities.insert(
+                id,
+                Rotator {
+                    blocked: true,
+                    ..rotator
+                },
+            );
+            entities.fire_output(id, "OnBlocked");
+        }
+        return;
+    }
+    entities.insert(
+        id,
+        Rotator {
+            blocked: false,
+            ..rotator
+        },
+    );
+    entities.insert(id, turned);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        class::ClassRegistry,
+        io::Outputs,
+        systems::move_brush_entities,
+    };
+
+    /// A brush filling the box from `min` to `max`.
+    fn box_brush(min: Vec3, max: Vec3) -> PolyBrush {
+        let corners = Aabb::new(min, max).corners().map(|pos| Vertex { pos });
+        PolyBrush(Box::new([BrushPlane::NGon(NGonPlane(Box::new(corners)))]))
+    }
+
+    /// Properties from `pairs`, with the `brushes` key a map would give.
+    fn properties(pairs: &[(&str, &str)]) -> Properties {
+        let mut properties: Properties = pairs.iter().copied().collect();
+        properties.insert("brushes", "0");
+        properties
+    }
+
+    /// Spawn a brush entity made of `brush`.
+    fn spawn(entities: &mut Entities, brush: PolyBrush, pairs: &[(&str, &str)]) -> EntityId {
+        let properties = properties(pairs);
+        ClassRegistry::with_builtins()
+            .spawn_with_brushes(entities, &properties, &[brush])
+            .unwrap()
+    }
+
+    /// A 1 by 2 by 1 door at the origin, which opens 2 up at 1 unit per second.
+    fn door(entities: &mut Entities) -> EntityId {
+        spawn(
+            entities,
+            box_brush(glm::to_vec3(0.), glm::vec3(1., 2., 1.)),
+            &[
+                ("classname", "func_door"),
+                ("targetname", "door"),
+                ("speed", "1"),
+                ("wait", "1"),
+                ("output", "OnFullyOpen -> x.Y"),
+                ("output", "OnFullyClosed -> x.Y"),
+                ("output", "OnBlocked -> x.Y"),
+            ],
+        )
+    }
+
+    /// A solid 1 unit box centred on `position`.
+    fn crate_at(entities: &mut Entities, position: Vec3) -> EntityId {
+        let id = entities.spawn();
+        entities.insert(id, Transform::from_position(position));
+        entities.insert(
+            id,
+            Collider {
+                half_extents: glm::to_vec3(0.5),
+                solid: true,
+            },
+        );
+        id
+    }
+
+    fn position(entities: &Entities, id: EntityId) -> Vec3 {
+        entities.get::<Transform>(id).unwrap().position
+    }
+
+    fn state(entities: &Entities, id: EntityId) -> MoverState {
+        entities.get::<Mover>(id).unwrap().state
+    }
+
+    /// The outputs fired and not yet delivered, in the order they fired.
+    fn fired(entities: &mut Entities) -> Vec<String> {
+        let fired = entities
+            .events()
+            .pending()
+            .iter()
+            .filter_map(|event| event.output.clone())
+            .collect();
+        // send them, so they're not seen again
+        entities.tick(0.);
+        fired
+    }
+
+    #[test]
+    fn mover_states() {
+        let mut mover = Mover::new(glm::to_vec3(0.), glm::vec3(0., 1., 0.), 1., 2.);
+        assert_eq!(mover.destination(), None);
+        mover.close();
+        assert_eq!(mover.state, MoverState::Closed);
+        mover.open();
+        assert_eq!(mover.state, MoverState::Opening);
+        assert_eq!(mover.destination(), Some(glm::vec3(0., 1., 0.)));
+        mover.toggle();
+        assert_eq!(mover.state, MoverState::Closing);
+        assert_eq!(mover.destination(), Some(glm::to_vec3(0.)));
+        mover.toggle();
+        assert_eq!(mover.state, MoverState::Opening);
+
+        mover.state = MoverState::Open;
+        mover.open();
+        assert_eq!((mover.state, mover.wait_left), (MoverState::Open, 2.));
+        mover.toggle();
+        assert_eq!(mover.state, MoverState::Closing);
+    }
+
+    #[test]
+    fn doors_open_wait_and_close() {
+        let mut entities = Entities::new();
+        let door = door(&mut entities);
+        let closed = position(&entities, door);
+        assert_eq!(closed, glm::vec3(0.5, 1., 0.5));
+        assert_eq!(
+            entities.get::<Mover>(door).unwrap().open_position,
+            glm::vec3(0.5, 3., 0.5)
+        );
+
+        entities.fire_input("door", "Open", 0.);
+        entities.tick(0.);
+        move_brush_entities(&mut entities, &[], 1.);
+        assert_eq!(position(&entities, door), glm::vec3(0.5, 2., 0.5));
+        assert_eq!(state(&entities, door), MoverState::Opening);
+        move_brush_entities(&mut entities, &[], 1.5);
+        assert_eq!(position(&entities, door), glm::vec3(0.5, 3., 0.5));
+        assert_eq!(state(&entities, door), MoverState::Open);
+        assert_eq!(fired(&mut entities), ["OnFullyOpen"]);
+
+        // it waits a second, then starts closing
+        move_brush_entities(&mut entities, &[], 0.5);
+        assert_eq!(state(&entities, door), MoverState::Open);
+        move_brush_entities(&mut entities, &[], 0.5);
+        assert_eq!(state(&entities, door), MoverState::Closing);
+        assert_eq!(position(&entities, door), glm::vec3(0.5, 3., 0.5));
+        move_brush_entities(&mut entities, &[], 2.);
+        assert_eq!(position(&entities, door), closed);
+        assert_eq!(state(&entities, door), MoverState::Closed);
+        assert_eq!(fired(&mut entities), ["OnFullyClosed"]);
+    }
+
+    #[test]
+    fn doors_that_wait_forever_stay_open() {
+        let mut entities = Entities::new();
+        let door = door(&mut entities);
+        entities.get_mut::<Mover>(door).unwrap().wait = -1.;
+        entities.get_mut::<Mover>(door).unwrap().open();
+        for _ in 0..10 {
+            move_brush_entities(&mut entities, &[], 1.);
+        }
+        assert_eq!(state(&entities, door), MoverState::Open);
+    }
+
+    #[test]
+    fn movers_push_and_carry_solid_entities() {
+        let mut entities = Entities::new();
+        let door = door(&mut entities);
+        // standing on top, and in the way above that
+        let rider = crate_at(&mut entities, glm::vec3(0.5, 2.5, 0.5));
+        let pushed = crate_at(&mut entities, glm::vec3(0.5, 4., 0.5));
+        // off to the side
+        let beside = crate_at(&mut entities, glm::vec3(2., 2.5, 0.5));
+        entities.get_mut::<Mover>(door).unwrap().open();
+
+        move_brush_entities(&mut entities, &[], 1.);
+        assert_eq!(position(&entities, rider), glm::vec3(0.5, 3.5, 0.5));
+        assert_eq!(position(&entities, pushed), glm::vec3(0.5, 4., 0.5));
+        move_brush_entities(&mut entities, &[], 1.);
+        assert_eq!(position(&entities, pushed), glm::vec3(0.5, 5., 0.5));
+        assert_eq!(position(&entities, beside), glm::vec3(2., 2.5, 0.5));
+
+        // things that aren't solid are left where they are
+        entities.get_mut::<Collider>(rider).unwrap().solid = false;
+        entities.get_mut::<Mover>(door).unwrap().close();
+        move_brush_entities(&mut entities, &[], 1.);
+        assert_eq!(position(&entities, rider), glm::vec3(0.5, 4.5, 0.5));
+    }
+
+    #[test]
+    fn blocked_movers_reverse() {
+        let mut entities = Entities::new();
+        let door = door(&mut entities);
+        let rider = crate_at(&mut entities, glm::vec3(0.5, 2.5, 0.5));
+        let ceiling = Aabb::new(glm::vec3(0., 3.5, 0.), glm::vec3(1., 4., 1.));
+        entities.fire_input("door", "Open", 0.);
+        entities.tick(0.);
+
+        move_brush_entities(&mut entities, &[None, Some(ceiling)], 1.);
+        assert_eq!(state(&entities, door), MoverState::Closing);
+        assert_eq!(position(&entities, door), glm::vec3(0.5, 1., 0.5));
+        assert_eq!(position(&entities, rider), glm::vec3(0.5, 2.5, 0.5));
+        assert_eq!(fired(&mut entities), ["OnBlocked"]);
+
+        move_brush_entities(&mut entities, &[Some(ceiling)], 1.);
+        assert_eq!(state(&entities, door), MoverState::Closed);
+    }
+
+    #[test]
+    fn movers_are_blocked_by_other_brush_entities() {
+        let mut entities = Entities::new();
+        let door = door(&mut entities);
+        crate_at(&mut entities, glm::vec3(0.5, 2.5, 0.5));
+        spawn(
+            &mut entities,
+            box_brush(glm::vec3(0., 3.5, 0.), glm::vec3(1., 4., 1.)),
+            &[("classname", "func_door")],
+        );
+        entities.get_mut::<Mover>(door).unwrap().open();
+        move_brush_entities(&mut entities, &[], 1.);
+        assert_eq!(state(&entities, door), MoverState::Closing);
+    }
+
+    #[test]
+    fn rotators_stop_for_solid_entities() {
+        let mut entities = Entities::new();
+        let fan = spawn(
+            &mut entities,
+            box_brush(glm::vec3(-1., 0., -0.1), glm::vec3(1., 1., 0.1)),
+            &[
+                ("classname", "func_rotating"),
+                ("targetname", "fan"),
+                ("output", "OnBlocked -> x.Y"),
+            ],
+        );
+        let in_the_way = crate_at(&mut entities, glm::vec3(0., 0.5, 0.8));
+
+        move_brush_entities(&mut entities, &[], 1.);
+        let rotator = entities.get::<Rotator>(fan).unwrap();
+        assert!(rotator.is_blocked());
+        assert_eq!(entities.get::<Transform>(fan).unwrap().rotation, glm::to_vec3(0.));
+        assert_eq!(fired(&mut entities), ["OnBlocked"]);
+        // only fired when it's first blocked
+        move_brush_entities(&mut entities, &[], 1.);
+        assert!(fired(&mut entities).is_empty());
+
+        entities.despawn(in_the_way);
+        move_brush_entities(&mut entities, &[], 0.5);
+        assert!(!entities.get::<Rotator>(fan).unwrap().is_blocked());
+        assert_eq!(
+            entities.get::<Transform>(fan).unwrap().rotation,
+            glm::vec3(0., 45., 0.)
+        );
+
+        entities.fire_input("fan", "Stop", 0.);
+        entities.tick(0.);
+        move_brush_entities(&mut entities, &[], 1.);
+        assert_eq!(
+            entities.get::<Transform>(fan).unwrap().rotation,
+            glm::vec3(0., 45., 0.)
+        );
+    }
+
+    #[test]
+    fn rotators_wrap_around() {
+        let mut entities = Entities::new();
+        let fan = spawn(
+            &mut entities,
+            box_brush(glm::to_vec3(-1.), glm::to_vec3(1.)),
+            &[("classname", "func_rotating"), ("axis", "x"), ("speed", "100")],
+        );
+        move_brush_entities(&mut entities, &[], 4.);
+        assert_eq!(
+            entities.get::<Transform>(fan).unwrap().rotation,
+            glm::vec3(40., 0., 0.)
+        );
+    }
+
+    #[test]
+    fn brush_entity_keys() {
+        let mut entities = Entities::new();
+        let classes = ClassRegistry::with_builtins();
+        let brush = box_brush(glm::to_vec3(0.), glm::vec3(2., 1., 1.));
+        let spawn = |entities: &mut Entities, pairs: &[(&str, &str)]| {
+            let properties = properties(pairs);
+            classes.spawn_with_brushes(entities, &properties, std::slice::from_ref(&brush))
+        };
+        assert!(spawn(&mut entities, &[("classname", "func_door"), ("movedir", "0 0 0")]).is_err());
+        assert!(spawn(&mut entities, &[("classname", "func_plat"), ("speed", "0")]).is_err());
+        assert!(spawn(&mut entities, &[("classname", "func_rotating"), ("axis", "w")]).is_err());
+        assert!(entities.is_empty());
+
+        // sideways by its width less the lip
+        let door = spawn(
+            &mut entities,
+            &[("classname", "func_door"), ("movedir", "-1 0 0"), ("lip", "0.5")],
+        )
+        .unwrap();
+        let mover = entities.get::<Mover>(door).unwrap();
+        assert_eq!(mover.open_position - mover.closed_position, glm::vec3(-1.5, 0., 0.));
+        // plats go up by their height, and stay up
+        let plat = spawn(&mut entities, &[("classname", "func_plat")]).unwrap();
+        let mover = entities.get::<Mover>(plat).unwrap();
+        assert_eq!(mover.open_position - mover.closed_position, glm::vec3(0., 1., 0.));
+        assert_eq!(mover.wait, -1.);
+        // brushes are kept around the origin
+        let model = entities.get::<BrushModel>(plat).unwrap();
+        assert_eq!(
+            model.bounds(),
+            Some(Aabb::new(glm::vec3(-1., -0.5, -0.5), glm::vec3(1., 0.5, 0.5)))
+        );
+        assert!(entities.get::<Outputs>(plat).is_none());
+    }
+}
@@ -0,0 +1,688 @@
+//! Exports [`ClassRegistry`], which spawns entities from a map's key/value [`Properties`].
+//!
+//! Every entity in a map has a `classname`, which picks the [`EntityClass`] that spawns it.
+//! The class lists the keys it needs, and its spawn function reads the rest as typed values
+//! with [`Properties::parse`]. Every entity gets a [`Classname`], a [`TargetName`] if it
+//! has a `targetname`, [`Outputs`] from its `output` keys, and its class's [`Inputs`],
+//! before its class's spawn function runs.
+//!
+//! Values are text. Vectors (`origin`, `angles`) are three numbers separated by spaces, and
+//! colours are three numbers from 0 to 1, or 0 to 255 if any is over 1.
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    fmt::{self, Display},
+};
+
+use glm::Vec3;
+
+use crate::{
+    bounds::Aabb,
+    brush::{Brush, PolyBrush},
+    brush_entity::{BrushModel, FUNC_DOOR, FUNC_PLAT, FUNC_ROTATING},
+    component::{Classname, Light, TargetName, Transform},
+    entity::{Entities, EntityId},
+    io::{Connection, Input, Inputs, Outputs},
+};
+
+/// An entity's keys and values, as they were in the map.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Properties {
+    /// Every key and value, in the order they were given.
+    pairs: Vec<(String, String)>,
+}
+
+impl Properties {
+    /// Create an empty set of properties.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Set `key` to `value`. Later values win over earlier ones, except for keys read
+    /// with [`Properties::parse_all`].
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.pairs.push((key.into(), value.into()));
+    }
+    /// The text of `key`, if it's set.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.pairs
+            .iter()
+            .rev()
+            .find(|(pair_key, _)| pair_key == key)
+            .map(|(_, value)| value.as_str())
+    }
+    /// Whether `key` is set.
+    pub fn contains(&self, key: &str) -> bool {
+        self.get(key).is_some()
+    }
+    /// Every key and value, in the order they were given.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.pairs
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+    /// The `classname`, if it's set.
+    pub fn classname(&self) -> Option<&str> {
+        self.get("classname")
+    }
+
+    /// `key` as a `T`, or [`None`] if it isn't set.
+    ///
+    /// # Errors
+    /// Errors if it's set, but isn't a `T`.
+    pub fn parse<T: PropertyValue>(&self, key: &str) -> Result<Option<T>, PropertyError> {
+        let Some(text) = self.get(key) else {
+            return Ok(None);
+        };
+        T::parse_property(text.trim())
+            .map(Some)
+            .ok_or_else(|| PropertyError::Bad {
+                key: key.to_owned(),
+                value: text.to_owned(),
+                expected: T::EXPECTED,
+            })
+    }
+    /// Every value of `key` as a `T`, for keys that can be given more than once.
+    ///
+    /// # Errors
+    /// Errors if any of them isn't a `T`.
+    pub fn parse_all<T: PropertyValue>(&self, key: &str) -> Result<Vec<T>, PropertyError> {
+        self.pairs
+            .iter()
+            .filter(|(pair_key, _)| pair_key == key)
+            .map(|(_, text)| {
+                T::parse_property(text.trim()).ok_or_else(|| PropertyError::Bad {
+                    key: key.to_owned(),
+                    value: text.clone(),
+                    expected: T::EXPECTED,
+                })
+            })
+            .collect()
+    }
+    /// `key` as a `T`, or `default` if it isn't set.
+    ///
+    /// # Errors
+    /// Errors if it's set, but isn't a `T`.
+    pub fn parse_or<T: PropertyValue>(&self, key: &str, default: T) -> Result<T, PropertyError> {
+        Ok(self.parse(key)?.unwrap_or(default))
+    }
+    /// `key` as a `T`.
+    ///
+    /// # Errors
+    /// Errors if it isn't set, or isn't a `T`.
+    pub fn require<T: PropertyValue>(&self, key: &str) -> Result<T, PropertyError> {
+        self.parse(key)?.ok_or_else(|| PropertyError::Missing {
+            key: key.to_owned(),
+        })
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for Properties {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self {
+            pairs: iter
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        }
+    }
+}
+
+/// A type a property's text can be read as.
+pub trait PropertyValue: Sized {
+    /// What the text should look like, for errors.
+    const EXPECTED: &'static str;
+    /// Read `text`, or [`None`] if it isn't one of these.
+    fn parse_property(text: &str) -> Option<Self>;
+}
+
+impl PropertyValue for String {
+    const EXPECTED: &'static str = "text";
+    fn parse_property(text: &str) -> Option<Self> {
+        Some(text.to_owned())
+    }
+}
+impl PropertyValue for f32 {
+    const EXPECTED: &'static str = "a number";
+    fn parse_property(text: &str) -> Option<Self> {
+        text.parse().ok().filter(|number: &f32| number.is_finite())
+    }
+}
+impl PropertyValue for i32 {
+    const EXPECTED: &'static str = "a whole number";
+    fn parse_property(text: &str) -> Option<Self> {
+        text.parse().ok()
+    }
+}
+impl PropertyValue for bool {
+    const EXPECTED: &'static str = "0 or 1";
+    fn parse_property(text: &str) -> Option<Self> {
+        match text {
+            "0" => Some(false),
+            "1" => Some(true),
+            _ => None,
+        }
+    }
+}
+impl PropertyValue for Vec<u32> {
+    const EXPECTED: &'static str = "whole numbers separated by spaces, like \"0 4 5\"";
+    fn parse_property(text: &str) -> Option<Self> {
+        text.split_whitespace()
+            .map(|number| number.parse().ok())
+            .collect()
+    }
+}
+impl PropertyValue for Vec3 {
+    const EXPECTED: &'static str = "three numbers, like \"0 16 -8\"";
+    fn parse_property(text: &str) -> Option<Self> {
+        let mut numbers = text.split_whitespace().map(f32::parse_property);
+        let vector = glm::vec3(numbers.next()??, numbers.next()??, numbers.next()??);
+        numbers.next().is_none().then_some(vector)
+    }
+}
+
+/// A colour, from 0 to 1 per channel.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Colour(pub Vec3);
+
+impl PropertyValue for Colour {
+    const EXPECTED: &'static str = "three numbers from 0 to 1, or 0 to 255";
+    fn parse_property(text: &str) -> Option<Self> {
+        let colour = Vec3::parse_property(text)?;
+        let channels = [colour.x, colour.y, colour.z];
+        if channels
+            .iter()
+            .any(|channel| *channel < 0. || *channel > 255.)
+        {
+            return None;
+        }
+        if channels.iter().any(|channel| *channel > 1.) {
+            return Some(Colour(colour / 255.));
+        }
+        Some(Colour(colour))
+    }
+}
+
+/// A property that's missing or can't be read.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PropertyError {
+    /// A key the class needs isn't set.
+    Missing {
+        /// The key.
+        key: String,
+    },
+    /// A key's value isn't the type the class needs.
+    Bad {
+        /// The key.
+        key: String,
+        /// Its value.
+        value: String,
+        /// What the value should look like.
+        expected: &'static str,
+    },
+}
+
+impl Error for PropertyError {}
+
+impl Display for PropertyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing { key } => write!(f, "\"{key}\" is required"),
+            Self::Bad {
+                key,
+                value,
+                expected,
+            } => write!(f, "\"{key}\" is \"{value}\", expected {expected}"),
+        }
+    }
+}
+
+/// Why an entity wasn't spawned.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SpawnError {
+    /// It has no `classname`.
+    NoClassname,
+    /// No class is registered with its `classname`.
+    UnknownClass(String),
+    /// A property its class needs is missing or bad.
+    Property {
+        /// The entity's class.
+        classname: String,
+        /// What's wrong.
+        error: PropertyError,
+    },
+}
+
+impl Error for SpawnError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Property { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl Display for SpawnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoClassname => f.write_str("entity has no classname"),
+            Self::UnknownClass(classname) => write!(f, "unknown entity class \"{classname}\""),
+            Self::Property { classname, error } => write!(f, "{classname}: {error}"),
+        }
+    }
+}
+
+/// Gives a newly spawned entity the components its class needs, from its properties.
+pub type SpawnFn = fn(&mut Entities, EntityId, &Properties) -> Result<(), PropertyError>;
+
+/// A kind of entity that can be spawned from a map.
+#[derive(Clone, Copy, Debug)]
+pub struct EntityClass {
+    /// The `classname` maps use for it.
+    pub classname: &'static str,
+    /// Keys it can't be spawned without.
+    pub required: &'static [&'static str],
+    /// Inputs other entities can send it, see [`io`](crate::io).
+    pub inputs: &'static [Input],
+    /// Gives the entity its components.
+    pub spawn: SpawnFn,
+}
+
+/// The entity classes maps can use, by classname.
+#[derive(Clone, Debug, Default)]
+pub struct ClassRegistry {
+    /// Every class, by classname.
+    classes: BTreeMap<&'static str, EntityClass>,
+}
+
+impl ClassRegistry {
+    /// Create a registry with no classes.
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Create a registry with the engine's own classes: [`INFO_PLAYER_START`], [`LIGHT`],
+    /// [`LOGIC_RELAY`], [`LOGIC_AUTO`], and the brush entities [`FUNC_DOOR`], [`FUNC_PLAT`]
+    /// and [`FUNC_ROTATING`].
+    pub fn with_builtins() -> Self {
+        let mut registry = Self::new();
+        registry.register(INFO_PLAYER_START);
+        registry.register(LIGHT);
+        registry.register(LOGIC_RELAY);
+        registry.register(LOGIC_AUTO);
+        registry.register(FUNC_DOOR);
+        registry.register(FUNC_PLAT);
+        registry.register(FUNC_ROTATING);
+        registry
+    }
+
+    /// Add `class`, returning the class it replaced, if there was one with the same name.
+    pub fn register(&mut self, class: EntityClass) -> Option<EntityClass> {
+        self.classes.insert(class.classname, class)
+    }
+    /// The class called `classname`, if there is one.
+    pub fn get(&self, classname: &str) -> Option<&EntityClass> {
+        self.classes.get(classname)
+    }
+    /// Every class, by classname.
+    pub fn iter(&self) -> impl Iterator<Item = &EntityClass> {
+        self.classes.values()
+    }
+
+    /// Spawn an entity from `properties` into `entities`, using the class its `classname` names.
+    ///
+    /// # Errors
+    /// Errors if there's no class for it, or its properties are missing or bad.
+    /// Nothing is spawned then.
+    pub fn spawn(
+        &self,
+        entities: &mut Entities,
+        properties: &Properties,
+    ) -> Result<EntityId, SpawnError> {
+        self.spawn_with_brushes(entities, properties, &[])
+    }
+    /// Spawn an entity made of `brushes`, which are in world space, like
+    /// [`ClassRegistry::spawn`].
+    ///
+    /// If there are any, the entity gets a [`Transform`] at its `origin`, or the centre of the
+    /// brushes if it has none, and a [`BrushModel`] of them around it, before its class's spawn
+    /// function runs. See [`brush_entity`](crate::brush_entity).
+    ///
+    /// # Errors
+    /// Errors if there's no class for it, or its properties are missing or bad.
+    /// Nothing is spawned then.
+    pub fn spawn_with_brushes(
+        &self,
+        entities: &mut Entities,
+        properties: &Properties,
+        brushes: &[PolyBrush],
+    ) -> Result<EntityId, SpawnError> {
+        let classname = properties.classname().ok_or(SpawnError::NoClassname)?;
+        let class = self
+            .get(classname)
+            .ok_or_else(|| SpawnError::UnknownClass(classname.to_owned()))?;
+        let property_error = |error| SpawnError::Property {
+            classname: classname.to_owned(),
+            error,
+        };
+        if let Some(key) = class.required.iter().find(|key| !properties.contains(key)) {
+            return Err(property_error(PropertyError::Missing {
+                key: (*key).to_owned(),
+            }));
+        }
+        let connections = properties
+            .parse_all::<Connection>("output")
+            .map_err(property_error)?;
+        let origin = match properties.parse::<Vec3>("origin").map_err(property_error)? {
+            Some(origin) => Some(origin),
+            None => brushes
+                .iter()
+                .filter_map(Brush::bounds)
+                .reduce(Aabb::union)
+                .map(|bounds| bounds.center()),
+        };
+
+        let id = entities.spawn();
+        entities.insert(id, Classname(classname.to_owned()));
+        if let Some(targetname) = properties.get("targetname") {
+            entities.insert(id, TargetName(targetname.to_owned()));
+        }
+        if !connections.is_empty() {
+            entities.insert(id, Outputs(connections));
+        }
+        if !class.inputs.is_empty() {
+            entities.insert(id, Inputs(class.inputs));
+        }
+        if !brushes.is_empty() {
+            let origin = origin.unwrap_or(glm::to_vec3(0.));
+            entities.insert(id, Transform::from_position(origin));
+            entities.insert(id, BrushModel::from_world_space(brushes, origin));
+        }
+        if let Err(error) = (class.spawn)(entities, id, properties) {
+            // don't leave half an entity behind
+            entities.despawn(id);
+            return Err(property_error(error));
+        }
+        Ok(id)
+    }
+}
+
+/// Read `origin` and `angles` as a transform.
+///
+/// # Errors
+/// Errors if either is set but isn't a vector.
+pub fn transform_from(properties: &Properties) -> Result<Transform, PropertyError> {
+    Ok(Transform {
+        position: properties.parse_or("origin", glm::to_vec3(0.))?,
+        rotation: properties.parse_or("angles", glm::to_vec3(0.))?,
+        ..Transform::default()
+    })
+}
+
+/// Where the player starts. Keys: `origin` (required), `angles`.
+pub const INFO_PLAYER_START: EntityClass = EntityClass {
+    classname: "info_player_start",
+    required: &["origin"],
+    inputs: &[],
+    spawn: |entities, id, properties| {
+        entities.insert(id, transform_from(properties)?);
+        Ok(())
+    },
+};
+
+/// A point light. Keys: `origin` (required), `color` (default white),
+/// `intensity` (default 1), `radius` (default 10), `start_off` (default 0).
+/// Inputs: `TurnOn`, `TurnOff`, `Toggle`.
+pub const LIGHT: EntityClass = EntityClass {
+    classname: "light",
+    required: &["origin"],
+    inputs: &[
+        Input {
+            name: "TurnOn",
+            handler: |entities, id, _| switch_light(entities, id, |_| true),
+        },
+        Input {
+            name: "TurnOff",
+            handler: |entities, id, _| switch_light(entities, id, |_| false),
+        },
+        Input {
+            name: "Toggle",
+            handler: |entities, id, _| switch_light(entities, id, |enabled| !enabled),
+        },
+    ],
+    spawn: |entities, id, properties| {
+        let Colour(colour) = properties.parse_or("color", Colour(glm::to_vec3(1.)))?;
+        let light = Light {
+            colour,
+            intensity: properties.parse_or("intensity", 1.)?,
+            radius: properties.parse_or("radius", 10.)?,
+            enabled: !properties.parse_or("start_off", false)?,
+        };
+        entities.insert(id, transform_from(properties)?);
+        entities.insert(id, light);
+        Ok(())
+    },
+};
+
+/// Set whether the light with `id` is on, from whether it was.
+fn switch_light(entities: &mut Entities, id: EntityId, switch: fn(bool) -> bool) {
+    if let Some(light) = entities.get_mut::<Light>(id) {
+        light.enabled = switch(light.enabled);
+    }
+}
+
+/// Passes `Trigger` on to whatever its `OnTrigger` output is wired to, so one input can fan
+/// out to several, with delays. No keys. Inputs: `Trigger`.
+pub const LOGIC_RELAY: EntityClass = EntityClass {
+    classname: "logic_relay",
+    required: &[],
+    inputs: &[Input {
+        name: "Trigger",
+        handler: |entities, id, _| entities.fire_output(id, "OnTrigger"),
+    }],
+    spawn: |_, _, _| Ok(()),
+};
+
+/// Fires its `OnMapSpawn` output on the map's first tick. No keys.
+pub const LOGIC_AUTO: EntityClass = EntityClass {
+    classname: "logic_auto",
+    required: &[],
+    inputs: &[],
+    spawn: |entities, id, _| {
+        entities.fire_output(id, "OnMapSpawn");
+        Ok(())
+    },
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn properties(pairs: &[(&str, &str)]) -> Properties {
+        pairs.iter().copied().collect()
+    }
+
+    #[test]
+    fn later_values_win() {
+        let properties = properties(&[("a", "1"), ("b", "2"), ("a", "3")]);
+        assert_eq!(properties.get("a"), Some("3"));
+        assert_eq!(properties.get("c"), None);
+        assert!(properties.contains("b"));
+        assert_eq!(properties.parse_all::<i32>("a"), Ok(vec![1, 3]));
+        assert_eq!(properties.iter().count(), 3);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let properties = properties(&[("n", " 1.5 "), ("bad", "one")]);
+        assert_eq!(properties.parse::<f32>("n"), Ok(Some(1.5)));
+        assert_eq!(properties.parse::<f32>("missing"), Ok(None));
+        assert_eq!(properties.parse_or("missing", 2.), Ok(2.));
+        assert_eq!(
+            properties.parse::<f32>("bad"),
+            Err(PropertyError::Bad {
+                key: "bad".to_owned(),
+                value: "one".to_owned(),
+                expected: f32::EXPECTED,
+            })
+        );
+        assert_eq!(
+            properties.require::<f32>("missing"),
+            Err(PropertyError::Missing {
+                key: "missing".to_owned()
+            })
+        );
+        assert!(properties.parse_all::<f32>("bad").is_err());
+        assert_eq!(
+            properties.parse::<f32>("bad").unwrap_err().to_string(),
+            "\"bad\" is \"one\", expected a number"
+        );
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(f32::parse_property("-2.5"), Some(-2.5));
+        assert_eq!(f32::parse_property("inf"), None);
+        assert_eq!(f32::parse_property("NaN"), None);
+        assert_eq!(i32::parse_property("7"), Some(7));
+        assert_eq!(i32::parse_property("7.5"), None);
+        assert_eq!(bool::parse_property("1"), Some(true));
+        assert_eq!(bool::parse_property("0"), Some(false));
+        assert_eq!(bool::parse_property("true"), None);
+        assert_eq!(Vec::<u32>::parse_property("0 4  5"), Some(vec![0, 4, 5]));
+        assert_eq!(Vec::<u32>::parse_property(""), Some(vec![]));
+        assert_eq!(Vec::<u32>::parse_property("1 -2"), None);
+    }
+
+    #[test]
+    fn vectors() {
+        assert_eq!(
+            Vec3::parse_property("0 16 -8"),
+            Some(glm::vec3(0., 16., -8.))
+        );
+        assert_eq!(
+            Vec3::parse_property(" 1\t2  3 "),
+            Some(glm::vec3(1., 2., 3.))
+        );
+        assert_eq!(Vec3::parse_property("1 2"), None);
+        assert_eq!(Vec3::parse_property("1 2 3 4"), None);
+        assert_eq!(Vec3::parse_property("1 x 3"), None);
+        assert_eq!(Vec3::parse_property("1 inf 3"), None);
+    }
+
+    #[test]
+    fn colours() {
+        assert_eq!(
+            Colour::parse_property("1 0.5 0"),
+            Some(Colour(glm::vec3(1., 0.5, 0.)))
+        );
+        // any channel over 1 means they're all out of 255
+        assert_eq!(
+            Colour::parse_property("255 51 0"),
+            Some(Colour(glm::vec3(1., 0.2, 0.)))
+        );
+        assert_eq!(
+            Colour::parse_property("1 1 2"),
+            Some(Colour(glm::vec3(1., 1., 2.) / 255.))
+        );
+        assert_eq!(Colour::parse_property("256 0 0"), None);
+        assert_eq!(Colour::parse_property("-1 0 0"), None);
+        assert_eq!(Colour::parse_property("1 1"), None);
+    }
+
+    #[test]
+    fn spawn_errors() {
+        let classes = ClassRegistry::with_builtins();
+        let mut entities = Entities::new();
+        assert_eq!(
+            classes.spawn(&mut entities, &properties(&[("origin", "0 0 0")])),
+            Err(SpawnError::NoClassname)
+        );
+        assert_eq!(
+            classes.spawn(&mut entities, &properties(&[("classname", "nope")])),
+            Err(SpawnError::UnknownClass("nope".to_owned()))
+        );
+        let missing = classes
+            .spawn(&mut entities, &properties(&[("classname", "light")]))
+            .unwrap_err();
+        assert_eq!(missing.to_string(), "light: \"origin\" is required");
+        assert!(missing.source().is_some());
+        // the spawn function fails after the entity was made, and it's removed again
+        let bad = properties(&[("classname", "light"), ("origin", "0 0 0"), ("color", "x")]);
+        assert!(classes.spawn(&mut entities, &bad).is_err());
+        let bad_output = properties(&[("classname", "logic_relay"), ("output", "OnTrigger")]);
+        assert!(classes.spawn(&mut entities, &bad_output).is_err());
+        assert!(entities.is_empty());
+    }
+
+    #[test]
+    fn spawn_lights() {
+        let classes = ClassRegistry::with_builtins();
+        let mut entities = Entities::new();
+        let id = classes
+            .spawn(
+                &mut entities,
+                &properties(&[
+                    ("classname", "light"),
+                    ("targetname", "lamp"),
+                    ("origin", "1 2 3"),
+                    ("color", "255 0 0"),
+                    ("start_off", "1"),
+                ]),
+            )
+            .unwrap();
+        assert_eq!(
+            entities.get::<Classname>(id),
+            Some(&Classname("light".to_owned()))
+        );
+        assert_eq!(
+            entities.get::<TargetName>(id),
+            Some(&TargetName("lamp".to_owned()))
+        );
+        assert_eq!(
+            entities.get::<Transform>(id).unwrap().position,
+            glm::vec3(1., 2., 3.)
+        );
+        assert_eq!(
+            entities.get::<Light>(id),
+            Some(&Light {
+                colour: glm::vec3(1., 0., 0.),
+                intensity: 1.,
+                radius: 10.,
+                enabled: false,
+            })
+        );
+        assert_eq!(entities.get::<Inputs>(id).unwrap().0.len(), 3);
+        assert_eq!(entities.get::<Outputs>(id), None);
+    }
+
+    #[test]
+    fn player_start_angles() {
+        let classes = ClassRegistry::with_builtins();
+        let mut entities = Entities::new();
+        let id = classes
+            .spawn(
+                &mut entities,
+                &properties(&[
+                    ("classname", "info_player_start"),
+                    ("origin", "0 0 3"),
+                    ("angles", "0 -90 0"),
+                ]),
+            )
+            .unwrap();
+        let transform = entities.get::<Transform>(id).unwrap();
+        assert_eq!(transform.rotation, glm::vec3(0., -90., 0.));
+        assert_eq!(transform.scale, glm::to_vec3(1.));
+    }
+
+    #[test]
+    fn registering_replaces() {
+        let mut classes = ClassRegistry::new();
+        assert!(classes.register(LOGIC_RELAY).is_none());
+        let replaced = classes.register(EntityClass {
+            required: &["x"],
+            ..LOGIC_RELAY
+        });
+        assert_eq!(replaced.unwrap().required.len(), 0);
+        assert_eq!(classes.get("logic_relay").unwrap().required, ["x"]);
+        assert_eq!(classes.iter().count(), 1);
+    }
+}
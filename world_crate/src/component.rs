@@ -0,0 +1,372 @@
+//! Exports [`Component`], the components entities are made of, and [`Components`],
+//! which stores them.
+//!
+//! Each component type has its own [`SparseSet`], so systems that only want transforms and
+//! velocities walk packed arrays of just those. Find entities with a set of components with
+//! [`Components::query`] and [`Components::query_mut`], see [`query`](crate::query).
+use std::any::TypeId;
+
+use glm::{Mat4, Vec3};
+
+use crate::{
+    bounds::Aabb,
+    brush_entity::{BrushModel, Mover, Rotator},
+    entity::EntityId,
+    io::{Inputs, Outputs},
+    query::{Fetch, QueryIter, ReadOnlyFetch},
+    storage::SparseSet,
+};
+
+/// A type entities can have one of. Every component type has a field in [`Components`].
+pub trait Component: Sized + 'static {
+    /// Where every entity's `Self` is kept.
+    fn storage(components: &Components) -> &SparseSet<Self>;
+    /// Where every entity's `Self` is kept.
+    fn storage_mut(components: &mut Components) -> &mut SparseSet<Self>;
+}
+
+/// Defines [`Components`], with a [`SparseSet`] field for each component type.
+macro_rules! components {
+    ($($component:ident => $field:ident),* $(,)?) => {
+        /// Every entity's components, one [`SparseSet`] per type.
+        #[derive(Default)]
+        pub struct Components {
+            /// Every entity that exists, whatever components it has.
+            pub(crate) living: SparseSet<()>,
+            $(
+                #[doc = concat!("Every entity's [`", stringify!($component), "`].")]
+                $field: SparseSet<$component>,
+            )*
+        }
+
+        impl Components {
+            /// Remove every component `id` has.
+            pub(crate) fn remove_all(&mut self, id: EntityId) {
+                $(self.$field.remove(id);)*
+            }
+        }
+
+        $(
+            impl Component for $component {
+                fn storage(components: &Components) -> &SparseSet<Self> {
+                    &components.$field
+                }
+                fn storage_mut(components: &mut Components) -> &mut SparseSet<Self> {
+                    &mut components.$field
+                }
+            }
+        )*
+    };
+}
+
+components! {
+    Transform => transforms,
+    Velocity => velocities,
+    Collider => colliders,
+    RenderMesh => meshes,
+    Health => healths,
+    Light => lights,
+    Classname => classnames,
+    TargetName => target_names,
+    Outputs => outputs,
+    Inputs => inputs,
+    BrushModel => brush_models,
+    Mover => movers,
+    Rotator => rotators,
+}
+
+impl Components {
+    /// Give the entity with `id` a `T`, replacing the one it had.
+    /// Does nothing if there's no entity with `id`.
+    pub fn insert<T: Component>(&mut self, id: EntityId, component: T) {
+        if self.living.contains(id) {
+            T::storage_mut(self).insert(id, component);
+        }
+    }
+    /// Take the entity with `id`'s `T` away, returning it.
+    pub fn remove<T: Component>(&mut self, id: EntityId) -> Option<T> {
+        T::storage_mut(self).remove(id)
+    }
+    /// Whether the entity with `id` has a `T`.
+    pub fn has<T: Component>(&self, id: EntityId) -> bool {
+        T::storage(self).contains(id)
+    }
+    /// The entity with `id`'s `T`, if it has one.
+    pub fn get<T: Component>(&self, id: EntityId) -> Option<&T> {
+        T::storage(self).get(id)
+    }
+    /// The entity with `id`'s `T`, if it has one.
+    pub fn get_mut<T: Component>(&mut self, id: EntityId) -> Option<&mut T> {
+        T::storage_mut(self).get_mut(id)
+    }
+    /// Every entity's `T`.
+    pub fn all<T: Component>(&self) -> &SparseSet<T> {
+        T::storage(self)
+    }
+
+    /// Every entity that has everything `Q` reads.
+    pub fn query<Q: ReadOnlyFetch>(&self) -> QueryIter<'_, Q> {
+        // SAFETY: read-only fetches only read through the pointer
+        unsafe { QueryIter::new(std::ptr::from_ref(self).cast_mut()) }
+    }
+    /// Every entity that has everything `Q` reads or writes.
+    ///
+    /// # Panics
+    /// Panics if `Q` writes a component type it also asks for some other way,
+    /// like `(&mut Transform, &Transform)`.
+    pub fn query_mut<Q: Fetch>(&mut self) -> QueryIter<'_, Q> {
+        let mut access = vec![];
+        Q::access(&mut access);
+        for (index, (type_id, name, writes)) in access.iter().enumerate() {
+            let clash = access[index + 1..]
+                .iter()
+                .any(|(other, _, other_writes)| other == type_id && (*writes || *other_writes));
+            assert!(!clash, "query asks for {name} twice, and writes it");
+        }
+        // SAFETY: every write is to a type nothing else in the query touches
+        unsafe { QueryIter::new(self) }
+    }
+}
+
+/// The type id and name of `T`, for [`Fetch::access`].
+pub(crate) fn type_key<T: 'static>() -> (TypeId, &'static str) {
+    (TypeId::of::<T>(), std::any::type_name::<T>())
+}
+
+/// The matrix that changes nothing, like the [`Transform::matrix`] of a default transform.
+#[rustfmt::skip]
+pub const IDENTITY: Mat4 = glm::mat4(
+    1., 0., 0., 0.,
+    0., 1., 0., 0.,
+    0., 0., 1., 0.,
+    0., 0., 0., 1.,
+);
+
+/// Where an entity is, which way it's facing, and how big it is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    /// Position in world space.
+    pub position: Vec3,
+    /// Pitch, yaw and roll, in degrees.
+    pub rotation: Vec3,
+    /// Size along each axis, 1 for unscaled.
+    pub scale: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            position: glm::to_vec3(0.),
+            rotation: glm::to_vec3(0.),
+            scale: glm::to_vec3(1.),
+        }
+    }
+}
+
+impl Transform {
+    /// An unrotated, unscaled transform at `position`.
+    pub fn from_position(position: Vec3) -> Self {
+        Self {
+            position,
+            ..Self::default()
+        }
+    }
+    /// Entity space to world space: scaled, then rolled around Z, pitched around X, and
+    /// turned around Y, then moved to the position.
+    pub fn matrix(&self) -> Mat4 {
+        let [pitch, yaw, roll] = [
+            self.rotation.x.to_radians(),
+            self.rotation.y.to_radians(),
+            self.rotation.z.to_radians(),
+        ];
+        let matrix = glm::ext::translate(&IDENTITY, self.position);
+        let matrix = glm::ext::rotate(&matrix, yaw, glm::vec3(0., 1., 0.));
+        let matrix = glm::ext::rotate(&matrix, pitch, glm::vec3(1., 0., 0.));
+        let matrix = glm::ext::rotate(&matrix, roll, glm::vec3(0., 0., 1.));
+        glm::ext::scale(&matrix, self.scale)
+    }
+    /// Move `point` from entity space to world space. See [`Transform::matrix`].
+    pub fn apply(&self, point: Vec3) -> Vec3 {
+        let moved = self.matrix() * glm::vec4(point.x, point.y, point.z, 1.);
+        glm::vec3(moved.x, moved.y, moved.z)
+    }
+}
+
+/// How fast an entity is moving. Added to its [`Transform`] every tick.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Velocity {
+    /// Units per second.
+    pub linear: Vec3,
+    /// Degrees per second, around each axis like [`Transform::rotation`].
+    pub angular: Vec3,
+}
+
+impl Default for Velocity {
+    fn default() -> Self {
+        Self {
+            linear: glm::to_vec3(0.),
+            angular: glm::to_vec3(0.),
+        }
+    }
+}
+
+/// The box an entity takes up, centred on its [`Transform::position`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Collider {
+    /// Half the size of the box along each axis.
+    pub half_extents: Vec3,
+    /// Whether it blocks movement, or things only pass through it.
+    pub solid: bool,
+}
+
+impl Collider {
+    /// The box in world space, for an entity at `transform`. Ignores rotation.
+    pub fn bounds(&self, transform: &Transform) -> Aabb {
+        let half_extents = self.half_extents * transform.scale;
+        Aabb::new(
+            transform.position - half_extents,
+            transform.position + half_extents,
+        )
+    }
+}
+
+/// A mesh drawn at the entity's [`Transform`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RenderMesh {
+    /// Which mesh. What the number means is up to the renderer.
+    pub mesh: u32,
+    /// Whether it's drawn.
+    pub visible: bool,
+}
+
+/// How much damage an entity can take.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Health {
+    /// Health left. At or below 0 is dead.
+    pub current: f32,
+    /// Health when fully healed.
+    pub max: f32,
+}
+
+impl Health {
+    /// Full health of `max`.
+    pub fn new(max: f32) -> Self {
+        Self { current: max, max }
+    }
+    /// Take `amount` away. Returns whether this killed it.
+    pub fn damage(&mut self, amount: f32) -> bool {
+        let was_alive = !self.is_dead();
+        self.current -= amount;
+        was_alive && self.is_dead()
+    }
+    /// Add `amount`, up to the max.
+    pub fn heal(&mut self, amount: f32) {
+        self.current = (self.current + amount).min(self.max);
+    }
+    /// Whether it's out of health.
+    pub fn is_dead(&self) -> bool {
+        self.current <= 0.
+    }
+}
+
+/// A point light at the entity's [`Transform::position`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Light {
+    /// Colour, from 0 to 1 per channel.
+    pub colour: Vec3,
+    /// Brightness the colour is multiplied by.
+    pub intensity: f32,
+    /// Distance at which it stops lighting anything.
+    pub radius: f32,
+    /// Whether it's lighting anything, or switched off.
+    pub enabled: bool,
+}
+
+/// The class an entity was spawned as, see [`class`](crate::class).
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Classname(pub String);
+
+/// The name other entities use to find this one, from its map `targetname`.
+/// Several entities can share one.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct TargetName(pub String);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!(glm::length(a - b) < 1e-5, "{a:?} != {b:?}");
+    }
+
+    #[test]
+    fn default_transforms_change_nothing() {
+        assert_eq!(Transform::default().matrix(), IDENTITY);
+        let point = glm::vec3(1., 2., 3.);
+        assert_eq!(Transform::default().apply(point), point);
+    }
+
+    #[test]
+    fn transforms_scale_then_turn_then_move() {
+        let transform = Transform {
+            position: glm::vec3(10., 0., 0.),
+            rotation: glm::vec3(0., 90., 0.),
+            scale: glm::to_vec3(2.),
+        };
+        // +X turns to -Z around Y
+        assert_near(transform.apply(glm::vec3(1., 0., 0.)), glm::vec3(10., 0., -2.));
+        assert_near(transform.apply(glm::vec3(0., 1., 0.)), glm::vec3(10., 2., 0.));
+        assert_near(transform.apply(glm::to_vec3(0.)), transform.position);
+    }
+
+    #[test]
+    fn rotation_axes() {
+        let turned = |rotation| Transform {
+            rotation,
+            ..Transform::default()
+        };
+        // pitch turns around X
+        assert_near(
+            turned(glm::vec3(90., 0., 0.)).apply(glm::vec3(0., 1., 0.)),
+            glm::vec3(0., 0., 1.),
+        );
+        // roll turns around Z
+        assert_near(
+            turned(glm::vec3(0., 0., 90.)).apply(glm::vec3(1., 0., 0.)),
+            glm::vec3(0., 1., 0.),
+        );
+        // roll comes before yaw
+        assert_near(
+            turned(glm::vec3(0., 90., 90.)).apply(glm::vec3(1., 0., 0.)),
+            glm::vec3(0., 1., 0.),
+        );
+    }
+
+    #[test]
+    fn collider_bounds() {
+        let collider = Collider {
+            half_extents: glm::vec3(1., 2., 1.),
+            solid: true,
+        };
+        let transform = Transform {
+            scale: glm::vec3(2., 1., 1.),
+            ..Transform::from_position(glm::vec3(0., 5., 0.))
+        };
+        assert_eq!(
+            collider.bounds(&transform),
+            Aabb::new(glm::vec3(-2., 3., -1.), glm::vec3(2., 7., 1.))
+        );
+    }
+
+    #[test]
+    fn health() {
+        let mut health = Health::new(10.);
+        assert!(!health.damage(4.));
+        health.heal(100.);
+        assert_eq!(health.current, 10.);
+        assert!(health.damage(10.));
+        assert!(health.is_dead());
+        // only the hit that kills counts
+        assert!(!health.damage(1.));
+    }
+}
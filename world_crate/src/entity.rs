@@ -1 +1,461 @@
-pub trait Entity {}
+//! Exports [`EntityId`], [`Script`], and [`Entities`], which owns entities for a world.
+//!
+//! An entity is an [`EntityId`] and the [components](crate::component) it has, plus a
+//! [`Script`] if it does anything on its own. Ids are a slot index and the generation of
+//! the slot. Despawning bumps the generation, so an id kept after its entity is gone finds
+//! nothing, even once the slot is reused.
+//!
+//! While scripts are ticking, [`Commands`] spawns, despawns, and adds components. Spawned
+//! entities get their id straight away, but every change waits until every script has ticked.
+//!
+//! Entities also send each other inputs through their outputs, see [`io`](crate::io).
+use std::fmt::{self, Display};
+
+use crate::{
+    component::{Component, Components},
+    io::EventQueue,
+    query::{Fetch, QueryIter, ReadOnlyFetch},
+    storage::SparseSet,
+};
+
+/// What an entity does on its own, run every tick.
+pub trait Script {
+    /// Advance the entity with `id` by `dt` seconds.
+    /// `components` are every entity's, so it can look at others, and change them.
+    fn tick(&mut self, id: EntityId, components: &mut Components, commands: &mut Commands, dt: f64);
+}
+
+/// A handle to an entity in [`Entities`]. Stays valid until the entity is despawned.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct EntityId {
+    /// The entity's slot.
+    index: u32,
+    /// How many times the slot had been freed when the entity was spawned.
+    generation: u32,
+}
+
+impl EntityId {
+    /// The entity's slot. Slots are reused, so this alone doesn't identify an entity.
+    pub fn index(self) -> u32 {
+        self.index
+    }
+    /// How many times the slot had been freed when the entity was spawned.
+    pub fn generation(self) -> u32 {
+        self.generation
+    }
+}
+
+impl Display for EntityId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}v{}", self.index, self.generation)
+    }
+}
+
+/// Hands out [`EntityId`]s, and knows which are still current.
+#[derive(Clone, Debug, Default)]
+struct IdAllocator {
+    /// Current generation of each slot.
+    generations: Vec<u32>,
+    /// Slots that are free to reuse.
+    free: Vec<u32>,
+}
+
+impl IdAllocator {
+    /// An id for a new entity, reusing a free slot if there is one.
+    fn allocate(&mut self) -> EntityId {
+        if let Some(index) = self.free.pop() {
+            return EntityId {
+                index,
+                generation: self.generations[index as usize],
+            };
+        }
+        let index = u32::try_from(self.generations.len()).expect("too many entities");
+        self.generations.push(0);
+        EntityId {
+            index,
+            generation: 0,
+        }
+    }
+    /// Free `id`'s slot. Returns false if `id` was already freed.
+    fn free(&mut self, id: EntityId) -> bool {
+        if !self.is_current(id) {
+            return false;
+        }
+        let generation = &mut self.generations[id.index as usize];
+        *generation = generation.wrapping_add(1);
+        self.free.push(id.index);
+        true
+    }
+    /// Whether `id` hasn't been freed.
+    fn is_current(&self, id: EntityId) -> bool {
+        self.generations.get(id.index as usize) == Some(&id.generation)
+    }
+}
+
+/// A change to [`Entities`], waiting for the scripts to finish ticking.
+type Command = Box<dyn FnOnce(&mut Entities)>;
+
+/// Spawns, despawns, and adds components while scripts are ticking.
+/// Changes are made in order, once every script has ticked.
+pub struct Commands<'a> {
+    /// Allocates ids for spawned entities.
+    ids: &'a mut IdAllocator,
+    /// Changes to make.
+    queued: Vec<Command>,
+}
+
+impl Commands<'_> {
+    /// Add an entity with no components after this tick. Returns its id, which can be used
+    /// with [`Commands::insert`] straight away.
+    pub fn spawn(&mut self) -> EntityId {
+        let id = self.ids.allocate();
+        self.add(move |entities| {
+            entities.components.living.insert(id, ());
+        });
+        id
+    }
+    /// Remove the entity with `id` after this tick. Does nothing if it's already gone.
+    pub fn despawn(&mut self, id: EntityId) {
+        self.add(move |entities| {
+            entities.despawn(id);
+        });
+    }
+    /// Give the entity with `id` a `T` after this tick.
+    pub fn insert<T: Component>(&mut self, id: EntityId, component: T) {
+        self.add(move |entities| entities.insert(id, component));
+    }
+    /// Give the entity with `id` a script after this tick.
+    pub fn set_script(&mut self, id: EntityId, script: Box<dyn Script>) {
+        self.add(move |entities| entities.set_script(id, script));
+    }
+    /// Fire the entity with `id`'s output called `output` after this tick.
+    /// See [`Entities::fire_output`].
+    pub fn fire_output(&mut self, id: EntityId, output: &'static str) {
+        self.add(move |entities| entities.fire_output(id, output));
+    }
+    /// Make any other change after this tick.
+    pub fn add(&mut self, command: impl FnOnce(&mut Entities) + 'static) {
+        self.queued.push(Box::new(command));
+    }
+}
+
+/// Owns entities: hands out their ids, and stores their components and scripts.
+#[derive(Default)]
+pub struct Entities {
+    /// Allocates ids, and knows which are current.
+    ids: IdAllocator,
+    /// Every entity's components.
+    components: Components,
+    /// Every entity's script.
+    scripts: SparseSet<Box<dyn Script>>,
+    /// Inputs waiting to be sent between entities.
+    events: EventQueue,
+}
+
+impl Entities {
+    /// Create an empty set of entities.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an entity with no components, and return its id.
+    pub fn spawn(&mut self) -> EntityId {
+        let id = self.ids.allocate();
+        self.components.living.insert(id, ());
+        id
+    }
+    /// Remove the entity with `id`, and everything it has.
+    /// Returns false if it was already gone.
+    pub fn despawn(&mut self, id: EntityId) -> bool {
+        if !self.contains(id) {
+            return false;
+        }
+        self.components.living.remove(id);
+        self.components.remove_all(id);
+        self.scripts.remove(id);
+        self.ids.free(id)
+    }
+    /// Whether the entity with `id` is still here.
+    pub fn contains(&self, id: EntityId) -> bool {
+        self.components.living.contains(id)
+    }
+
+    /// Give the entity with `id` a `T`, replacing the one it had.
+    /// Does nothing if there's no entity with `id`.
+    pub fn insert<T: Component>(&mut self, id: EntityId, component: T) {
+        self.components.insert(id, component);
+    }
+    /// Take the entity with `id`'s `T` away, returning it.
+    pub fn remove<T: Component>(&mut self, id: EntityId) -> Option<T> {
+        self.components.remove(id)
+    }
+    /// The entity with `id`'s `T`, if it has one.
+    pub fn get<T: Component>(&self, id: EntityId) -> Option<&T> {
+        self.components.get(id)
+    }
+    /// The entity with `id`'s `T`, if it has one.
+    pub fn get_mut<T: Component>(&mut self, id: EntityId) -> Option<&mut T> {
+        self.components.get_mut(id)
+    }
+    /// Give the entity with `id` a script, replacing the one it had.
+    /// Does nothing if there's no entity with `id`.
+    pub fn set_script(&mut self, id: EntityId, script: Box<dyn Script>) {
+        if self.contains(id) {
+            self.scripts.insert(id, script);
+        }
+    }
+    /// Take the entity with `id`'s script away, returning it.
+    pub fn remove_script(&mut self, id: EntityId) -> Option<Box<dyn Script>> {
+        self.scripts.remove(id)
+    }
+
+    /// Every entity's components.
+    pub fn components(&self) -> &Components {
+        &self.components
+    }
+    /// Every entity's components.
+    pub fn components_mut(&mut self) -> &mut Components {
+        &mut self.components
+    }
+    /// Every entity that has everything `Q` reads. See [`Components::query`].
+    pub fn query<Q: ReadOnlyFetch>(&self) -> QueryIter<'_, Q> {
+        self.components.query()
+    }
+    /// Every entity that has everything `Q` reads or writes. See [`Components::query_mut`].
+    pub fn query_mut<Q: Fetch>(&mut self) -> QueryIter<'_, Q> {
+        self.components.query_mut()
+    }
+
+    /// Inputs waiting to be sent, and the ones that were.
+    pub fn events(&self) -> &EventQueue {
+        &self.events
+    }
+    /// Inputs waiting to be sent, and the ones that were.
+    pub(crate) fn events_mut(&mut self) -> &mut EventQueue {
+        &mut self.events
+    }
+
+    /// Number of entities.
+    pub fn len(&self) -> usize {
+        self.components.living.len()
+    }
+    /// Whether there are no entities.
+    pub fn is_empty(&self) -> bool {
+        self.components.living.is_empty()
+    }
+    /// The id of every entity, in no particular order.
+    pub fn ids(&self) -> &[EntityId] {
+        self.components.living.ids()
+    }
+
+    /// Tick every script by `dt` seconds, make the changes they asked for, then send
+    /// every input that's due. Entities spawned this tick first tick next time.
+    pub fn tick(&mut self, dt: f64) {
+        let mut commands = Commands {
+            ids: &mut self.ids,
+            queued: vec![],
+        };
+        for (id, script) in self.scripts.iter_mut() {
+            script.tick(id, &mut self.components, &mut commands, dt);
+        }
+        let queued = commands.queued;
+        for command in queued {
+            command(self);
+        }
+        self.deliver_events(dt);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+    use crate::component::Health;
+
+    /// Runs a closure each tick.
+    struct FnScript<F>(F);
+
+    impl<F: FnMut(EntityId, &mut Components, &mut Commands)> Script for FnScript<F> {
+        fn tick(&mut self, id: EntityId, components: &mut Components, commands: &mut Commands, _: f64) {
+            (self.0)(id, components, commands);
+        }
+    }
+
+    fn script(f: impl FnMut(EntityId, &mut Components, &mut Commands) + 'static) -> Box<dyn Script> {
+        Box::new(FnScript(f))
+    }
+
+    #[test]
+    fn allocator_reuses_slots_with_new_generations() {
+        let mut ids = IdAllocator::default();
+        let a = ids.allocate();
+        let b = ids.allocate();
+        assert_eq!((a.index(), a.generation()), (0, 0));
+        assert_eq!((b.index(), b.generation()), (1, 0));
+
+        assert!(ids.free(a));
+        assert!(!ids.is_current(a));
+        assert!(!ids.free(a));
+        let c = ids.allocate();
+        assert_eq!((c.index(), c.generation()), (0, 1));
+        assert!(ids.is_current(c));
+        assert!(!ids.is_current(a));
+
+        // a freed slot is only reused once
+        let d = ids.allocate();
+        assert_eq!((d.index(), d.generation()), (2, 0));
+    }
+
+    #[test]
+    fn allocator_doesnt_know_other_ids() {
+        let mut other = IdAllocator::default();
+        other.allocate();
+        let foreign = other.allocate();
+        let ids = IdAllocator::default();
+        assert!(!ids.is_current(foreign));
+    }
+
+    #[test]
+    fn allocator_generations_wrap() {
+        let mut ids = IdAllocator::default();
+        ids.allocate();
+        ids.generations[0] = u32::MAX;
+        let old = EntityId {
+            index: 0,
+            generation: u32::MAX,
+        };
+        assert!(ids.free(old));
+        let id = ids.allocate();
+        assert_eq!((id.index(), id.generation()), (0, 0));
+    }
+
+    #[test]
+    fn despawned_ids_stay_stale() {
+        let mut entities = Entities::new();
+        let a = entities.spawn();
+        assert!(entities.despawn(a));
+        assert!(!entities.despawn(a));
+        let b = entities.spawn();
+        assert_eq!(a.index(), b.index());
+        assert!(!entities.contains(a));
+        assert!(entities.contains(b));
+        assert_eq!(entities.len(), 1);
+        assert_eq!(b.to_string(), "#0v1");
+    }
+
+    #[test]
+    fn commands_wait_for_every_script() {
+        let mut entities = Entities::new();
+        let spawner = entities.spawn();
+        let watcher = entities.spawn();
+        let spawned = Rc::new(RefCell::new(vec![]));
+        let seen = spawned.clone();
+        entities.set_script(
+            spawner,
+            script(move |_, components, commands| {
+                let id = commands.spawn();
+                commands.insert(id, Health::new(5.));
+                // not there until every script has ticked
+                assert!(!components.living.contains(id));
+                seen.borrow_mut().push(id);
+            }),
+        );
+        let counted = Rc::new(RefCell::new(0));
+        let count = counted.clone();
+        entities.set_script(
+            watcher,
+            script(move |_, components, _| {
+                *count.borrow_mut() = components.all::<Health>().len();
+            }),
+        );
+
+        entities.tick(0.1);
+        let first = spawned.borrow()[0];
+        assert!(entities.contains(first));
+        assert_eq!(entities.get::<Health>(first), Some(&Health::new(5.)));
+        assert_eq!(*counted.borrow(), 0);
+
+        entities.tick(0.1);
+        assert_eq!(entities.len(), 4);
+        assert_eq!(*counted.borrow(), 1);
+        assert_ne!(spawned.borrow()[0], spawned.borrow()[1]);
+    }
+
+    #[test]
+    fn commands_run_in_order() {
+        let mut entities = Entities::new();
+        let a = entities.spawn();
+        let b = entities.spawn();
+        let order = Rc::new(RefCell::new(vec![]));
+        let log = order.clone();
+        entities.set_script(
+            a,
+            script(move |_, _, commands| {
+                // inserted then despawned, so gone
+                commands.insert(b, Health::new(1.));
+                commands.despawn(b);
+                // despawned then inserted, so the insert finds nothing
+                let c = commands.spawn();
+                commands.despawn(c);
+                commands.insert(c, Health::new(1.));
+                let log = log.clone();
+                commands.add(move |entities| {
+                    log.borrow_mut().push((entities.contains(b), entities.contains(c)));
+                });
+            }),
+        );
+        entities.tick(0.);
+        assert_eq!(*order.borrow(), [(false, false)]);
+        assert!(!entities.contains(b));
+        assert_eq!(entities.components().all::<Health>().len(), 0);
+        assert_eq!(entities.ids(), [a]);
+    }
+
+    #[test]
+    fn scripts_can_despawn_themselves() {
+        let mut entities = Entities::new();
+        let a = entities.spawn();
+        entities.insert(a, Health::new(1.));
+        entities.set_script(
+            a,
+            script(|id, _, commands| {
+                commands.despawn(id);
+                commands.despawn(id);
+            }),
+        );
+        entities.tick(0.);
+        assert!(entities.is_empty());
+        assert!(entities.remove_script(a).is_none());
+        assert_eq!(entities.get::<Health>(a), None);
+        // the slot is reused once
+        let b = entities.spawn();
+        assert_eq!((b.index(), b.generation()), (a.index(), 1));
+    }
+
+    #[test]
+    fn spawned_scripts_tick_next_time() {
+        let mut entities = Entities::new();
+        let parent = entities.spawn();
+        let ticks = Rc::new(RefCell::new(vec![]));
+        let log = ticks.clone();
+        entities.set_script(
+            parent,
+            script(move |id, _, commands| {
+                log.borrow_mut().push(id);
+                if log.borrow().len() == 1 {
+                    let child = commands.spawn();
+                    let log = log.clone();
+                    commands.set_script(
+                        child,
+                        script(move |id, _, _| log.borrow_mut().push(id)),
+                    );
+                }
+            }),
+        );
+        entities.tick(0.);
+        assert_eq!(*ticks.borrow(), [parent]);
+        entities.tick(0.);
+        assert_eq!(ticks.borrow().len(), 3);
+    }
+}
@@ -0,0 +1,528 @@
+//! Exports [`Connection`], which wires one entity's output to other entities' inputs,
+//! and [`EventQueue`], which delivers them.
+//!
+//! An entity fires an *output*, like `OnTrigger`, when something happens to it. Each of its
+//! [`Outputs`] connected to that output then sends an *input*, like `Open`, to every entity
+//! whose [`TargetName`] matches, after the connection's delay. What an input does is up to the
+//! receiver's class, see [`Inputs`]. Every entity also takes `Kill`, which despawns it.
+//!
+//! Maps wire them with `output` keys, as many as an entity needs:
+//!
+//! ```text
+//! "output" "OnTrigger -> door1.Open, delay 0.5"
+//! "output" "OnTrigger -> alarm.TurnOn, once"
+//! ```
+//!
+//! Events are delivered while the world ticks, so delays are in simulated time and stop
+//! while the simulation is paused. The last [`TRACE_CAPACITY`] deliveries are kept, see
+//! [`EventQueue::trace`].
+use std::{
+    collections::VecDeque,
+    fmt::{self, Display},
+};
+
+use crate::{
+    class::PropertyValue,
+    component::TargetName,
+    entity::{Entities, EntityId},
+};
+
+/// Most events delivered in one tick. The rest wait for the next, so entities that
+/// trigger each other with no delay can't hang the tick.
+pub const MAX_EVENTS_PER_TICK: usize = 1024;
+/// Number of deliveries kept in [`EventQueue::trace`].
+pub const TRACE_CAPACITY: usize = 256;
+/// The input every entity takes, which despawns it.
+pub const KILL_INPUT: &str = "Kill";
+
+/// One of an entity's outputs, wired to an input of the entities called `target`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Connection {
+    /// The output that sends it, like `OnTrigger`.
+    pub output: String,
+    /// The [`TargetName`] of the entities it's sent to.
+    pub target: String,
+    /// The input it's sent to, like `Open`.
+    pub input: String,
+    /// Seconds between the output firing and the input being sent.
+    pub delay: f64,
+    /// Whether it's removed once it has fired.
+    pub once: bool,
+}
+
+impl PropertyValue for Connection {
+    const EXPECTED: &'static str = "an output, like \"OnTrigger -> door1.Open, delay 0.5\"";
+    fn parse_property(text: &str) -> Option<Self> {
+        let (output, rest) = text.split_once("->")?;
+        let mut options = rest.split(',');
+        let (target, input) = options.next()?.trim().rsplit_once('.')?;
+        let mut connection = Connection {
+            output: output.trim().to_owned(),
+            target: target.to_owned(),
+            input: input.to_owned(),
+            delay: 0.,
+            once: false,
+        };
+        let is_name = |name: &str| !name.is_empty() && !name.contains(char::is_whitespace);
+        if ![&connection.output, &connection.target, &connection.input]
+            .iter()
+            .all(|name| is_name(name))
+        {
+            return None;
+        }
+        for option in options {
+            match option.split_whitespace().collect::<Vec<_>>()[..] {
+                ["delay", delay] => {
+                    connection.delay = delay
+                        .parse()
+                        .ok()
+                        .filter(|delay: &f64| delay.is_finite() && *delay >= 0.)?;
+                }
+                ["once"] => connection.once = true,
+                _ => return None,
+            }
+        }
+        Some(connection)
+    }
+}
+
+impl Display for Connection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} -> {}.{}", self.output, self.target, self.input)?;
+        if self.delay > 0. {
+            write!(f, ", delay {}", self.delay)?;
+        }
+        if self.once {
+            f.write_str(", once")?;
+        }
+        Ok(())
+    }
+}
+
+/// An entity's connections, from its map `output` keys.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Outputs(pub Vec<Connection>);
+
+/// What an input does to the entity with the id that received it.
+/// The caller is the entity whose output sent it, if one did.
+pub type InputFn = fn(entities: &mut Entities, receiver: EntityId, caller: Option<EntityId>);
+
+/// An input an entity class takes.
+#[derive(Clone, Copy, Debug)]
+pub struct Input {
+    /// What connections call it, like `Open`.
+    pub name: &'static str,
+    /// What it does.
+    pub handler: InputFn,
+}
+
+/// The inputs an entity takes, from its class. Every entity also takes [`KILL_INPUT`].
+#[derive(Clone, Copy, Debug)]
+pub struct Inputs(pub &'static [Input]);
+
+impl Inputs {
+    /// The input called `name`, if there is one.
+    pub fn get(&self, name: &str) -> Option<&Input> {
+        self.0.iter().find(|input| input.name == name)
+    }
+}
+
+/// An input waiting to be sent.
+#[derive(Clone, Debug)]
+pub struct PendingEvent {
+    /// Seconds its connection said to wait after it was fired.
+    pub delay: f64,
+    /// The queue's time it's sent at.
+    pub fire_at: f64,
+    /// Counts up with each event, so events due at the same time go in the order they fired.
+    seq: u64,
+    /// The entity that fired it, if one did.
+    pub caller: Option<EntityId>,
+    /// The output that fired it, if one did.
+    pub output: Option<String>,
+    /// Name of the entities it's sent to.
+    pub target: String,
+    /// The input it's sent to.
+    pub input: String,
+}
+
+/// An input that was sent, kept for debugging.
+#[derive(Clone, Debug)]
+pub struct TraceEntry {
+    /// Counts up with each delivery, and is never reused.
+    pub seq: u64,
+    /// The queue's time it was sent at.
+    pub time: f64,
+    /// Seconds its connection said to wait after it was fired.
+    pub delay: f64,
+    /// The entity that fired it, if one did.
+    pub caller: Option<EntityId>,
+    /// The output that fired it, if one did.
+    pub output: Option<String>,
+    /// Name of the entities it was sent to.
+    pub target: String,
+    /// The input it was sent to.
+    pub input: String,
+    /// How many entities called `target` took `input`. 0 usually means a typo in the map.
+    pub receivers: usize,
+}
+
+/// Inputs waiting to be sent, and a trace of the ones that were.
+#[derive(Clone, Debug, Default)]
+pub struct EventQueue {
+    /// Seconds of simulation so far.
+    time: f64,
+    /// `seq` of the next event.
+    next_seq: u64,
+    /// `seq` of the next trace entry.
+    next_trace_seq: u64,
+    /// Events waiting to be sent, in no particular order.
+    pending: Vec<PendingEvent>,
+    /// The last [`TRACE_CAPACITY`] deliveries, oldest first.
+    trace: VecDeque<TraceEntry>,
+}
+
+impl EventQueue {
+    /// Seconds of simulation so far, which delays count in.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+    /// Events waiting to be sent, in no particular order.
+    pub fn pending(&self) -> &[PendingEvent] {
+        &self.pending
+    }
+    /// The last [`TRACE_CAPACITY`] inputs that were sent, oldest first.
+    pub fn trace(&self) -> &VecDeque<TraceEntry> {
+        &self.trace
+    }
+
+    /// Queue `input` to be sent to the entities called `target` in `delay` seconds.
+    pub(crate) fn push(
+        &mut self,
+        caller: Option<EntityId>,
+        output: Option<String>,
+        target: String,
+        input: String,
+        delay: f64,
+    ) {
+        self.pending.push(PendingEvent {
+            delay,
+            fire_at: self.time + delay,
+            seq: self.next_seq,
+            caller,
+            output,
+            target,
+            input,
+        });
+        self.next_seq += 1;
+    }
+    /// Take the earliest event that's due, if there is one.
+    fn pop_due(&mut self) -> Option<PendingEvent> {
+        let (index, _) = self
+            .pending
+            .iter()
+            .enumerate()
+            .filter(|(_, event)| event.fire_at <= self.time)
+            .min_by(|(_, a), (_, b)| a.fire_at.total_cmp(&b.fire_at).then(a.seq.cmp(&b.seq)))?;
+        Some(self.pending.swap_remove(index))
+    }
+    /// Remember that `event` was sent to `receivers` entities.
+    fn record(&mut self, event: PendingEvent, receivers: usize) {
+        if self.trace.len() == TRACE_CAPACITY {
+            self.trace.pop_front();
+        }
+        self.trace.push_back(TraceEntry {
+            seq: self.next_trace_seq,
+            time: self.time,
+            delay: event.delay,
+            caller: event.caller,
+            output: event.output,
+            target: event.target,
+            input: event.input,
+            receivers,
+        });
+        self.next_trace_seq += 1;
+    }
+}
+
+impl Entities {
+    /// Fire the entity with `caller`'s output called `output`, queueing an input for each of
+    /// its connections to it.
+    pub fn fire_output(&mut self, caller: EntityId, output: &str) {
+        let Some(Outputs(connections)) = self.get_mut::<Outputs>(caller) else {
+            return;
+        };
+        let fired: Vec<Connection> = connections
+            .iter()
+            .filter(|connection| connection.output == output)
+            .cloned()
+            .collect();
+        connections.retain(|connection| !(connection.once && connection.output == output));
+        for connection in fired {
+            self.events_mut().push(
+                Some(caller),
+                Some(connection.output),
+                connection.target,
+                connection.input,
+                connection.delay,
+            );
+        }
+    }
+    /// Queue `input` to be sent to every entity called `target` in `delay` seconds,
+    /// without an output firing it.
+    pub fn fire_input(&mut self, target: &str, input: &str, delay: f64) {
+        self.events_mut()
+            .push(None, None, target.to_owned(), input.to_owned(), delay);
+    }
+    /// The entities called `target`.
+    pub fn find_by_name(&self, target: &str) -> Vec<EntityId> {
+        self.components()
+            .all::<TargetName>()
+            .iter()
+            .filter(|(_, name)| name.0 == target)
+            .map(|(id, _)| id)
+            .collect()
+    }
+
+    /// Move the event queue on by `dt` seconds, and send every input that's due.
+    pub(crate) fn deliver_events(&mut self, dt: f64) {
+        self.events_mut().time += dt;
+        for _ in 0..MAX_EVENTS_PER_TICK {
+            let Some(event) = self.events_mut().pop_due() else {
+                break;
+            };
+            let mut receivers = 0;
+            for receiver in self.find_by_name(&event.target) {
+                if self.send_input(receiver, &event.input, event.caller) {
+                    receivers += 1;
+                }
+            }
+            self.events_mut().record(event, receivers);
+        }
+    }
+    /// Send `input` to `receiver`. Returns whether it takes that input.
+    fn send_input(&mut self, receiver: EntityId, input: &str, caller: Option<EntityId>) -> bool {
+        if input == KILL_INPUT {
+            return self.despawn(receiver);
+        }
+        let Some(handler) = self
+            .get::<Inputs>(receiver)
+            .and_then(|inputs| inputs.get(input))
+            .map(|input| input.handler)
+        else {
+            return false;
+        };
+        handler(self, receiver, caller);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        class::{ClassRegistry, Properties},
+        component::Light,
+    };
+
+    fn connection(text: &str) -> Option<Connection> {
+        Connection::parse_property(text)
+    }
+
+    /// Spawn a builtin entity from `pairs`.
+    fn spawn(entities: &mut Entities, pairs: &[(&str, &str)]) -> EntityId {
+        let properties: Properties = pairs.iter().copied().collect();
+        ClassRegistry::with_builtins()
+            .spawn(entities, &properties)
+            .unwrap()
+    }
+
+    fn lamp(entities: &mut Entities, name: &str) -> EntityId {
+        spawn(
+            entities,
+            &[("classname", "light"), ("targetname", name), ("origin", "0 0 0")],
+        )
+    }
+
+    fn is_on(entities: &Entities, id: EntityId) -> bool {
+        entities.get::<Light>(id).unwrap().enabled
+    }
+
+    /// `(target, input)` of each delivery, oldest first.
+    fn delivered(entities: &Entities) -> Vec<(String, String)> {
+        entities
+            .events()
+            .trace()
+            .iter()
+            .map(|entry| (entry.target.clone(), entry.input.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn parse_connections() {
+        assert_eq!(
+            connection("OnTrigger -> door1.Open"),
+            Some(Connection {
+                output: "OnTrigger".to_owned(),
+                target: "door1".to_owned(),
+                input: "Open".to_owned(),
+                delay: 0.,
+                once: false,
+            })
+        );
+        let with_options = connection(" OnTrigger->door1.Open ,  once, delay 0.5 ").unwrap();
+        assert_eq!(with_options.delay, 0.5);
+        assert!(with_options.once);
+        assert_eq!(
+            with_options.to_string(),
+            "OnTrigger -> door1.Open, delay 0.5, once"
+        );
+        // the input is after the last dot
+        let dotted = connection("OnTrigger -> level.door.Open").unwrap();
+        assert_eq!((&*dotted.target, &*dotted.input), ("level.door", "Open"));
+    }
+
+    #[test]
+    fn parse_bad_connections() {
+        for text in [
+            "OnTrigger",
+            "OnTrigger -> door1",
+            "OnTrigger -> .Open",
+            "OnTrigger -> door1.",
+            "-> door1.Open",
+            "On Trigger -> door1.Open",
+            "OnTrigger -> door 1.Open",
+            "OnTrigger -> door1.Open, delay",
+            "OnTrigger -> door1.Open, delay -1",
+            "OnTrigger -> door1.Open, delay inf",
+            "OnTrigger -> door1.Open, delay NaN",
+            "OnTrigger -> door1.Open, delay 1 2",
+            "OnTrigger -> door1.Open, twice",
+            "OnTrigger -> door1.Open,",
+        ] {
+            assert_eq!(connection(text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn delays_count_in_ticks() {
+        let mut entities = Entities::new();
+        let lamp = lamp(&mut entities, "lamp");
+        entities.fire_input("lamp", "TurnOff", 0.5);
+        entities.tick(0.25);
+        assert!(is_on(&entities, lamp));
+        assert_eq!(entities.events().pending().len(), 1);
+        entities.tick(0.25);
+        assert!(!is_on(&entities, lamp));
+        assert!(entities.events().pending().is_empty());
+        let entry = &entities.events().trace()[0];
+        assert_eq!((entry.time, entry.delay, entry.receivers), (0.5, 0.5, 1));
+    }
+
+    #[test]
+    fn same_time_events_go_in_fired_order() {
+        let mut entities = Entities::new();
+        let lamp = lamp(&mut entities, "lamp");
+        entities.fire_input("lamp", "TurnOff", 1.);
+        entities.fire_input("lamp", "TurnOn", 1.);
+        entities.fire_input("lamp", "Toggle", 0.5);
+        entities.tick(1.);
+        assert_eq!(
+            delivered(&entities),
+            [
+                ("lamp".to_owned(), "Toggle".to_owned()),
+                ("lamp".to_owned(), "TurnOff".to_owned()),
+                ("lamp".to_owned(), "TurnOn".to_owned()),
+            ]
+        );
+        assert!(is_on(&entities, lamp));
+        let seqs: Vec<_> = entities.events().trace().iter().map(|entry| entry.seq).collect();
+        assert_eq!(seqs, [0, 1, 2]);
+    }
+
+    #[test]
+    fn outputs_fan_out_and_once_connections_go() {
+        let mut entities = Entities::new();
+        let a = lamp(&mut entities, "a");
+        let b = lamp(&mut entities, "b");
+        let relay = spawn(
+            &mut entities,
+            &[
+                ("classname", "logic_relay"),
+                ("targetname", "relay"),
+                ("output", "OnTrigger -> a.Toggle"),
+                ("output", "OnTrigger -> b.Toggle, once"),
+                ("output", "OnOther -> b.Toggle, once"),
+            ],
+        );
+        entities.fire_input("relay", "Trigger", 0.);
+        entities.tick(0.);
+        assert!(!is_on(&entities, a));
+        assert!(!is_on(&entities, b));
+        assert_eq!(entities.get::<Outputs>(relay).unwrap().0.len(), 2);
+
+        entities.fire_input("relay", "Trigger", 0.);
+        entities.tick(0.);
+        assert!(is_on(&entities, a));
+        assert!(!is_on(&entities, b));
+        let caller = entities.events().trace().back().unwrap().caller;
+        assert_eq!(caller, Some(relay));
+    }
+
+    #[test]
+    fn unknown_targets_and_inputs_reach_nobody() {
+        let mut entities = Entities::new();
+        lamp(&mut entities, "lamp");
+        entities.fire_input("nobody", "TurnOff", 0.);
+        entities.fire_input("lamp", "Explode", 0.);
+        entities.tick(0.);
+        let receivers: Vec<_> = entities
+            .events()
+            .trace()
+            .iter()
+            .map(|entry| entry.receivers)
+            .collect();
+        assert_eq!(receivers, [0, 0]);
+    }
+
+    #[test]
+    fn kill_despawns() {
+        let mut entities = Entities::new();
+        let first = lamp(&mut entities, "lamp");
+        let second = lamp(&mut entities, "lamp");
+        let other = lamp(&mut entities, "other");
+        entities.fire_input("lamp", KILL_INPUT, 0.);
+        entities.tick(0.);
+        assert!(!entities.contains(first));
+        assert!(!entities.contains(second));
+        assert!(entities.contains(other));
+        assert_eq!(entities.events().trace()[0].receivers, 2);
+    }
+
+    #[test]
+    fn zero_delay_loops_are_cut_off() {
+        let mut entities = Entities::new();
+        spawn(
+            &mut entities,
+            &[
+                ("classname", "logic_relay"),
+                ("targetname", "loop"),
+                ("output", "OnTrigger -> loop.Trigger"),
+            ],
+        );
+        entities.fire_input("loop", "Trigger", 0.);
+        entities.tick(0.);
+        // each delivery queues the next, so one is always left over
+        assert_eq!(entities.events().pending().len(), 1);
+        assert_eq!(
+            entities.events().trace().back().unwrap().seq,
+            MAX_EVENTS_PER_TICK as u64 - 1
+        );
+        entities.tick(0.);
+        assert_eq!(
+            entities.events().trace().back().unwrap().seq,
+            2 * MAX_EVENTS_PER_TICK as u64 - 1
+        );
+        assert_eq!(entities.events().trace().len(), TRACE_CAPACITY);
+    }
+}
@@ -1,29 +1,78 @@
 #![crate_type = "rlib"]
 #![allow(dead_code, clippy::let_and_return)]
 
+pub mod bounds;
 pub mod brush;
+pub mod brush_entity;
+pub mod class;
+pub mod component;
 pub mod entity;
+pub mod io;
+pub mod query;
+pub mod storage;
+pub mod systems;
 pub mod vertex;
 
+pub use bounds::Aabb;
 pub use vertex::Vertex;
 
 use brush::Brush;
-use entity::Entity;
+use entity::{Entities, EntityId};
 
-pub struct World<'a> {
-    entities: Vec<&'a dyn Entity>,
+pub struct World {
+    /// Everything that isn't a static brush.
+    entities: Entities,
     brushes: Vec<Box<dyn Brush>>,
+    /// Bounds of each brush in `brushes`, worked out when it's added.
+    brush_bounds: Vec<Option<Aabb>>,
 }
 
-impl World<'_> {
+impl World {
     pub fn new() -> Self {
         World {
-            entities: vec![],
+            entities: Entities::new(),
             brushes: vec![],
+            brush_bounds: vec![],
         }
     }
     // Add a brush to this world.
     pub fn add_brush(&mut self, brush: Box<dyn Brush>) {
+        self.brush_bounds.push(brush.bounds());
         self.brushes.push(brush);
     }
+    /// All the static brushes in this world.
+    pub fn brushes(&self) -> &[Box<dyn Brush>] {
+        &self.brushes
+    }
+    /// The bounding box of each brush, in the same order as [`World::brushes`].
+    /// [`None`] for brushes without any vertices.
+    pub fn brush_bounds(&self) -> &[Option<Aabb>] {
+        &self.brush_bounds
+    }
+
+    /// Add an entity with no components to this world, and return its id.
+    /// While scripts are ticking, use [`Commands::spawn`](entity::Commands::spawn) instead.
+    pub fn spawn(&mut self) -> EntityId {
+        self.entities.spawn()
+    }
+    /// Remove the entity with `id`, and everything it has.
+    /// Returns false if it was already gone.
+    pub fn despawn(&mut self, id: EntityId) -> bool {
+        self.entities.despawn(id)
+    }
+    /// All the entities in this world.
+    pub fn entities(&self) -> &Entities {
+        &self.entities
+    }
+    /// All the entities in this world.
+    pub fn entities_mut(&mut self) -> &mut Entities {
+        &mut self.entities
+    }
+    /// Tick every script by `dt` seconds (see [`Entities::tick`]), move brush entities,
+    /// then move everything with a velocity.
+    pub fn tick(&mut self, dt: f64) {
+        self.entities.tick(dt);
+        systems::move_brush_entities(&mut self.entities, &self.brush_bounds, dt);
+        systems::integrate_velocity(self.entities.components_mut(), dt);
+    }
 }
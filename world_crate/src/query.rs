@@ -0,0 +1,361 @@
+//! Exports [`Fetch`], what a query asks each entity for, and [`QueryIter`], which walks them.
+//!
+//! A query is a [`Fetch`], or a tuple of up to 6:
+//! - `&T` and `&mut T` match entities with a `T`.
+//! - `Option<&T>` matches every entity, with its `T` if it has one.
+//! - [`EntityId`] matches every entity, giving its id.
+//!
+//! ```
+//! # use world::component::{Components, Transform, Velocity};
+//! # let mut components = Components::default();
+//! for (transform, velocity) in components.query_mut::<(&mut Transform, &Velocity)>() {
+//!     transform.position = transform.position + velocity.linear;
+//! }
+//! ```
+//!
+//! A query walks the ids of whichever component it asks for has the fewest entities,
+//! and looks the others up for each.
+use std::{any::TypeId, marker::PhantomData};
+
+use crate::{
+    component::{Component, Components, type_key},
+    entity::EntityId,
+    storage::RawSparseSet,
+};
+
+/// Something a query can ask each entity for.
+///
+/// # Safety
+/// [`access`](Fetch::access) must list every component type [`fetch`](Fetch::fetch) reads,
+/// and say if it writes it.
+pub unsafe trait Fetch {
+    /// What the query gives for each entity.
+    type Item<'w>;
+    /// Pointers into the storage it reads, made once per query.
+    type State: Copy;
+
+    /// Add the component types this reads to `access`, with whether it writes them.
+    fn access(access: &mut Vec<(TypeId, &'static str, bool)>);
+    /// Get pointers into `components`.
+    ///
+    /// # Safety
+    /// `components` must be valid, and only read from unless this writes.
+    unsafe fn prepare(components: *mut Components) -> Self::State;
+    /// The entities this can match, if it narrows them down.
+    ///
+    /// # Safety
+    /// The storage `state` points into must be unchanged, and outlive `'w`.
+    unsafe fn candidates<'w>(state: Self::State) -> Option<&'w [EntityId]>;
+    /// What to give for the entity with `id`, or [`None`] if it doesn't match.
+    ///
+    /// # Safety
+    /// The storage `state` points into must be unchanged, and outlive `'w`.
+    /// Nothing else may be borrowing what this writes, and it must be called once per entity.
+    unsafe fn fetch<'w>(state: Self::State, id: EntityId) -> Option<Self::Item<'w>>;
+}
+
+/// A [`Fetch`] that only reads, so can be used with [`Components::query`].
+///
+/// # Safety
+/// The fetch must not write anything.
+pub unsafe trait ReadOnlyFetch: Fetch {}
+
+unsafe impl<T: Component> Fetch for &T {
+    type Item<'w> = &'w T;
+    type State = RawSparseSet<T>;
+
+    fn access(access: &mut Vec<(TypeId, &'static str, bool)>) {
+        let (type_id, name) = type_key::<T>();
+        access.push((type_id, name, false));
+    }
+    unsafe fn prepare(components: *mut Components) -> Self::State {
+        unsafe { T::storage(&*components).raw_shared() }
+    }
+    unsafe fn candidates<'w>(state: Self::State) -> Option<&'w [EntityId]> {
+        Some(unsafe { state.ids() })
+    }
+    unsafe fn fetch<'w>(state: Self::State, id: EntityId) -> Option<Self::Item<'w>> {
+        unsafe { state.find(id).map(|value| &*value) }
+    }
+}
+unsafe impl<T: Component> ReadOnlyFetch for &T {}
+
+unsafe impl<T: Component> Fetch for &mut T {
+    type Item<'w> = &'w mut T;
+    type State = RawSparseSet<T>;
+
+    fn access(access: &mut Vec<(TypeId, &'static str, bool)>) {
+        let (type_id, name) = type_key::<T>();
+        access.push((type_id, name, true));
+    }
+    unsafe fn prepare(components: *mut Components) -> Self::State {
+        unsafe { T::storage_mut(&mut *components).raw() }
+    }
+    unsafe fn candidates<'w>(state: Self::State) -> Option<&'w [EntityId]> {
+        Some(unsafe { state.ids() })
+    }
+    unsafe fn fetch<'w>(state: Self::State, id: EntityId) -> Option<Self::Item<'w>> {
+        unsafe { state.find(id).map(|value| &mut *value) }
+    }
+}
+
+unsafe impl<T: Component> Fetch for Option<&T> {
+    type Item<'w> = Option<&'w T>;
+    type State = RawSparseSet<T>;
+
+    fn access(access: &mut Vec<(TypeId, &'static str, bool)>) {
+        <&T>::access(access);
+    }
+    unsafe fn prepare(components: *mut Components) -> Self::State {
+        unsafe { <&T>::prepare(components) }
+    }
+    unsafe fn candidates<'w>(_: Self::State) -> Option<&'w [EntityId]> {
+        None
+    }
+    unsafe fn fetch<'w>(state: Self::State, id: EntityId) -> Option<Self::Item<'w>> {
+        Some(unsafe { <&T>::fetch(state, id) })
+    }
+}
+unsafe impl<T: Component> ReadOnlyFetch for Option<&T> {}
+
+unsafe impl Fetch for EntityId {
+    type Item<'w> = EntityId;
+    type State = ();
+
+    fn access(_: &mut Vec<(TypeId, &'static str, bool)>) {}
+    unsafe fn prepare(_: *mut Components) -> Self::State {}
+    unsafe fn candidates<'w>(_: Self::State) -> Option<&'w [EntityId]> {
+        None
+    }
+    unsafe fn fetch<'w>(_: Self::State, id: EntityId) -> Option<Self::Item<'w>> {
+        Some(id)
+    }
+}
+unsafe impl ReadOnlyFetch for EntityId {}
+
+/// Implements [`Fetch`] for a tuple of fetches, matching entities every one of them matches.
+macro_rules! tuple_fetch {
+    ($($fetch:ident),+) => {
+        #[allow(non_snake_case)]
+        unsafe impl<$($fetch: Fetch),+> Fetch for ($($fetch,)+) {
+            type Item<'w> = ($($fetch::Item<'w>,)+);
+            type State = ($($fetch::State,)+);
+
+            fn access(access: &mut Vec<(TypeId, &'static str, bool)>) {
+                $($fetch::access(access);)+
+            }
+            unsafe fn prepare(components: *mut Components) -> Self::State {
+                unsafe { ($($fetch::prepare(components),)+) }
+            }
+            unsafe fn candidates<'w>(state: Self::State) -> Option<&'w [EntityId]> {
+                let ($($fetch,)+) = state;
+                let mut fewest: Option<&'w [EntityId]> = None;
+                $(
+                    if let Some(ids) = unsafe { $fetch::candidates($fetch) }
+                        && fewest.is_none_or(|fewest| ids.len() < fewest.len())
+                    {
+                        fewest = Some(ids);
+                    }
+                )+
+                fewest
+            }
+            unsafe fn fetch<'w>(state: Self::State, id: EntityId) -> Option<Self::Item<'w>> {
+                let ($($fetch,)+) = state;
+                Some(($(unsafe { $fetch::fetch($fetch, id)? },)+))
+            }
+        }
+        unsafe impl<$($fetch: ReadOnlyFetch),+> ReadOnlyFetch for ($($fetch,)+) {}
+    };
+}
+
+tuple_fetch!(A);
+tuple_fetch!(A, B);
+tuple_fetch!(A, B, C);
+tuple_fetch!(A, B, C, D);
+tuple_fetch!(A, B, C, D, E);
+tuple_fetch!(A, B, C, D, E, F);
+
+/// Walks the entities that match `Q`. Made by [`Components::query`] and [`Components::query_mut`].
+pub struct QueryIter<'w, Q: Fetch> {
+    /// Pointers into the storage `Q` reads.
+    state: Q::State,
+    /// Entities that might match.
+    candidates: std::slice::Iter<'w, EntityId>,
+    /// The query borrows the components for as long as it lives.
+    _components: PhantomData<&'w mut Components>,
+}
+
+impl<'w, Q: Fetch> QueryIter<'w, Q> {
+    /// Start a query of `components`.
+    ///
+    /// # Safety
+    /// `components` must be borrowed for `'w`, mutably if `Q` writes, and no component type
+    /// `Q` writes can be one it also reads.
+    pub(crate) unsafe fn new(components: *mut Components) -> Self {
+        let state = unsafe { Q::prepare(components) };
+        let candidates = unsafe { Q::candidates(state) }
+            .unwrap_or_else(|| unsafe { (*components).living.raw_shared().ids() });
+        Self {
+            state,
+            candidates: candidates.iter(),
+            _components: PhantomData,
+        }
+    }
+}
+
+impl<'w, Q: Fetch> Iterator for QueryIter<'w, Q> {
+    type Item = Q::Item<'w>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for id in self.candidates.by_ref() {
+            // SAFETY: each id comes up once, and the storage is borrowed for 'w
+            if let Some(item) = unsafe { Q::fetch(self.state, *id) } {
+                return Some(item);
+            }
+        }
+        None
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some(self.candidates.len()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        component::{Components, Health, Transform, Velocity},
+        entity::{Entities, EntityId},
+    };
+
+    /// Entities with transforms at x = 0..5, velocities on the even ones,
+    /// and health on 4 only.
+    fn entities() -> (Entities, Vec<EntityId>) {
+        let mut entities = Entities::new();
+        let ids: Vec<_> = (0..5).map(|_| entities.spawn()).collect();
+        for (x, id) in ids.iter().enumerate() {
+            entities.insert(*id, Transform::from_position(glm::vec3(x as f32, 0., 0.)));
+            if x % 2 == 0 {
+                entities.insert(
+                    *id,
+                    Velocity {
+                        linear: glm::vec3(1., 0., 0.),
+                        ..Velocity::default()
+                    },
+                );
+            }
+        }
+        entities.insert(ids[4], Health::new(10.));
+        (entities, ids)
+    }
+
+    fn sorted(mut ids: Vec<EntityId>) -> Vec<EntityId> {
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn queries_match_entities_with_everything() {
+        let (entities, ids) = entities();
+        let moving = entities.query::<(EntityId, &Transform, &Velocity)>();
+        assert_eq!(moving.size_hint(), (0, Some(3)));
+        let moving: Vec<_> = moving.map(|(id, _, _)| id).collect();
+        assert_eq!(sorted(moving), [ids[0], ids[2], ids[4]]);
+
+        // the smallest set is walked, whatever order the query lists them in
+        let healthy: Vec<_> = entities
+            .query::<(&Transform, &Velocity, &Health, EntityId)>()
+            .map(|(transform, _, health, id)| (id, transform.position.x, health.current))
+            .collect();
+        assert_eq!(healthy, [(ids[4], 4., 10.)]);
+        assert_eq!(
+            entities
+                .query::<(&Health, &Transform)>()
+                .size_hint(),
+            (0, Some(1))
+        );
+    }
+
+    #[test]
+    fn optional_components() {
+        let (entities, ids) = entities();
+        let mut found: Vec<_> = entities
+            .query::<(EntityId, Option<&Velocity>)>()
+            .map(|(id, velocity)| (id, velocity.is_some()))
+            .collect();
+        found.sort();
+        assert_eq!(
+            found,
+            [
+                (ids[0], true),
+                (ids[1], false),
+                (ids[2], true),
+                (ids[3], false),
+                (ids[4], true),
+            ]
+        );
+        assert_eq!(entities.query::<EntityId>().count(), 5);
+    }
+
+    #[test]
+    fn mutable_queries_write_through() {
+        let (mut entities, ids) = entities();
+        for (transform, velocity) in entities.query_mut::<(&mut Transform, &Velocity)>() {
+            transform.position = transform.position + velocity.linear;
+        }
+        for (transform, health) in entities.query_mut::<(&mut Transform, &mut Health)>() {
+            transform.position.y = 1.;
+            health.damage(5.);
+        }
+        let xs: Vec<_> = ids
+            .iter()
+            .map(|id| entities.get::<Transform>(*id).unwrap().position.x)
+            .collect();
+        assert_eq!(xs, [1., 1., 3., 3., 5.]);
+        assert_eq!(entities.get::<Transform>(ids[4]).unwrap().position.y, 1.);
+        assert_eq!(entities.get::<Health>(ids[4]).unwrap().current, 5.);
+    }
+
+    #[test]
+    fn despawned_entities_dont_match() {
+        let (mut entities, ids) = entities();
+        entities.despawn(ids[2]);
+        let reused = entities.spawn();
+        assert_eq!(reused.index(), ids[2].index());
+        let moving: Vec<_> = entities
+            .query::<(EntityId, &Velocity)>()
+            .map(|(id, _)| id)
+            .collect();
+        assert_eq!(sorted(moving), [ids[0], ids[4]]);
+        assert!(!entities.query::<EntityId>().any(|id| id == ids[2]));
+    }
+
+    #[test]
+    fn empty_queries() {
+        let mut components = Components::default();
+        assert_eq!(components.query::<&Transform>().count(), 0);
+        assert_eq!(components.query_mut::<(&mut Transform, EntityId)>().count(), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "twice, and writes it")]
+    fn writing_a_component_twice_panics() {
+        let (mut entities, _) = entities();
+        entities.query_mut::<(&mut Transform, &Transform)>();
+    }
+
+    #[test]
+    #[should_panic(expected = "twice, and writes it")]
+    fn optional_reads_of_written_components_panic() {
+        let (mut entities, _) = entities();
+        entities.query_mut::<(Option<&Velocity>, &mut Velocity)>();
+    }
+
+    #[test]
+    fn reading_a_component_twice_is_fine() {
+        let (mut entities, _) = entities();
+        let count = entities
+            .query_mut::<(&Transform, Option<&Transform>, &mut Velocity)>()
+            .count();
+        assert_eq!(count, 3);
+    }
+}
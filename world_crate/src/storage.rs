@@ -0,0 +1,318 @@
+//! Exports [`SparseSet`], which stores one component type for many entities.
+use crate::entity::EntityId;
+
+/// Marks an empty slot in [`SparseSet::sparse`].
+const EMPTY: u32 = u32::MAX;
+
+/// Values for some entities, packed together so iterating them is a walk through memory.
+///
+/// `sparse` maps an entity's slot index to where its value is in `values`.
+/// Removing swaps the last value into the hole, so `values` never has gaps.
+#[derive(Clone, Debug)]
+pub struct SparseSet<T> {
+    /// Index into `ids` and `values` for each slot index, or [`EMPTY`].
+    sparse: Vec<u32>,
+    /// The entity each value belongs to.
+    ids: Vec<EntityId>,
+    /// The values, in no particular order.
+    values: Vec<T>,
+}
+
+impl<T> Default for SparseSet<T> {
+    fn default() -> Self {
+        Self {
+            sparse: vec![],
+            ids: vec![],
+            values: vec![],
+        }
+    }
+}
+
+impl<T> SparseSet<T> {
+    /// Create an empty set.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Where the value for `id` is in `values`, if there is one.
+    fn dense_index(&self, id: EntityId) -> Option<usize> {
+        let dense = *self.sparse.get(id.index() as usize)?;
+        if dense == EMPTY || self.ids[dense as usize] != id {
+            return None;
+        }
+        Some(dense as usize)
+    }
+
+    /// Set the value for `id`, returning the old one.
+    pub fn insert(&mut self, id: EntityId, value: T) -> Option<T> {
+        if let Some(dense) = self.dense_index(id) {
+            return Some(std::mem::replace(&mut self.values[dense], value));
+        }
+        let index = id.index() as usize;
+        if self.sparse.len() <= index {
+            self.sparse.resize(index + 1, EMPTY);
+        }
+        let stale = self.sparse[index];
+        if stale != EMPTY {
+            // left by an older entity in the same slot
+            self.remove(self.ids[stale as usize]);
+        }
+        self.sparse[index] = u32::try_from(self.values.len()).expect("too many components");
+        self.ids.push(id);
+        self.values.push(value);
+        None
+    }
+    /// Remove the value for `id`, returning it.
+    pub fn remove(&mut self, id: EntityId) -> Option<T> {
+        let dense = self.dense_index(id)?;
+        self.sparse[id.index() as usize] = EMPTY;
+        self.ids.swap_remove(dense);
+        let value = self.values.swap_remove(dense);
+        if let Some(moved) = self.ids.get(dense) {
+            self.sparse[moved.index() as usize] = dense as u32;
+        }
+        Some(value)
+    }
+
+    /// Whether `id` has a value.
+    pub fn contains(&self, id: EntityId) -> bool {
+        self.dense_index(id).is_some()
+    }
+    /// The value for `id`, if it has one.
+    pub fn get(&self, id: EntityId) -> Option<&T> {
+        self.dense_index(id).map(|dense| &self.values[dense])
+    }
+    /// The value for `id`, if it has one.
+    pub fn get_mut(&mut self, id: EntityId) -> Option<&mut T> {
+        self.dense_index(id).map(|dense| &mut self.values[dense])
+    }
+
+    /// Number of values.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+    /// Whether there are no values.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+    /// The entity each value belongs to, in the same order as [`SparseSet::values`].
+    pub fn ids(&self) -> &[EntityId] {
+        &self.ids
+    }
+    /// Every value, in no particular order.
+    pub fn values(&self) -> &[T] {
+        &self.values
+    }
+    /// Every value with its entity.
+    pub fn iter(&self) -> impl Iterator<Item = (EntityId, &T)> {
+        self.ids.iter().copied().zip(&self.values)
+    }
+    /// Every value with its entity.
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (EntityId, &mut T)> {
+        self.ids.iter().copied().zip(&mut self.values)
+    }
+
+    /// Raw pointers into the set, for queries that need several sets at once.
+    pub(crate) fn raw(&mut self) -> RawSparseSet<T> {
+        RawSparseSet {
+            sparse: self.sparse.as_ptr(),
+            sparse_len: self.sparse.len(),
+            ids: self.ids.as_ptr(),
+            values: self.values.as_mut_ptr(),
+            len: self.values.len(),
+        }
+    }
+    /// Raw pointers into the set, which must only be read through.
+    pub(crate) fn raw_shared(&self) -> RawSparseSet<T> {
+        RawSparseSet {
+            sparse: self.sparse.as_ptr(),
+            sparse_len: self.sparse.len(),
+            ids: self.ids.as_ptr(),
+            values: self.values.as_ptr().cast_mut(),
+            len: self.values.len(),
+        }
+    }
+}
+
+/// Pointers into the buffers of a [`SparseSet`], which stay valid until it's next changed.
+///
+/// Queries hold one for each set they read, so they can hand out references into
+/// several sets, and several mutable references into one, without borrowing the sets.
+pub struct RawSparseSet<T> {
+    /// [`SparseSet::sparse`].
+    sparse: *const u32,
+    /// Length of `sparse`.
+    sparse_len: usize,
+    /// [`SparseSet::ids`].
+    ids: *const EntityId,
+    /// [`SparseSet::values`].
+    values: *mut T,
+    /// Length of `ids` and `values`.
+    len: usize,
+}
+
+impl<T> Clone for RawSparseSet<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+impl<T> Copy for RawSparseSet<T> {}
+
+impl<T> RawSparseSet<T> {
+    /// The entity each value belongs to.
+    ///
+    /// # Safety
+    /// The set must not have changed since this was made, and must outlive `'a`.
+    pub(crate) unsafe fn ids<'a>(self) -> &'a [EntityId] {
+        unsafe { std::slice::from_raw_parts(self.ids, self.len) }
+    }
+    /// Pointer to the value for `id`, if it has one.
+    ///
+    /// # Safety
+    /// The set must not have changed since this was made.
+    pub(crate) unsafe fn find(self, id: EntityId) -> Option<*mut T> {
+        let index = id.index() as usize;
+        if index >= self.sparse_len {
+            return None;
+        }
+        let dense = unsafe { *self.sparse.add(index) };
+        if dense == EMPTY || unsafe { *self.ids.add(dense as usize) } != id {
+            return None;
+        }
+        Some(unsafe { self.values.add(dense as usize) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entity::Entities;
+
+    /// `count` fresh ids, in slots 0 to `count - 1`.
+    fn spawn(entities: &mut Entities, count: usize) -> Vec<EntityId> {
+        (0..count).map(|_| entities.spawn()).collect()
+    }
+
+    #[test]
+    fn insert_get_replace() {
+        let mut entities = Entities::new();
+        let ids = spawn(&mut entities, 3);
+        let mut set = SparseSet::new();
+        assert_eq!(set.insert(ids[2], "c"), None);
+        assert_eq!(set.insert(ids[0], "a"), None);
+        assert_eq!(set.insert(ids[0], "A"), Some("a"));
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.get(ids[0]), Some(&"A"));
+        assert_eq!(set.get(ids[1]), None);
+        *set.get_mut(ids[2]).unwrap() = "C";
+        assert_eq!(set.ids(), [ids[2], ids[0]]);
+        assert_eq!(set.values(), ["C", "A"]);
+    }
+
+    #[test]
+    fn remove_swaps_the_last_value_in() {
+        let mut entities = Entities::new();
+        let ids = spawn(&mut entities, 4);
+        let mut set = SparseSet::new();
+        for (index, id) in ids.iter().enumerate() {
+            set.insert(*id, index);
+        }
+        assert_eq!(set.remove(ids[1]), Some(1));
+        assert_eq!(set.remove(ids[1]), None);
+        assert_eq!(set.ids(), [ids[0], ids[3], ids[2]]);
+        // the moved value is still found through its id
+        assert_eq!(set.get(ids[3]), Some(&3));
+        assert_eq!(set.remove(ids[2]), Some(2));
+        assert_eq!(set.remove(ids[3]), Some(3));
+        assert_eq!(set.remove(ids[0]), Some(0));
+        assert!(set.is_empty());
+        assert!(ids.iter().all(|id| !set.contains(*id)));
+    }
+
+    #[test]
+    fn reused_slots_dont_see_old_values() {
+        let mut entities = Entities::new();
+        let [old, other] = spawn(&mut entities, 2)[..] else {
+            unreachable!()
+        };
+        let mut set = SparseSet::new();
+        set.insert(old, "old");
+        set.insert(other, "other");
+        entities.despawn(old);
+        let new = entities.spawn();
+        assert_eq!(new.index(), old.index());
+
+        assert!(!set.contains(new));
+        assert_eq!(set.remove(new), None);
+        // inserting for the new entity evicts the old one's value
+        assert_eq!(set.insert(new, "new"), None);
+        assert_eq!(set.len(), 2);
+        assert_eq!(set.get(old), None);
+        assert_eq!(set.get(new), Some(&"new"));
+        assert_eq!(set.get(other), Some(&"other"));
+
+        // and the old id can't remove it
+        assert_eq!(set.remove(old), None);
+        assert_eq!(set.remove(new), Some("new"));
+        assert_eq!(set.ids(), [other]);
+    }
+
+    #[test]
+    fn iter_mut_pairs_ids_and_values() {
+        let mut entities = Entities::new();
+        let ids = spawn(&mut entities, 3);
+        let mut set = SparseSet::new();
+        for id in &ids {
+            set.insert(*id, id.index());
+        }
+        for (id, value) in set.iter_mut() {
+            *value += id.index() * 10;
+        }
+        let values: Vec<_> = set.iter().map(|(id, value)| (id, *value)).collect();
+        assert_eq!(values, [(ids[0], 0), (ids[1], 11), (ids[2], 22)]);
+    }
+
+    #[test]
+    fn raw_sets_find_values() {
+        let mut entities = Entities::new();
+        let ids = spawn(&mut entities, 3);
+        // an id past the end of the set's sparse array
+        let far = *spawn(&mut entities, 10).last().unwrap();
+        let mut set = SparseSet::new();
+        set.insert(ids[1], 1);
+        set.insert(ids[0], 0);
+
+        let raw = set.raw();
+        // SAFETY: the set doesn't change while `raw` is used
+        unsafe {
+            assert_eq!(raw.ids(), [ids[1], ids[0]]);
+            assert_eq!(raw.find(ids[2]), None);
+            assert_eq!(raw.find(far), None);
+            *raw.find(ids[0]).unwrap() += 5;
+            *raw.find(ids[1]).unwrap() += 5;
+        }
+        assert_eq!(set.values(), [6, 5]);
+
+        let raw = set.raw_shared();
+        // SAFETY: as above, and only read through
+        unsafe {
+            assert_eq!(*raw.find(ids[1]).unwrap(), 6);
+        }
+    }
+
+    #[test]
+    fn raw_sets_check_generations() {
+        let mut entities = Entities::new();
+        let old = entities.spawn();
+        let mut set = SparseSet::new();
+        set.insert(old, ());
+        entities.despawn(old);
+        let new = entities.spawn();
+        let raw = set.raw_shared();
+        // SAFETY: the set doesn't change while `raw` is used
+        unsafe {
+            assert!(raw.find(old).is_some());
+            assert!(raw.find(new).is_none());
+        }
+    }
+}
@@ -0,0 +1,30 @@
+//! Systems: functions run on every entity with some set of components, each tick.
+use crate::{
+    bounds::Aabb,
+    brush_entity::{self, Mover, Rotator},
+    component::{Components, Transform, Velocity},
+    entity::Entities,
+};
+
+/// Move and turn everything with a [`Velocity`] by `dt` seconds of it.
+pub fn integrate_velocity(components: &mut Components, dt: f64) {
+    let dt = dt as f32;
+    for (transform, velocity) in components.query_mut::<(&mut Transform, &Velocity)>() {
+        transform.position = transform.position + velocity.linear * dt;
+        transform.rotation = transform.rotation + velocity.angular * dt;
+    }
+}
+
+/// Move every [`Mover`] and turn every [`Rotator`] by `dt` seconds, pushing or being blocked
+/// by what's in their way. `static_bounds` are the bounds of the world's static brushes.
+/// See [`brush_entity`].
+pub fn move_brush_entities(entities: &mut Entities, static_bounds: &[Option<Aabb>], dt: f64) {
+    let movers = entities.components().all::<Mover>().ids().to_vec();
+    for id in movers {
+        brush_entity::move_mover(entities, id, static_bounds, dt);
+    }
+    let rotators = entities.components().all::<Rotator>().ids().to_vec();
+    for id in rotators {
+        brush_entity::turn_rotator(entities, id, dt);
+    }
+}